@@ -0,0 +1,498 @@
+pub mod damage;
+
+use rand::Rng;
+
+use damage::Formula;
+use crate::map::item::{Effect, Item};
+use crate::map::monster::Monster;
+use crate::map::status::StatusKind;
+use crate::map::{Map, Position};
+use crate::player::{Player, UseError};
+
+/// Rounds after which an encounter nobody is winning is broken off.
+pub const MAX_ROUNDS: u32 = 100;
+
+/// One of the two sides of an encounter.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Side {
+    Player,
+    Monster,
+}
+
+/// What the player does in a round.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Action {
+    Attack,
+    /// Take half damage from the monster this round.
+    Defend,
+    /// Use the item at this index of the inventory.
+    Use(usize),
+    Flee,
+}
+
+/// Something that happened during a round.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Entry {
+    /// `attacker` dealt `damage`, leaving the other side with `life`.
+    Hit { attacker: Side, damage: i32, life: i32, critical: bool },
+    Defend,
+    Use(String),
+    /// The player could not use an item.
+    CannotUse(UseError),
+    Flee { success: bool },
+    /// This side was stunned and lost its action.
+    Stunned(Side),
+    /// A weapon put `status` on `target`.
+    Afflict { target: Side, status: StatusKind },
+    /// `target`'s statuses changed its life by `change`, leaving `life`.
+    Suffer { target: Side, change: i32, life: i32 },
+    /// `status` wore off `target`.
+    WearOff { target: Side, status: StatusKind },
+}
+
+/// How an encounter ended.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Ending {
+    Victory,
+    Defeat,
+    Fled,
+    /// `MAX_ROUNDS` went by without a winner.
+    Stalemate,
+}
+
+/// Everything that happened in an encounter, round by round.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CombatLog {
+    pub monster: String,
+    pub initiative: Side,
+    pub rounds: Vec<Vec<Entry>>,
+    pub ending: Ending,
+    /// What the monster dropped, when it was defeated.
+    pub loot: Option<(Item, u32)>,
+    /// Whether the loot was left behind, the player's bag being full.
+    pub bag_full: bool,
+}
+
+impl CombatLog {
+    fn new(monster: &Monster, initiative: Side) -> CombatLog {
+        CombatLog {
+            monster: monster.name().to_string(),
+            initiative,
+            rounds: vec![],
+            ending: Ending::Stalemate,
+            loot: None,
+            bag_full: false,
+        }
+    }
+
+    /// One sentence for each entry of a round, as told to the player.
+    pub fn describe(&self, round: usize) -> Vec<String> {
+        let entries = self.rounds.get(round).map_or(&[][..], Vec::as_slice);
+        entries.iter().map(|entry| match entry {
+            Entry::Hit { attacker: Side::Player, damage, life, critical: false } => {
+                format!("You hit the {} for {} ({} life left).", self.monster, damage, life.max(&0))
+            },
+            Entry::Hit { attacker: Side::Player, damage, life, critical: true } => {
+                format!("A critical hit! You strike the {} for {} ({} life left).", self.monster, damage, life.max(&0))
+            },
+            Entry::Hit { attacker: Side::Monster, damage, life, critical: false } => {
+                format!("The {} hits you for {} ({} life left).", self.monster, damage, life.max(&0))
+            },
+            Entry::Hit { attacker: Side::Monster, damage, life, critical: true } => {
+                format!("A critical hit! The {} strikes you for {} ({} life left).", self.monster, damage, life.max(&0))
+            },
+            Entry::Defend => "You raise your guard.".to_string(),
+            Entry::Use(item) => format!("You use the {}.", item),
+            Entry::CannotUse(UseError::NoItem(index)) => format!("You fumble for item {}.", index + 1),
+            Entry::CannotUse(error) => error.to_string(),
+            Entry::Flee { success: true } => format!("You get away from the {}.", self.monster),
+            Entry::Flee { success: false } => format!("The {} blocks your escape.", self.monster),
+            Entry::Stunned(Side::Player) => "You are stunned and cannot act.".to_string(),
+            Entry::Stunned(Side::Monster) => format!("The {} is stunned and cannot act.", self.monster),
+            Entry::Afflict { target: Side::Player, status } => format!("You are now {}.", status.adjective()),
+            Entry::Afflict { target: Side::Monster, status } => format!("The {} is now {}.", self.monster, status.adjective()),
+            Entry::Suffer { target: Side::Player, change, life } if *change < 0 => format!("You lose {} life ({} life left).", -change, life.max(&0)),
+            Entry::Suffer { target: Side::Player, change, life } => format!("You recover {} life ({} life left).", change, life),
+            Entry::Suffer { target: Side::Monster, change, life } if *change < 0 => format!("The {} loses {} life ({} life left).", self.monster, -change, life.max(&0)),
+            Entry::Suffer { target: Side::Monster, change, life } => format!("The {} recovers {} life ({} life left).", self.monster, change, life),
+            Entry::WearOff { target: Side::Player, status } => format!("You are no longer {}.", status.adjective()),
+            Entry::WearOff { target: Side::Monster, status } => format!("The {} is no longer {}.", self.monster, status.adjective()),
+        }).collect()
+    }
+}
+
+/// Chooses the player's action at the start of every round.
+pub trait Strategy {
+    fn choose(&mut self, log: &CombatLog, player: &Player, monster: &Monster) -> Action;
+}
+
+impl<F: FnMut(&CombatLog, &Player, &Monster) -> Action> Strategy for F {
+    fn choose(&mut self, log: &CombatLog, player: &Player, monster: &Monster) -> Action {
+        self(log, player, monster)
+    }
+}
+
+/// Attacks every round, drinking the first healing item at a quarter of
+/// the maximum life.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Aggressive;
+
+impl Strategy for Aggressive {
+    fn choose(&mut self, _: &CombatLog, player: &Player, _: &Monster) -> Action {
+        if player.life() * 4 <= player.max_life() {
+            let heal = player.inventory().iter().position(|stack| matches!(stack.item().use_item(), Some(Effect::Heal(_))));
+            if let Some(index) = heal {
+                return Action::Use(index);
+            }
+        }
+        Action::Attack
+    }
+}
+
+/// The monster's blow at the player, halved when the player defends and
+/// when the monster is blind. A stunned monster strikes nothing.
+fn strike(player: &mut Player, monster: &Monster, formula: &Formula, defending: bool, entries: &mut Vec<Entry>, rng: &mut impl Rng) {
+    if monster.statuses().has(StatusKind::Stun) {
+        entries.push(Entry::Stunned(Side::Monster));
+        return;
+    }
+    let mut blow = formula.roll(monster.attack(), player.defense(), rng);
+    if defending {
+        blow.damage /= 2;
+    }
+    if monster.statuses().has(StatusKind::Blindness) {
+        blow.damage /= 2;
+    }
+    player.take_damage(blow.damage);
+    entries.push(Entry::Hit { attacker: Side::Monster, damage: blow.damage, life: player.life(), critical: blow.critical });
+    if let Some(status) = monster.weapon().inflicts().filter(|_| blow.damage > 0) {
+        player.add_status(*status);
+        entries.push(Entry::Afflict { target: Side::Player, status: status.kind });
+    }
+}
+
+/// The player's blow at the monster, halved when the player is blind.
+fn hit(player: &Player, monster: &mut Monster, formula: &Formula, entries: &mut Vec<Entry>, rng: &mut impl Rng) {
+    let mut blow = formula.roll(player.attack(), monster.defense(), rng);
+    if player.statuses().has(StatusKind::Blindness) {
+        blow.damage /= 2;
+    }
+    monster.take_damage(blow.damage);
+    entries.push(Entry::Hit { attacker: Side::Player, damage: blow.damage, life: monster.life(), critical: blow.critical });
+    if let Some(status) = player.weapon().and_then(Item::inflicts).filter(|_| blow.damage > 0) {
+        monster.add_status(*status);
+        entries.push(Entry::Afflict { target: Side::Monster, status: status.kind });
+    }
+}
+
+/// Pass a turn of both sides' statuses, at the end of a round.
+fn tick(player: &mut Player, monster: &mut Monster, entries: &mut Vec<Entry>) {
+    let life = player.life();
+    let expired = player.tick_statuses().expired;
+    if player.life() != life {
+        entries.push(Entry::Suffer { target: Side::Player, change: player.life() - life, life: player.life() });
+    }
+    entries.extend(expired.into_iter().map(|status| Entry::WearOff { target: Side::Player, status }));
+
+    let life = monster.life();
+    let expired = monster.tick_statuses().expired;
+    if monster.life() != life {
+        entries.push(Entry::Suffer { target: Side::Monster, change: monster.life() - life, life: monster.life() });
+    }
+    entries.extend(expired.into_iter().map(|status| Entry::WearOff { target: Side::Monster, status }));
+}
+
+/// How the encounter stands: over once a side falls or the player was
+/// teleported away from `position`.
+fn outcome(player: &Player, monster: &Monster, position: &Position) -> Option<Ending> {
+    if monster.life() <= 0 {
+        Some(Ending::Victory)
+    } else if player.is_dead() {
+        Some(Ending::Defeat)
+    } else if player.position() != position {
+        Some(Ending::Fled)
+    } else {
+        None
+    }
+}
+
+/// Percent chance of getting away from `monster`: 50, plus 10 for every
+/// level of the player past the first, minus the monster's attack and
+/// defense, kept between 10 and 90.
+pub fn flee_chance(player: &Player, monster: &Monster) -> u32 {
+    let chance = 50 + 10 * (player.level() - 1) - monster.attack() - monster.defense();
+    chance.clamp(10, 90) as u32
+}
+
+/// Fight `monster` until one side falls, the player gets away or
+/// `MAX_ROUNDS` pass. Both sides roll a d20 for initiative, the player adding
+/// their level, unless only one of them is hasted, and act in that order
+/// every round, every blow going through `formula`. Fleeing succeeds with
+/// `flee_chance`; a failed attempt lets the monster strike at once, on top of
+/// its usual blow. Statuses tick at the end of every round, and a weapon that
+/// inflicts one puts it on whoever it hurts. A defeated monster's drop goes
+/// to the player, if it fits in their bag. `map` is where items may teleport
+/// the player to, which ends the encounter as if they fled.
+pub fn fight(player: &mut Player, monster: &mut Monster, map: &mut Map, formula: &Formula, strategy: &mut impl Strategy, rng: &mut impl Rng) -> CombatLog {
+    let player_roll = rng.gen_range(1..=20) + player.level();
+    let monster_roll = rng.gen_range(1..=20);
+    let initiative = match (player.statuses().has(StatusKind::Haste), monster.statuses().has(StatusKind::Haste)) {
+        (true, false) => Side::Player,
+        (false, true) => Side::Monster,
+        _ if player_roll >= monster_roll => Side::Player,
+        _ => Side::Monster,
+    };
+    let order = match initiative {
+        Side::Player => [Side::Player, Side::Monster],
+        Side::Monster => [Side::Monster, Side::Player],
+    };
+    let mut log = CombatLog::new(monster, initiative);
+    let position = *player.position();
+
+    for _ in 0..MAX_ROUNDS {
+        // A stunned player has no say in the round.
+        let action = match player.statuses().has(StatusKind::Stun) {
+            true => None,
+            false => Some(strategy.choose(&log, player, monster)),
+        };
+        let mut entries = vec![];
+        let mut ending = None;
+        for side in order {
+            match (side, action) {
+                (Side::Player, None) => entries.push(Entry::Stunned(Side::Player)),
+                (Side::Player, Some(Action::Attack)) => hit(player, monster, formula, &mut entries, rng),
+                (Side::Player, Some(Action::Defend)) => entries.push(Entry::Defend),
+                (Side::Player, Some(Action::Use(index))) => match player.use_item(index, map, Some(monster)) {
+                    Ok(item) => entries.push(Entry::Use(item.name().to_string())),
+                    Err(error) => entries.push(Entry::CannotUse(error)),
+                },
+                (Side::Player, Some(Action::Flee)) => {
+                    let success = rng.gen_range(0..100) < flee_chance(player, monster);
+                    entries.push(Entry::Flee { success });
+                    if success {
+                        ending = Some(Ending::Fled);
+                    } else {
+                        strike(player, monster, formula, false, &mut entries, rng);
+                    }
+                },
+                (Side::Monster, action) => strike(player, monster, formula, action == Some(Action::Defend), &mut entries, rng),
+            }
+
+            ending = outcome(player, monster, &position).or(ending);
+            if ending.is_some() {
+                break;
+            }
+        }
+        if ending.is_none() {
+            tick(player, monster, &mut entries);
+            ending = outcome(player, monster, &position);
+        }
+        log.rounds.push(entries);
+        if let Some(ending) = ending {
+            log.ending = ending;
+            break;
+        }
+    }
+
+    if log.ending == Ending::Victory {
+        if let Some((drop, count)) = monster.drop_mut().take() {
+            log.bag_full = player.add_items(drop.clone(), count).is_err();
+            log.loot = Some((drop, count));
+        }
+    }
+    log
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::map::status::StatusEffect;
+    use crate::player::{equipment::Equipment, inventory::Inventory};
+
+    fn slime(life: i32, damage: i32) -> Monster {
+        Monster::new("slime", life, Item::new_weapon("goo", "", 0, damage), Some(Item::new_exp("experience", "", 10)), 3, 1)
+    }
+
+    fn hero() -> Player {
+        let mut player = Player::new("Hero");
+        player.equip(Item::new_weapon("Sword", "", 0, 10)).unwrap();
+        player
+    }
+
+    #[test]
+    fn test_combat_victory() {
+        let mut player = hero();
+        let mut monster = slime(25, 4);
+        let log = fight(&mut player, &mut monster, &mut Map::new(), &Formula::default(), &mut Aggressive, &mut StdRng::seed_from_u64(1));
+        assert_eq!(log.ending, Ending::Victory);
+        assert_eq!(log.rounds.len(), 3);
+        assert_eq!(log.rounds[2].last(), Some(&Entry::Hit { attacker: Side::Player, damage: 10, life: -5, critical: false }));
+        assert_eq!(log.loot, Some((Item::new_exp("experience", "", 10), 3)));
+        assert_eq!(player.experience(), 30);
+        let hits = log.rounds.iter().flatten().filter(|entry| matches!(entry, Entry::Hit { attacker: Side::Monster, .. })).count();
+        assert_eq!(player.life(), 100 - 4 * hits as i32);
+        assert_eq!(monster.drop(), &None);
+    }
+
+    #[test]
+    fn test_combat_is_deterministic() {
+        let run = |seed| {
+            let mut player = hero();
+            let mut monster = slime(100, 7);
+            let log = fight(&mut player, &mut monster, &mut Map::new(), &Formula::default(), &mut Aggressive, &mut StdRng::seed_from_u64(seed));
+            (log, player.life())
+        };
+        assert_eq!(run(9), run(9));
+        let initiatives: Vec<Side> = (0..20).map(|seed| run(seed).0.initiative).collect();
+        assert!(initiatives.contains(&Side::Player));
+        assert!(initiatives.contains(&Side::Monster));
+    }
+
+    #[test]
+    fn test_combat_defeat() {
+        let mut player = hero();
+        let mut monster = slime(1000, 30);
+        let log = fight(&mut player, &mut monster, &mut Map::new(), &Formula::default(), &mut Aggressive, &mut StdRng::seed_from_u64(2));
+        assert_eq!(log.ending, Ending::Defeat);
+        assert!(player.is_dead());
+        assert_eq!(log.loot, None);
+        assert!(monster.drop().is_some());
+    }
+
+    #[test]
+    fn test_combat_defend_and_use() {
+        let mut player = hero();
+        player.add_item(Item::new_effect("bomb", "", 0, Effect::Damage(50))).unwrap();
+        let mut monster = slime(60, 10);
+        let mut actions = vec![Action::Attack, Action::Use(0), Action::Use(0), Action::Defend];
+        let mut strategy = |_: &CombatLog, _: &Player, _: &Monster| actions.remove(0);
+        let log = fight(&mut player, &mut monster, &mut Map::new(), &Formula::default(), &mut strategy, &mut StdRng::seed_from_u64(3));
+        assert!(log.rounds[0].contains(&Entry::Hit { attacker: Side::Player, damage: 10, life: 50, critical: false }));
+        assert!(log.rounds[1].contains(&Entry::Use("bomb".to_string())));
+        assert_eq!(log.ending, Ending::Victory);
+        assert_eq!(log.rounds.len(), 2);
+        assert!(player.inventory().is_empty());
+    }
+
+    #[test]
+    fn test_combat_flee_and_stalemate() {
+        let mut player = hero();
+        let mut monster = slime(60, 10);
+        let log = fight(&mut player, &mut monster, &mut Map::new(), &Formula::default(), &mut |_: &CombatLog, _: &Player, _: &Monster| Action::Flee, &mut StdRng::seed_from_u64(4));
+        assert_eq!(log.ending, Ending::Fled);
+        assert!(log.rounds.last().unwrap().contains(&Entry::Flee { success: true }));
+        assert_eq!(monster.life(), 60);
+
+        let mut player = Player::new("Hero");
+        player.equip(Item::new_weapon("feather", "", 0, 0)).unwrap();
+        let mut monster = slime(10, 0);
+        let log = fight(&mut player, &mut monster, &mut Map::new(), &Formula::default().with_minimum(0), &mut Aggressive, &mut StdRng::seed_from_u64(5));
+        assert_eq!(log.ending, Ending::Stalemate);
+        assert_eq!(log.rounds.len(), MAX_ROUNDS as usize);
+    }
+
+    #[test]
+    fn test_combat_armor_and_critical() {
+        let mut player = hero();
+        player.equip(Item::new_armor("mail", "", 500, 3)).unwrap();
+        let mut monster = slime(30, 4).with_armor(Item::new_armor("shell", "", 0, 4));
+        let log = fight(&mut player, &mut monster, &mut Map::new(), &Formula::default(), &mut Aggressive, &mut StdRng::seed_from_u64(6));
+        assert_eq!(log.rounds.len(), 5);
+        for entry in log.rounds.iter().flatten() {
+            match entry {
+                Entry::Hit { attacker: Side::Player, damage, .. } => assert_eq!(*damage, 6),
+                Entry::Hit { attacker: Side::Monster, damage, .. } => assert_eq!(*damage, 1),
+                entry => panic!("unexpected {:?}", entry),
+            }
+        }
+
+        let mut monster = slime(30, 4).with_armor(Item::new_armor("shell", "", 0, 4));
+        let formula = Formula::default().with_critical(100, 300);
+        let log = fight(&mut player, &mut monster, &mut Map::new(), &formula, &mut Aggressive, &mut StdRng::seed_from_u64(6));
+        assert_eq!(log.rounds.len(), 2);
+        assert!(log.rounds[0].contains(&Entry::Hit { attacker: Side::Player, damage: 26, life: 4, critical: true }));
+        assert!(log.describe(0).contains(&"A critical hit! You strike the slime for 26 (4 life left).".to_string()));
+    }
+
+    #[test]
+    fn test_combat_flee_chance() {
+        let mut player = hero();
+        assert_eq!(flee_chance(&player, &slime(10, 5)), 45);
+        assert_eq!(flee_chance(&player, &slime(10, 5).with_armor(Item::new_armor("shell", "", 0, 20))), 25);
+        assert_eq!(flee_chance(&player, &slime(10, 50)), 10);
+        let veteran = Player::new_all("Hero", 100, 100, Position::new(0, 0), Equipment::new(), Inventory::new(), 0, 4, 100);
+        assert_eq!(flee_chance(&veteran, &slime(10, 5)), 75);
+        assert_eq!(flee_chance(&veteran, &slime(10, 0)), 80);
+        player.add_item(Item::new_exp("experience", "", 100)).unwrap();
+        assert_eq!(flee_chance(&player, &slime(10, 5)), 55);
+
+        let mut player = hero();
+        let mut monster = slime(60, 30).with_armor(Item::new_armor("shell", "", 0, 30));
+        let mut actions = vec![Action::Flee, Action::Attack];
+        let mut strategy = |_: &CombatLog, _: &Player, _: &Monster| if actions.len() > 1 { actions.remove(0) } else { actions[0] };
+        let log = fight(&mut player, &mut monster, &mut Map::new(), &Formula::default(), &mut strategy, &mut StdRng::seed_from_u64(7));
+        let hits = |side: Side| log.rounds[0].iter().filter(|entry| matches!(entry, Entry::Hit { attacker, .. } if *attacker == side)).count();
+        assert!(log.rounds[0].contains(&Entry::Flee { success: false }));
+        assert_eq!((hits(Side::Player), hits(Side::Monster)), (0, 2));
+        assert!(log.rounds[0].contains(&Entry::Hit { attacker: Side::Monster, damage: 30, life: 40, critical: false }));
+        assert_eq!(log.ending, Ending::Defeat);
+    }
+
+    #[test]
+    fn test_combat_statuses() {
+        let mut player = hero();
+        player.add_status(StatusEffect::new(StatusKind::Blindness, 0, 1));
+        let fangs = Item::new_weapon("fangs", "", 0, 4).with_inflicts(StatusEffect::new(StatusKind::Poison, 2, 2));
+        let mut monster = Monster::new("spider", 30, fangs, None, 0, 1);
+        monster.add_status(StatusEffect::new(StatusKind::Stun, 0, 1));
+        monster.add_status(StatusEffect::new(StatusKind::Haste, 0, 3));
+        let log = fight(&mut player, &mut monster, &mut Map::new(), &Formula::default(), &mut Aggressive, &mut StdRng::seed_from_u64(8));
+        assert_eq!(log.initiative, Side::Monster);
+        assert_eq!(log.rounds[0], vec![
+            Entry::Stunned(Side::Monster),
+            Entry::Hit { attacker: Side::Player, damage: 5, life: 25, critical: false },
+            Entry::WearOff { target: Side::Player, status: StatusKind::Blindness },
+            Entry::WearOff { target: Side::Monster, status: StatusKind::Stun },
+        ]);
+        assert_eq!(log.rounds[1], vec![
+            Entry::Hit { attacker: Side::Monster, damage: 4, life: 96, critical: false },
+            Entry::Afflict { target: Side::Player, status: StatusKind::Poison },
+            Entry::Hit { attacker: Side::Player, damage: 10, life: 15, critical: false },
+            Entry::Suffer { target: Side::Player, change: -2, life: 94 },
+        ]);
+        assert_eq!(log.describe(1)[1..], ["You are now poisoned.", "You hit the spider for 10 (15 life left).", "You lose 2 life (94 life left)."]);
+        assert_eq!(log.ending, Ending::Victory);
+
+        let mut player = hero();
+        player.add_status(StatusEffect::new(StatusKind::Haste, 0, 1));
+        player.add_status(StatusEffect::new(StatusKind::Stun, 0, 1));
+        let mut monster = slime(15, 1);
+        monster.add_status(StatusEffect::new(StatusKind::Poison, 5, 9));
+        let log = fight(&mut player, &mut monster, &mut Map::new(), &Formula::default(), &mut Aggressive, &mut StdRng::seed_from_u64(8));
+        assert_eq!(log.initiative, Side::Player);
+        assert_eq!(log.rounds[0][0], Entry::Stunned(Side::Player));
+        assert_eq!(log.describe(0)[0], "You are stunned and cannot act.");
+        assert!(log.rounds[0].contains(&Entry::Suffer { target: Side::Monster, change: -5, life: 10 }));
+        assert_eq!(log.rounds.len(), 2);
+        assert_eq!(log.ending, Ending::Victory);
+    }
+
+    #[test]
+    fn test_combat_describe() {
+        let mut player = hero();
+        let mut monster = slime(10, 4);
+        let mut actions = vec![Action::Use(3), Action::Defend, Action::Attack];
+        let mut strategy = |_: &CombatLog, _: &Player, _: &Monster| actions.remove(0);
+        let log = fight(&mut player, &mut monster, &mut Map::new(), &Formula::default(), &mut strategy, &mut StdRng::seed_from_u64(0));
+        let lines: Vec<String> = (0..log.rounds.len()).flat_map(|round| log.describe(round)).collect();
+        assert_eq!(lines, vec![
+            "You fumble for item 4.",
+            "The slime hits you for 4 (96 life left).",
+            "You raise your guard.",
+            "The slime hits you for 2 (94 life left).",
+            "You hit the slime for 10 (0 life left).",
+        ]);
+    }
+}
@@ -0,0 +1,140 @@
+use rand::Rng;
+
+/// A blow as rolled by a `Formula`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Blow {
+    pub damage: i32,
+    pub critical: bool,
+}
+
+/// How much damage an attack deals through a defense, used alike for the
+/// player and for monsters. The default deals the attack minus the defense,
+/// at least 1, with no critical hits and no variance; the `@combat` block of
+/// the content can change every part.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Formula {
+    minimum: i32,
+    /// Percent chance of a critical hit.
+    critical_chance: u32,
+    /// Percent of the attack a critical hit strikes with.
+    critical_multiplier: u32,
+    /// Percent the attack varies by, up or down, from blow to blow.
+    variance: u32,
+}
+
+impl Default for Formula {
+    fn default() -> Formula {
+        Formula { minimum: 1, critical_chance: 0, critical_multiplier: 200, variance: 0 }
+    }
+}
+
+impl Formula {
+    pub fn minimum(&self) -> i32 {
+        self.minimum
+    }
+
+    pub fn critical_chance(&self) -> u32 {
+        self.critical_chance
+    }
+
+    pub fn critical_multiplier(&self) -> u32 {
+        self.critical_multiplier
+    }
+
+    pub fn variance(&self) -> u32 {
+        self.variance
+    }
+
+    /// Damage dealt by every blow that gets through, however strong the
+    /// defense. Negative values count as 0.
+    pub fn with_minimum(mut self, minimum: i32) -> Formula {
+        self.minimum = minimum.max(0);
+        self
+    }
+
+    /// Critical hits happen `chance` percent of the time (at most 100) and
+    /// strike with `multiplier` percent of the attack.
+    pub fn with_critical(mut self, chance: u32, multiplier: u32) -> Formula {
+        self.critical_chance = chance.min(100);
+        self.critical_multiplier = multiplier;
+        self
+    }
+
+    /// Every attack is scaled by a random percentage within `variance`
+    /// (at most 100) of its value.
+    pub fn with_variance(mut self, variance: u32) -> Formula {
+        self.variance = variance.min(100);
+        self
+    }
+
+    /// Damage of an attack through a defense, before any luck.
+    pub fn damage(&self, attack: i32, defense: i32) -> i32 {
+        (attack - defense).max(self.minimum)
+    }
+
+    /// Roll one blow. The RNG is only drawn from for the parts that are
+    /// enabled, so the plain formula leaves it untouched.
+    pub fn roll(&self, attack: i32, defense: i32, rng: &mut impl Rng) -> Blow {
+        let critical = self.critical_chance > 0 && rng.gen_range(0..100) < self.critical_chance;
+        let mut attack = attack as i64;
+        if critical {
+            attack = attack * self.critical_multiplier as i64 / 100;
+        }
+        if self.variance > 0 {
+            let variance = self.variance as i64;
+            attack = attack * rng.gen_range(100 - variance..=100 + variance) / 100;
+        }
+        let attack = attack.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        Blow { damage: self.damage(attack, defense), critical }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_formula_damage() {
+        let formula = Formula::default();
+        assert_eq!(formula.damage(10, 3), 7);
+        assert_eq!(formula.damage(3, 10), 1);
+        assert_eq!(formula.with_minimum(0).damage(3, 10), 0);
+        assert_eq!(formula.with_minimum(-5).minimum(), 0);
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..20 {
+            assert_eq!(formula.roll(10, 3, &mut rng), Blow { damage: 7, critical: false });
+        }
+    }
+
+    #[test]
+    fn test_formula_critical() {
+        let formula = Formula::default().with_critical(100, 300);
+        assert_eq!(formula.roll(10, 5, &mut StdRng::seed_from_u64(0)), Blow { damage: 25, critical: true });
+        assert_eq!(Formula::default().with_critical(250, 150).critical_chance(), 100);
+
+        let formula = Formula::default().with_critical(25, 200);
+        let mut rng = StdRng::seed_from_u64(1);
+        let blows: Vec<Blow> = (0..400).map(|_| formula.roll(10, 0, &mut rng)).collect();
+        let critical = blows.iter().filter(|blow| blow.critical).count();
+        assert!((60..140).contains(&critical), "{} critical hits", critical);
+        assert!(blows.iter().all(|blow| blow.damage == if blow.critical { 20 } else { 10 }));
+    }
+
+    #[test]
+    fn test_formula_variance() {
+        let formula = Formula::default().with_variance(50);
+        let mut rng = StdRng::seed_from_u64(2);
+        let damages: Vec<i32> = (0..200).map(|_| formula.roll(100, 10, &mut rng).damage).collect();
+        assert!(damages.iter().all(|damage| (40..=140).contains(damage)));
+        assert!(damages.iter().any(|damage| *damage < 70));
+        assert!(damages.iter().any(|damage| *damage > 110));
+
+        let run = || {
+            let mut rng = StdRng::seed_from_u64(3);
+            (0..10).map(|_| formula.roll(100, 10, &mut rng)).collect::<Vec<Blow>>()
+        };
+        assert_eq!(run(), run());
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::data_loader::ContentRoot;
+use crate::map::generator::{Generator, GeneratorError};
+use crate::map::population::Density;
+
+/// Options selected on the command line.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Config {
+    content: ContentRoot,
+    load: Option<PathBuf>,
+    debug_map: bool,
+    density: Density,
+    generator: Option<Generator>,
+    floors: u32,
+    map: Option<String>,
+}
+
+/// Error produced when the command line cannot be understood.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ConfigError {
+    MissingValue(String),
+    InvalidValue(String, String),
+    InvalidGenerator(GeneratorError),
+    UnknownArgument(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::MissingValue(flag) => write!(f, "missing value after {}", flag),
+            ConfigError::InvalidValue(flag, value) => write!(f, "invalid value {} after {}", value, flag),
+            ConfigError::InvalidGenerator(error) => write!(f, "{}", error),
+            ConfigError::UnknownArgument(arg) => write!(f, "unknown argument {}", arg),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            content: ContentRoot::from_env(),
+            load: None,
+            debug_map: false,
+            density: Density::default(),
+            generator: None,
+            floors: 3,
+            map: None,
+        }
+    }
+}
+
+impl Config {
+    pub const USAGE: &'static str = "usage: blind_labyrinth [--data <dir>] [--load <save>] [--monsters <percent>] [--treasures <percent>] [--generator <name[:option=value,...]>] [--floors <count>] [--map <name>] [--debug-map]";
+
+    /// Build the configuration from the program arguments (without the
+    /// program name). The data directory defaults to `ContentRoot::from_env`.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--data" | "-d" => {
+                    let dir = args.next().ok_or(ConfigError::MissingValue(arg))?;
+                    config.content = ContentRoot::new(dir);
+                },
+                "--load" => {
+                    let save = args.next().ok_or(ConfigError::MissingValue(arg))?;
+                    config.load = Some(PathBuf::from(save));
+                },
+                "--monsters" => config.density.monsters = Config::percent(arg, args.next())?,
+                "--treasures" => config.density.treasures = Config::percent(arg, args.next())?,
+                "--generator" => {
+                    let spec = args.next().ok_or(ConfigError::MissingValue(arg))?;
+                    config.generator = Some(Generator::parse(&spec).map_err(ConfigError::InvalidGenerator)?);
+                },
+                "--floors" => {
+                    let value = args.next().ok_or(ConfigError::MissingValue(arg.clone()))?;
+                    config.floors = match value.parse() {
+                        Ok(floors) if (1..=100).contains(&floors) => floors,
+                        _ => return Err(ConfigError::InvalidValue(arg, value)),
+                    };
+                },
+                "--map" => config.map = Some(args.next().ok_or(ConfigError::MissingValue(arg))?),
+                "--debug-map" => config.debug_map = true,
+                _ => return Err(ConfigError::UnknownArgument(arg)),
+            }
+        }
+
+        Ok(config)
+    }
+
+    fn percent(flag: String, value: Option<String>) -> Result<u32, ConfigError> {
+        let value = value.ok_or(ConfigError::MissingValue(flag.clone()))?;
+        match value.parse() {
+            Ok(percent) if percent <= 100 => Ok(percent),
+            _ => Err(ConfigError::InvalidValue(flag, value)),
+        }
+    }
+
+    pub fn content(&self) -> &ContentRoot {
+        &self.content
+    }
+
+    /// Save file to resume instead of starting a new run.
+    pub fn load(&self) -> Option<&Path> {
+        self.load.as_deref()
+    }
+
+    /// How crowded newly generated maps are.
+    pub fn density(&self) -> Density {
+        self.density
+    }
+
+    /// Map generator overriding the one of the content.
+    pub fn generator(&self) -> Option<&Generator> {
+        self.generator.as_ref()
+    }
+
+    /// How many floors newly generated dungeons have.
+    pub fn floors(&self) -> u32 {
+        self.floors
+    }
+
+    /// Name of a hand-drawn `@map` to play instead of generating one.
+    pub fn map(&self) -> Option<&str> {
+        self.map.as_deref()
+    }
+
+    /// Show the whole map instead of only what the player knows of it.
+    pub fn debug_map(&self) -> bool {
+        self.debug_map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_config_data_flag() {
+        let config = Config::from_args(args(&["--data", "content"])).unwrap();
+        assert_eq!(config.content().root(), Path::new("content"));
+    }
+
+    #[test]
+    fn test_config_load_flag() {
+        let config = Config::from_args(args(&["--load", "run.blind"])).unwrap();
+        assert_eq!(config.load(), Some(Path::new("run.blind")));
+    }
+
+    #[test]
+    fn test_config_debug_map_flag() {
+        assert!(!Config::from_args(args(&[])).unwrap().debug_map());
+        assert!(Config::from_args(args(&["--debug-map"])).unwrap().debug_map());
+    }
+
+    #[test]
+    fn test_config_density_flags() {
+        let config = Config::from_args(args(&["--monsters", "20", "--treasures", "0"])).unwrap();
+        assert_eq!(config.density(), Density { monsters: 20, treasures: 0 });
+        assert_eq!(Config::from_args(args(&[])).unwrap().density(), Density::default());
+        assert_eq!(
+            Config::from_args(args(&["--monsters", "101"])),
+            Err(ConfigError::InvalidValue("--monsters".to_string(), "101".to_string())),
+        );
+    }
+
+    #[test]
+    fn test_config_generator_flag() {
+        let config = Config::from_args(args(&["--generator", "maze:width=5"])).unwrap();
+        assert_eq!(config.generator(), Generator::parse("maze:width=5").ok().as_ref());
+        assert_eq!(
+            Config::from_args(args(&["--generator", "spiral"])),
+            Err(ConfigError::InvalidGenerator(GeneratorError::UnknownAlgorithm("spiral".to_string()))),
+        );
+    }
+
+    #[test]
+    fn test_config_floors_flag() {
+        assert_eq!(Config::from_args(args(&[])).unwrap().floors(), 3);
+        assert_eq!(Config::from_args(args(&["--floors", "1"])).unwrap().floors(), 1);
+        assert_eq!(
+            Config::from_args(args(&["--floors", "0"])),
+            Err(ConfigError::InvalidValue("--floors".to_string(), "0".to_string())),
+        );
+    }
+
+    #[test]
+    fn test_config_map_flag() {
+        assert_eq!(Config::from_args(args(&[])).unwrap().map(), None);
+        assert_eq!(Config::from_args(args(&["--map", "tutorial"])).unwrap().map(), Some("tutorial"));
+    }
+
+    #[test]
+    fn test_config_missing_value() {
+        assert_eq!(Config::from_args(args(&["--data"])), Err(ConfigError::MissingValue("--data".to_string())));
+    }
+
+    #[test]
+    fn test_config_unknown_argument() {
+        assert_eq!(Config::from_args(args(&["--fly"])), Err(ConfigError::UnknownArgument("--fly".to_string())));
+    }
+}
@@ -0,0 +1,217 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::error::LoadError;
+use super::lexer::Lexer;
+use super::parser::Parser;
+use super::registry::{Catalog, Registry};
+
+/// Extension of the files holding game content.
+pub const EXTENSION: &str = "blind";
+
+/// Directory containing the game content, laid out as:
+///
+/// ```text
+/// <root>/items/*.blind
+/// <root>/monsters/*.blind
+/// <root>/player.blind
+/// <root>/hints.blind      (optional)
+/// <root>/generator.blind  (optional)
+/// <root>/combat.blind     (optional)
+/// <root>/maps/*.blind     (optional)
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ContentRoot {
+    root: PathBuf,
+}
+
+impl ContentRoot {
+    /// Environment variable used when no directory is given on the command line.
+    pub const ENV_VAR: &'static str = "BLIND_LABYRINTH_DATA";
+    /// Directory used when neither the command line nor the environment name one.
+    pub const DEFAULT_DIR: &'static str = "data";
+
+    pub fn new(root: impl Into<PathBuf>) -> ContentRoot {
+        ContentRoot { root: root.into() }
+    }
+
+    /// Content root taken from `ENV_VAR`, falling back to `DEFAULT_DIR`.
+    pub fn from_env() -> ContentRoot {
+        match std::env::var_os(Self::ENV_VAR) {
+            Some(dir) => ContentRoot::new(dir),
+            None => ContentRoot::new(Self::DEFAULT_DIR),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn items_dir(&self) -> PathBuf {
+        self.root.join("items")
+    }
+
+    pub fn monsters_dir(&self) -> PathBuf {
+        self.root.join("monsters")
+    }
+
+    pub fn maps_dir(&self) -> PathBuf {
+        self.root.join("maps")
+    }
+
+    pub fn player_file(&self) -> PathBuf {
+        self.root.join("player").with_extension(EXTENSION)
+    }
+
+    pub fn hints_file(&self) -> PathBuf {
+        self.root.join("hints").with_extension(EXTENSION)
+    }
+
+    pub fn generator_file(&self) -> PathBuf {
+        self.root.join("generator").with_extension(EXTENSION)
+    }
+
+    pub fn combat_file(&self) -> PathBuf {
+        self.root.join("combat").with_extension(EXTENSION)
+    }
+
+    pub fn item_files(&self) -> Result<Vec<PathBuf>, LoadError> {
+        Self::list(&self.items_dir())
+    }
+
+    pub fn monster_files(&self) -> Result<Vec<PathBuf>, LoadError> {
+        Self::list(&self.monsters_dir())
+    }
+
+    /// The hand-drawn maps, none when there is no maps directory.
+    pub fn map_files(&self) -> Result<Vec<PathBuf>, LoadError> {
+        match self.maps_dir() {
+            dir if dir.is_dir() => Self::list(&dir),
+            _ => Ok(vec![]),
+        }
+    }
+
+    /// Read a content file, reporting a missing file as `LoadError::MissingFile`.
+    pub fn read(path: &Path) -> Result<String, LoadError> {
+        if !path.is_file() {
+            return Err(LoadError::MissingFile(path.to_path_buf()));
+        }
+        fs::read_to_string(path).map_err(|error| LoadError::Io(path.to_path_buf(), error))
+    }
+
+    fn parser(path: &Path) -> Result<Parser, LoadError> {
+        let source = Self::read(path)?;
+        Ok(Parser::new(Lexer::new(source).lex_spanned()).with_file(path))
+    }
+
+    /// Parse every content file into a `Registry`, without resolving
+    /// references between definitions yet. Any file may hold any number of
+    /// definitions of any kind; the directories only group them.
+    pub fn registry(&self) -> Result<Registry, LoadError> {
+        let mut registry = Registry::new();
+        let mut files = self.item_files()?;
+        files.extend(self.monster_files()?);
+        files.push(self.player_file());
+        for optional in [self.hints_file(), self.generator_file(), self.combat_file()] {
+            if optional.is_file() {
+                files.push(optional);
+            }
+        }
+        files.extend(self.map_files()?);
+        for path in files {
+            for definition in Self::parser(&path)?.parse_all()? {
+                registry.add(definition);
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Parse and resolve the whole content directory.
+    pub fn load(&self) -> Result<Catalog, LoadError> {
+        Ok(self.registry()?.resolve()?)
+    }
+
+    /// Every `.blind` file directly inside `dir`, sorted by path so loading
+    /// does not depend on the order the file system lists them in.
+    fn list(dir: &Path) -> Result<Vec<PathBuf>, LoadError> {
+        if !dir.is_dir() {
+            return Err(LoadError::MissingDirectory(dir.to_path_buf()));
+        }
+
+        let entries = fs::read_dir(dir).map_err(|error| LoadError::Io(dir.to_path_buf(), error))?;
+        let mut files = vec![];
+        for entry in entries {
+            let path = entry.map_err(|error| LoadError::Io(dir.to_path_buf(), error))?.path();
+            if path.is_file() && path.extension().is_some_and(|extension| extension == EXTENSION) {
+                files.push(path);
+            }
+        }
+        files.sort();
+
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("blind_labyrinth_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    #[test]
+    fn test_content_root_paths() {
+        let content = ContentRoot::new(Path::new("some").join("dir"));
+        assert_eq!(content.items_dir(), Path::new("some").join("dir").join("items"));
+        assert_eq!(content.monsters_dir(), Path::new("some").join("dir").join("monsters"));
+        assert_eq!(content.player_file(), Path::new("some").join("dir").join("player.blind"));
+        assert_eq!(content.hints_file(), Path::new("some").join("dir").join("hints.blind"));
+        assert_eq!(content.generator_file(), Path::new("some").join("dir").join("generator.blind"));
+        assert_eq!(content.combat_file(), Path::new("some").join("dir").join("combat.blind"));
+        assert_eq!(content.maps_dir(), Path::new("some").join("dir").join("maps"));
+    }
+
+    #[test]
+    fn test_content_root_missing_directory() {
+        let root = temp_root("missing");
+        let content = ContentRoot::new(&root);
+        match content.item_files() {
+            Err(LoadError::MissingDirectory(dir)) => assert_eq!(dir, root.join("items")),
+            other => panic!("unexpected result {:?}", other),
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_content_root_sorted_blind_files() {
+        let root = temp_root("sorted");
+        let items = root.join("items");
+        fs::create_dir_all(&items).unwrap();
+        for name in ["c.blind", "a.blind", "notes.txt", "b.blind"] {
+            fs::write(items.join(name), "").unwrap();
+        }
+        let content = ContentRoot::new(&root);
+        assert_eq!(content.item_files().unwrap(), vec![
+            items.join("a.blind"),
+            items.join("b.blind"),
+            items.join("c.blind"),
+        ]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_content_root_default_data() {
+        let content = ContentRoot::new(Path::new(env!("CARGO_MANIFEST_DIR")).join(ContentRoot::DEFAULT_DIR));
+        assert!(!content.item_files().unwrap().is_empty());
+        assert!(!content.monster_files().unwrap().is_empty());
+        assert!(ContentRoot::read(&content.player_file()).is_ok());
+        match content.load() {
+            Ok(catalog) => assert!(catalog.map("tutorial").is_some()),
+            Err(error) => panic!("{}", error),
+        }
+    }
+}
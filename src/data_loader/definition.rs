@@ -0,0 +1,189 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use super::lexer::Span;
+use crate::map::hints::HintKind;
+use crate::map::item::Item;
+use crate::map::monster::Behaviour;
+use crate::map::status::StatusEffect;
+use crate::map::tile::Terrain;
+use crate::map::Position;
+
+/// Where a definition or a reference was written.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Origin {
+    pub file: Option<PathBuf>,
+    pub span: Span,
+}
+
+impl Origin {
+    pub fn new(file: Option<PathBuf>, span: Span) -> Origin {
+        Origin { file, span }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}", file.display())?,
+            None => write!(f, "<input>")?,
+        }
+        write!(f, ":{}:{}", self.span.line, self.span.column)
+    }
+}
+
+/// A name used to refer to another definition, resolved by the `Registry`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Reference {
+    pub name: String,
+    pub origin: Origin,
+}
+
+/// A `@monster` block whose item references are not resolved yet.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MonsterDef {
+    pub name: String,
+    pub life: i32,
+    pub weapon: Option<Reference>,
+    pub armor: Option<Reference>,
+    pub drop: Option<(Reference, u32)>,
+    pub rareness: u32,
+    pub behaviour: Behaviour,
+    pub origin: Origin,
+}
+
+/// A `@player` block whose item references are not resolved yet.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PlayerDef {
+    pub name: String,
+    pub life: i32,
+    pub max_life: i32,
+    pub weapon: Option<Reference>,
+    pub armor: Option<Reference>,
+    /// Items equipped besides `weapon` and `armor`, each in its slot.
+    pub equipment: Vec<Reference>,
+    pub experience: i32,
+    pub level: i32,
+    pub next_level: i32,
+    /// Items and their counts; gold is counted in coins.
+    pub inventory: Vec<(Reference, u32)>,
+    /// Slots of the inventory.
+    pub capacity: usize,
+    pub position: Position,
+    pub statuses: Vec<StatusEffect>,
+    pub origin: Origin,
+}
+
+/// An `@item` block together with where it was defined.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ItemDef {
+    pub item: Item,
+    /// Set by `@treasure[: rareness]`: the item can be found lying in the
+    /// labyrinth, the rarer the higher its rareness (1 by default).
+    pub treasure: Option<u32>,
+    pub origin: Origin,
+}
+
+/// The event of a `@tile` entry, referring to items and monsters by name.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TileDef {
+    Empty,
+    /// A monster, its remaining life and the steps it walked so far.
+    Monster(Reference, i32, u32),
+    /// An item and how many of it the treasure holds; gold treasures hold
+    /// that many coins.
+    Treasure(Reference, u32),
+    Teleport(Position),
+    End,
+    StairsDown,
+    StairsUp,
+}
+
+/// The entries of a `@save` block following a `@depth` marker: one floor.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct FloorDef {
+    pub tiles: Vec<(Position, Terrain, TileDef)>,
+    /// Positions the player walked on and felt, for the fog of war.
+    pub visited: Vec<Position>,
+    pub sensed: Vec<Position>,
+}
+
+/// A `@save` block: the state of a run that is not part of the content.
+/// Entries written before any `@depth` belong to the top floor.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SaveDef {
+    pub seed: u64,
+    pub turn: u32,
+    pub floors: Vec<FloorDef>,
+    /// The floor the player is on.
+    pub current: usize,
+    pub origin: Origin,
+}
+
+/// A `@hints` block: the fields it sets on top of the default hints.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct HintsDef {
+    pub radius: Option<u32>,
+    pub messages: Vec<(HintKind, String)>,
+    pub origin: Origin,
+}
+
+/// A `@generator` block: the map generator and the options to set on it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GeneratorDef {
+    pub algorithm: String,
+    pub options: Vec<(String, i64)>,
+    pub origin: Origin,
+}
+
+/// A `@combat` block: the parts of the damage formula it sets on top of
+/// `Formula::default`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CombatDef {
+    pub min_damage: Option<u32>,
+    /// Percent chance of a critical hit and percent of the attack it deals.
+    pub critical: Option<(u32, u32)>,
+    pub variance: Option<u32>,
+    pub origin: Origin,
+}
+
+/// What a `@legend` symbol stands for.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum LegendEvent {
+    Tile(TileDef),
+    /// A teleport to the only tile drawn with the given symbol.
+    TeleportTo(char),
+}
+
+/// A `@legend` entry of a `@map` block, binding a grid symbol to a tile.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LegendDef {
+    pub symbol: char,
+    pub terrain: Terrain,
+    pub event: LegendEvent,
+    pub origin: Origin,
+}
+
+/// A `@map` block: a hand-drawn map, one `@row` per line of the grid.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MapDef {
+    pub name: String,
+    pub rows: Vec<String>,
+    pub legend: Vec<LegendDef>,
+    /// The grid column and row set by `@origin`, if any.
+    pub origin_cell: Option<Position>,
+    pub origin: Origin,
+}
+
+/// Any top-level block of a `.blind` file.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Definition {
+    Item(ItemDef),
+    Monster(MonsterDef),
+    Player(PlayerDef),
+    Save(SaveDef),
+    Hints(HintsDef),
+    Generator(GeneratorDef),
+    Map(MapDef),
+    Combat(CombatDef),
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use super::lexer::{Span, Token};
+use super::registry::ValidationError;
+
+/// Error produced when a `.blind` source does not match the grammar.
+///
+/// Displayed as `file:line:column: expected ..., found ...` so content
+/// authors can jump straight to the offending token.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseError {
+    file: Option<PathBuf>,
+    span: Span,
+    expected: String,
+    found: Token,
+}
+
+impl ParseError {
+    pub fn new(file: Option<PathBuf>, span: Span, expected: &str, found: Token) -> ParseError {
+        ParseError {
+            file,
+            span,
+            expected: expected.to_string(),
+            found,
+        }
+    }
+
+    pub fn file(&self) -> Option<&PathBuf> {
+        self.file.as_ref()
+    }
+
+    pub fn line(&self) -> usize {
+        self.span.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.span.column
+    }
+
+    pub fn expected(&self) -> &str {
+        &self.expected
+    }
+
+    pub fn found(&self) -> &Token {
+        &self.found
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}", file.display())?,
+            None => write!(f, "<input>")?,
+        }
+        write!(f, ":{}:{}: expected {}, found {}", self.span.line, self.span.column, self.expected, self.found)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Error produced while locating or reading game content.
+#[derive(Debug)]
+pub enum LoadError {
+    MissingDirectory(PathBuf),
+    MissingFile(PathBuf),
+    Io(PathBuf, std::io::Error),
+    Parse(ParseError),
+    Invalid(Vec<ValidationError>),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::MissingDirectory(dir) => write!(f, "{}: missing content directory", dir.display()),
+            LoadError::MissingFile(file) => write!(f, "{}: missing content file", file.display()),
+            LoadError::Io(path, error) => write!(f, "{}: {}", path.display(), error),
+            LoadError::Parse(error) => write!(f, "{}", error),
+            LoadError::Invalid(errors) => {
+                let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                write!(f, "{}", errors.join("\n"))
+            },
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<ParseError> for LoadError {
+    fn from(error: ParseError) -> Self {
+        LoadError::Parse(error)
+    }
+}
+
+impl From<Vec<ValidationError>> for LoadError {
+    fn from(errors: Vec<ValidationError>) -> Self {
+        LoadError::Invalid(errors)
+    }
+}
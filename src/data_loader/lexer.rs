@@ -27,63 +27,200 @@ pub enum Token {
     Name,
     Description,
     Value,
+    Slot,
 
-    Int(i32),
+    Int(i64),
     Str(String),
+    /// Input the lexer could not make sense of, with a short explanation.
+    Invalid(String),
 
     Colon,
 
     Eof,
 
+    Player,
     Level,
     MaxLife,
     ExpToLevelUp,
     Inventory,
+    Capacity,
+    Equip,
+    Position,
+
+    Save,
+    Seed,
+    Turn,
+    Tile,
+    Empty,
+    End,
+    StairsDown,
+    StairsUp,
+    Visited,
+    Sensed,
+    Depth,
+    Current,
+    Floor,
+    Wall,
+    Door,
+    Water,
+    Pit,
+
+    Hints,
+    Radius,
+    Hint,
+
+    Generator,
+    Algorithm,
+    Option,
+
+    Behaviour,
+    Status,
+
+    Combat,
+    MinDamage,
+    Critical,
+    Variance,
+
+    Map,
+    Row,
+    Legend,
+    Origin,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Int(i) => write!(f, "integer {}", i),
+            Token::Str(s) => write!(f, "'{}'", s),
+            Token::Invalid(reason) => write!(f, "{}", reason),
+            Token::Colon => write!(f, "':'"),
+            Token::Eof => write!(f, "end of file"),
+            keyword => write!(f, "{}", keyword.keyword().unwrap_or("?")),
+        }
+    }
+}
+
+impl Token {
+    /// The `@keyword` spelling of this token, if it has one.
+    pub fn keyword(&self) -> Option<&'static str> {
+        KEYWORDS.iter().find(|(_, token)| token == self).map(|(keyword, _)| *keyword)
+    }
+}
+
+const KEYWORDS: &[(&str, Token)] = &[
+    ("@item", Token::Item),
+    ("@weapon", Token::Weapon),
+    ("@armor", Token::Armor),
+    ("@gold", Token::Gold),
+    ("@effect", Token::Effect),
+    ("@exp", Token::Exp),
+
+    ("@heal", Token::Heal),
+    ("@damage", Token::Damage),
+    ("@teleport", Token::Teleport),
+    ("@start", Token::Start),
+    ("@random", Token::Random),
+
+    ("@treasure", Token::Treasure),
+
+    ("@monster", Token::Monster),
+    ("@life", Token::Life),
+    ("@drop", Token::Drop),
+    ("@rareness", Token::Rareness),
+
+    ("@atk", Token::Atk),
+    ("@def", Token::Def),
+    ("@name", Token::Name),
+    ("@description", Token::Description),
+    ("@value", Token::Value),
+    ("@slot", Token::Slot),
+
+    ("@player", Token::Player),
+    ("@level", Token::Level),
+    ("@max-life", Token::MaxLife),
+    ("@exp-to-level-up", Token::ExpToLevelUp),
+    ("@inventory", Token::Inventory),
+    ("@capacity", Token::Capacity),
+    ("@equip", Token::Equip),
+    ("@position", Token::Position),
+
+    ("@save", Token::Save),
+    ("@seed", Token::Seed),
+    ("@turn", Token::Turn),
+    ("@tile", Token::Tile),
+    ("@empty", Token::Empty),
+    ("@end", Token::End),
+    ("@stairs-down", Token::StairsDown),
+    ("@stairs-up", Token::StairsUp),
+    ("@visited", Token::Visited),
+    ("@sensed", Token::Sensed),
+    ("@depth", Token::Depth),
+    ("@current", Token::Current),
+    ("@floor", Token::Floor),
+    ("@wall", Token::Wall),
+    ("@door", Token::Door),
+    ("@water", Token::Water),
+    ("@pit", Token::Pit),
+
+    ("@hints", Token::Hints),
+    ("@radius", Token::Radius),
+    ("@hint", Token::Hint),
+
+    ("@generator", Token::Generator),
+    ("@algorithm", Token::Algorithm),
+    ("@option", Token::Option),
+
+    ("@behaviour", Token::Behaviour),
+    ("@status", Token::Status),
+
+    ("@combat", Token::Combat),
+    ("@min-damage", Token::MinDamage),
+    ("@critical", Token::Critical),
+    ("@variance", Token::Variance),
+
+    ("@map", Token::Map),
+    ("@row", Token::Row),
+    ("@legend", Token::Legend),
+    ("@origin", Token::Origin),
+];
+
+/// Line and column (both starting at 1) where a token begins in the source.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, column: usize) -> Span {
+        Span { line, column }
+    }
 }
 
 pub struct Lexer {
-    input: String,
+    input: Vec<char>,
     position: usize,
+    line: usize,
+    column: usize,
+    start: Span,
+    quoted: bool,
     table: HashMap<String, Token>,
 }
 
 impl Lexer {
     pub fn new(input: String) -> Lexer {
-        let mut table = HashMap::new();
-        table.insert("@item".to_string(), Token::Item);
-        table.insert("@weapon".to_string(), Token::Weapon);
-        table.insert("@armor".to_string(), Token::Armor);
-        table.insert("@gold".to_string(), Token::Gold);
-        table.insert("@effect".to_string(), Token::Effect);
-        table.insert("@exp".to_string(), Token::Exp);
-
-        table.insert("@heal".to_string(), Token::Heal);
-        table.insert("@damage".to_string(), Token::Damage);
-        table.insert("@teleport".to_string(), Token::Teleport);
-        table.insert("@start".to_string(), Token::Start);
-        table.insert("@random".to_string(), Token::Random);
-
-        table.insert("@treasure".to_string(), Token::Treasure);
-
-        table.insert("@monster".to_string(), Token::Monster);
-        table.insert("@life".to_string(), Token::Life);
-        table.insert("@drop".to_string(), Token::Drop);
-        table.insert("@rareness".to_string(), Token::Rareness);
-
-        table.insert("@atk".to_string(), Token::Atk);
-        table.insert("@def".to_string(), Token::Def);
-        table.insert("@name".to_string(), Token::Name);
-        table.insert("@description".to_string(), Token::Description);
-        table.insert("@value".to_string(), Token::Value);
-
-        table.insert("@level".to_string(), Token::Level);
-        table.insert("@max-life".to_string(), Token::MaxLife);
-        table.insert("@exp-to-level-up".to_string(), Token::ExpToLevelUp);
-        table.insert("@inventory".to_string(), Token::Inventory);
+        let table = KEYWORDS
+            .iter()
+            .map(|(keyword, token)| (keyword.to_string(), token.clone()))
+            .collect();
 
         Lexer {
-            input,
+            input: input.chars().collect(),
             position: 0,
+            line: 1,
+            column: 1,
+            start: Span::new(1, 1),
+            quoted: false,
             table,
         }
     }
@@ -92,80 +229,159 @@ impl Lexer {
         c.is_alphabetic() || c.is_numeric() || c == '@' || c == '-'
     }
 
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.input.get(self.position + offset).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.position += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_comment(&mut self) {
+        while let Some(c) = self.peek(0) {
+            if c == '\n' {
+                break;
+            }
+            self.advance();
+        }
+    }
+
+    /// Lex an optionally signed decimal integer.
+    fn lex_int(&mut self) -> Token {
+        let negative = self.peek(0) == Some('-');
+        if matches!(self.peek(0), Some('-') | Some('+')) {
+            self.advance();
+        }
+
+        let mut value: Option<i64> = Some(0);
+        while let Some(digit) = self.peek(0).and_then(|c| c.to_digit(10)) {
+            self.advance();
+            value = value
+                .and_then(|value| value.checked_mul(10))
+                .and_then(|value| if negative { value.checked_sub(digit as i64) } else { value.checked_add(digit as i64) });
+        }
+
+        match value {
+            Some(value) => Token::Int(value),
+            None => Token::Invalid("integer out of range".to_string()),
+        }
+    }
+
+    /// Lex a double-quoted string, the opening quote being the current character.
+    fn lex_quoted(&mut self) -> Token {
+        self.advance();
+        let mut value = String::new();
+        loop {
+            match self.advance() {
+                Some('"') => return Token::Str(value),
+                Some('\\') => match self.advance() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some(c) => return Token::Invalid(format!("unknown escape '\\{}'", c)),
+                    None => return Token::Invalid("unterminated string".to_string()),
+                },
+                Some(c) => value.push(c),
+                None => return Token::Invalid("unterminated string".to_string()),
+            }
+        }
+    }
+
     fn next_token(&mut self) -> Option<Token> {
-        let mut token = None;
-        
-        while self.position < self.input.len() {
-            let c = self.input.chars().nth(self.position).unwrap();
+        self.quoted = false;
+
+        while let Some(c) = self.peek(0) {
             if c.is_whitespace() {
-                self.position += 1;
+                self.advance();
+                continue;
+            }
+
+            if c == '#' {
+                self.skip_comment();
                 continue;
             }
 
+            self.start = Span::new(self.line, self.column);
+
             if c == ':' {
-                token = Some(Token::Colon);
-                self.position += 1;
-                break;
+                self.advance();
+                return Some(Token::Colon);
             }
 
-            if c.is_numeric() {
-                let mut value = 0;
-                while self.position < self.input.len() && self.input.chars().nth(self.position).unwrap().is_numeric() {
-                    value = value * 10 + self.input.chars().nth(self.position).unwrap().to_digit(10).unwrap() as i32;
-                    self.position += 1;
-                }
-                token = Some(Token::Int(value));
-                break;
+            if c == '"' {
+                self.quoted = true;
+                return Some(self.lex_quoted());
+            }
+
+            let signed = (c == '-' || c == '+') && self.peek(1).is_some_and(|c| c.is_ascii_digit());
+            if c.is_ascii_digit() || signed {
+                return Some(self.lex_int());
             }
 
             if Lexer::is_alphanumericorat(c) {
                 let mut value = String::new();
-                while self.position < self.input.len() && Lexer::is_alphanumericorat(self.input.chars().nth(self.position).unwrap()) {
-                    value.push(self.input.chars().nth(self.position).unwrap());
-                    self.position += 1;
+                while let Some(c) = self.peek(0).filter(|c| Lexer::is_alphanumericorat(*c)) {
+                    value.push(c);
+                    self.advance();
                 }
-                token = self.table.get(&value).cloned().or(Some(Token::Str(value)));
-                break;
+                return self.table.get(&value).cloned().or(Some(Token::Str(value)));
             }
-        }
 
-        if token == None && self.position >= self.input.len() {
-            token = Some(Token::Eof);
+            self.advance();
+            return Some(Token::Invalid(format!("character '{}'", c)));
         }
 
-        token
+        self.start = Span::new(self.line, self.column);
+        Some(Token::Eof)
     }
 
-    fn combine_string_next_to_each_others(&self, tokens: Vec<Token>) -> Vec<Token> {
+    /// Join runs of bare words into a single `Str`, so `@name: Big Sword`
+    /// reads as one name. Quoted strings are kept as written.
+    fn combine_string_next_to_each_others(&self, tokens: Vec<(Token, Span, bool)>) -> Vec<(Token, Span)> {
         let mut new_tokens = Vec::new();
-        let mut iter = tokens.iter().peekable();
-        while let Some(token) = iter.next() {
-            if let Token::Str(s) = token {
-                let mut combined = s.clone();
-                while let Some(Token::Str(s)) = iter.peek() {
-                    combined.push_str(" ");
-                    combined.push_str(s);
-                    iter.next();
-                }
-                new_tokens.push(Token::Str(combined));
-            } else {
-                new_tokens.push(token.clone());
+        let mut iter = tokens.into_iter().peekable();
+        while let Some((token, span, quoted)) = iter.next() {
+            match token {
+                Token::Str(s) if !quoted => {
+                    let mut combined = s;
+                    while let Some((Token::Str(s), _, false)) = iter.peek() {
+                        combined.push(' ');
+                        combined.push_str(s);
+                        iter.next();
+                    }
+                    new_tokens.push((Token::Str(combined), span));
+                },
+                token => new_tokens.push((token, span)),
             }
         }
         new_tokens
     }
 
-    pub fn lex(&mut self) -> Vec<Token> {
+    /// Lex the whole input, keeping the position where each token starts.
+    pub fn lex_spanned(&mut self) -> Vec<(Token, Span)> {
         let mut tokens = Vec::new();
         while let Some(token) = self.next_token() {
-            if token == Token::Eof {
-                tokens.push(token);
+            let is_eof = token == Token::Eof;
+            tokens.push((token, self.start, self.quoted));
+            if is_eof {
                 break;
             }
-            tokens.push(token);
         }
         self.combine_string_next_to_each_others(tokens)
     }
+
+    pub fn lex(&mut self) -> Vec<Token> {
+        self.lex_spanned().into_iter().map(|(token, _)| token).collect()
+    }
 }
 
 #[cfg(test)]
@@ -259,4 +475,120 @@ mod tests {
             Token::Eof,
         ])
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_lexer_spans() {
+        let input = "@monster\n@drop: exp 30";
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens = lexer.lex_spanned();
+        assert_eq!(tokens, vec![
+            (Token::Monster, Span::new(1, 1)),
+            (Token::Drop, Span::new(2, 1)),
+            (Token::Colon, Span::new(2, 6)),
+            (Token::Str("exp".to_string()), Span::new(2, 8)),
+            (Token::Int(30), Span::new(2, 12)),
+            (Token::Eof, Span::new(2, 14)),
+        ])
+    }
+
+    #[test]
+    fn test_lexer_comments() {
+        let input = "# an item\n@item @gold # the only field\n@value: 1 #";
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens = lexer.lex_spanned();
+        assert_eq!(tokens, vec![
+            (Token::Item, Span::new(2, 1)),
+            (Token::Gold, Span::new(2, 7)),
+            (Token::Value, Span::new(3, 1)),
+            (Token::Colon, Span::new(3, 7)),
+            (Token::Int(1), Span::new(3, 9)),
+            (Token::Eof, Span::new(3, 12)),
+        ])
+    }
+
+    #[test]
+    fn test_lexer_quoted_string() {
+        let input = r#"@description: "A sharp, shiny sword: it's \"Bob's\"" @name: Big Sword"#;
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens = lexer.lex();
+        assert_eq!(tokens, vec![
+            Token::Description,
+            Token::Colon,
+            Token::Str("A sharp, shiny sword: it's \"Bob's\"".to_string()),
+            Token::Name,
+            Token::Colon,
+            Token::Str("Big Sword".to_string()),
+            Token::Eof,
+        ])
+    }
+
+    #[test]
+    fn test_lexer_quoted_string_escapes() {
+        let input = r#""a\tb\nc\\d#e" "@item""#;
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens = lexer.lex();
+        assert_eq!(tokens, vec![
+            Token::Str("a\tb\nc\\d#e".to_string()),
+            Token::Str("@item".to_string()),
+            Token::Eof,
+        ])
+    }
+
+    #[test]
+    fn test_lexer_quoted_string_errors() {
+        let mut lexer = Lexer::new(r#""bad \q""#.to_string());
+        assert_eq!(lexer.next_token(), Some(Token::Invalid("unknown escape '\\q'".to_string())));
+        let mut lexer = Lexer::new(r#"@name: "never closed"#.to_string());
+        assert_eq!(lexer.lex()[2], Token::Invalid("unterminated string".to_string()));
+    }
+
+    #[test]
+    fn test_lexer_signed_integers() {
+        let input = "@heal: -5 @damage: +7 @max-life: 9223372036854775807 -9223372036854775808 9999999999999999999";
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens = lexer.lex();
+        assert_eq!(tokens, vec![
+            Token::Heal,
+            Token::Colon,
+            Token::Int(-5),
+            Token::Damage,
+            Token::Colon,
+            Token::Int(7),
+            Token::MaxLife,
+            Token::Colon,
+            Token::Int(i64::MAX),
+            Token::Int(i64::MIN),
+            Token::Invalid("integer out of range".to_string()),
+            Token::Eof,
+        ])
+    }
+
+    #[test]
+    fn test_lexer_utf8() {
+        let input = "@name: Épée dorée @value: 3";
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens = lexer.lex_spanned();
+        assert_eq!(tokens, vec![
+            (Token::Name, Span::new(1, 1)),
+            (Token::Colon, Span::new(1, 6)),
+            (Token::Str("Épée dorée".to_string()), Span::new(1, 8)),
+            (Token::Value, Span::new(1, 19)),
+            (Token::Colon, Span::new(1, 25)),
+            (Token::Int(3), Span::new(1, 27)),
+            (Token::Eof, Span::new(1, 28)),
+        ])
+    }
+
+    #[test]
+    fn test_lexer_invalid_character() {
+        let mut lexer = Lexer::new("@name: a, b".to_string());
+        assert_eq!(lexer.lex(), vec![
+            Token::Name,
+            Token::Colon,
+            Token::Str("a".to_string()),
+            Token::Invalid("character ','".to_string()),
+            Token::Str("b".to_string()),
+            Token::Eof,
+        ])
+    }
+}
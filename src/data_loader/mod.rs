@@ -1,5 +1,15 @@
+mod content;
+mod definition;
+mod error;
 mod lexer;
 mod parser;
+mod registry;
+mod writer;
 
-pub use lexer::Lexer;
-pub use parser::Parser;
\ No newline at end of file
+pub use content::ContentRoot;
+pub use definition::{CombatDef, Definition, FloorDef, GeneratorDef, HintsDef, ItemDef, LegendDef, LegendEvent, MapDef, MonsterDef, Origin, PlayerDef, Reference, SaveDef, TileDef};
+pub use error::{LoadError, ParseError};
+pub use lexer::{Lexer, Span, Token};
+pub use parser::Parser;
+pub use registry::{Catalog, Kind, MapError, Registry, SavedRun, ValidationError};
+pub use writer::Writer;
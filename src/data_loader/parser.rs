@@ -1,714 +1,1101 @@
-use super::lexer::{Token, Lexer};
+use std::path::{Path, PathBuf};
+
+use super::definition::{CombatDef, Definition, FloorDef, GeneratorDef, HintsDef, ItemDef, LegendDef, LegendEvent, MapDef, MonsterDef, Origin, PlayerDef, Reference, SaveDef, TileDef};
+use super::error::ParseError;
+use super::lexer::{Span, Token};
 use crate::map::Position;
-use crate::map::{item::{Item, Effect}, monster::Monster};
-use crate::player::Player;
+use crate::map::hints::{HintKind, Hints};
+use crate::map::monster::Behaviour;
+use crate::map::status::{StatusEffect, StatusKind};
+use crate::map::tile::Terrain;
+use crate::map::item::{Item, Effect, ItemType};
+use crate::player::{equipment::Slot, inventory::Inventory};
 
 pub struct Parser {
-    tokens: Vec<Token>,
+    tokens: Vec<(Token, Span)>,
     position: usize,
+    file: Option<PathBuf>,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
+    pub fn new(tokens: Vec<(Token, Span)>) -> Self {
         Parser {
             tokens,
             position: 0,
+            file: None,
         }
     }
 
-    fn next_token(&mut self) -> &Token {
-        self.position += 1;
-        &self.tokens[self.position - 1]
+    /// Attach the path of the source file, reported in every `ParseError`.
+    pub fn with_file(mut self, file: &Path) -> Self {
+        self.file = Some(file.to_path_buf());
+        self
+    }
+
+    fn next_token(&mut self) -> Token {
+        let token = self.at().clone();
+        if token != Token::Eof {
+            self.position += 1;
+        }
+        token
     }
 
     fn at(&self) -> &Token {
-        &self.tokens[self.position]
+        &self.tokens[self.position].0
     }
 
-    fn fetch_weapon(&mut self) -> Option<Item> {
-        let mut name = String::new();
-        let mut atk = 0;
-        let mut description = String::new();
-        let mut value = 0;
+    /// Whether the current token closes the block being parsed.
+    fn at_block_end(&self) -> bool {
+        matches!(self.at(), Token::Eof | Token::Item | Token::Monster | Token::Player | Token::Save | Token::Hints | Token::Generator | Token::Map | Token::Combat)
+    }
 
-        while self.at() != &Token::Eof {
-            match self.next_token() {
-                Token::Name => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Str(s) => {
-                            name = s.to_string();
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                Token::Atk => {
+    fn origin(&self) -> Origin {
+        Origin::new(self.file.clone(), self.tokens[self.position].1)
+    }
+
+    fn error(&self, expected: &str) -> ParseError {
+        let (found, span) = self.tokens[self.position].clone();
+        ParseError::new(self.file.clone(), span, expected, found)
+    }
+
+    fn expect(&mut self, token: Token, expected: &str) -> Result<(), ParseError> {
+        if self.at() == &token {
+            self.next_token();
+            Ok(())
+        } else {
+            Err(self.error(expected))
+        }
+    }
+
+    fn expect_i64(&mut self, expected: &str) -> Result<i64, ParseError> {
+        match self.at() {
+            Token::Int(i) => {
+                let i = *i;
+                self.next_token();
+                Ok(i)
+            },
+            _ => Err(self.error(expected)),
+        }
+    }
+
+    fn expect_int(&mut self, expected: &str) -> Result<i32, ParseError> {
+        match self.at() {
+            Token::Int(i) => match i32::try_from(*i) {
+                Ok(i) => {
                     self.next_token();
-                    match self.next_token() {
-                        Token::Int(i) => {
-                            atk = *i;
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
+                    Ok(i)
                 },
-                Token::Description => {
+                Err(_) => Err(self.error(&format!("{} (32-bit)", expected))),
+            },
+            _ => Err(self.error(expected)),
+        }
+    }
+
+    /// Consume a count, which cannot be negative.
+    fn expect_count(&mut self, expected: &str) -> Result<u32, ParseError> {
+        match self.at() {
+            Token::Int(i) => match u32::try_from(*i) {
+                Ok(i) => {
                     self.next_token();
-                    match self.next_token() {
-                        Token::Str(s) => {
-                            description = s.to_string();
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
+                    Ok(i)
                 },
-                Token::Value => {
+                Err(_) => Err(self.error(&format!("{} (0 or more)", expected))),
+            },
+            _ => Err(self.error(expected)),
+        }
+    }
+
+    /// Consume a percent, from 0 to 100.
+    fn expect_percent(&mut self, expected: &str) -> Result<u32, ParseError> {
+        match self.at() {
+            Token::Int(i) if (0..=100).contains(i) => {
+                let percent = *i as u32;
+                self.next_token();
+                Ok(percent)
+            },
+            Token::Int(_) => Err(self.error(&format!("{} (0 to 100)", expected))),
+            _ => Err(self.error(expected)),
+        }
+    }
+
+    fn expect_str(&mut self, expected: &str) -> Result<String, ParseError> {
+        match self.at() {
+            Token::Str(s) => {
+                let s = s.clone();
+                self.next_token();
+                Ok(s)
+            },
+            _ => Err(self.error(expected)),
+        }
+    }
+
+    /// Consume an `<x> <y>` pair of integers.
+    fn position_value(&mut self, after: &str) -> Result<Position, ParseError> {
+        let x = self.expect_int(&format!("x coordinate after {}", after))?;
+        let y = self.expect_int(&format!("y coordinate after {}", after))?;
+        Ok(Position::new(x, y))
+    }
+
+    /// Consume a `@field: <integer>` entry.
+    fn field_int(&mut self) -> Result<i32, ParseError> {
+        let field = self.next_token();
+        self.expect(Token::Colon, &format!("':' after {}", field))?;
+        self.expect_int(&format!("integer after {}", field))
+    }
+
+    /// Consume a `@field: <count>` entry.
+    fn field_count(&mut self) -> Result<u32, ParseError> {
+        let field = self.next_token();
+        self.expect(Token::Colon, &format!("':' after {}", field))?;
+        self.expect_count(&format!("count after {}", field))
+    }
+
+    /// Consume a `@field: <percent>` entry.
+    fn field_percent(&mut self) -> Result<u32, ParseError> {
+        let field = self.next_token();
+        self.expect(Token::Colon, &format!("':' after {}", field))?;
+        self.expect_percent(&format!("percent after {}", field))
+    }
+
+    /// Consume a `@field: <text>` entry.
+    fn field_str(&mut self) -> Result<String, ParseError> {
+        let field = self.next_token();
+        self.expect(Token::Colon, &format!("':' after {}", field))?;
+        self.expect_str(&format!("text after {}", field))
+    }
+
+    /// Consume a `<status> <power> for <turns>` status, as written after
+    /// `after`.
+    fn status_value(&mut self, after: &str) -> Result<StatusEffect, ParseError> {
+        let kind = match self.at() {
+            Token::Str(name) => StatusKind::from_name(name),
+            _ => None,
+        };
+        let Some(kind) = kind else {
+            let names: Vec<&str> = StatusKind::ALL.iter().map(StatusKind::name).collect();
+            return Err(self.error(&format!("status ({}) after {}", names.join(", "), after)));
+        };
+        self.next_token();
+        let power = self.expect_int(&format!("power after {}", kind.name()))?;
+        match self.at() {
+            Token::Str(word) if word == "for" => self.next_token(),
+            _ => return Err(self.error(&format!("'for' after the power of {}", kind.name()))),
+        };
+        let turns = self.expect_count(&format!("number of turns after {} {} for", kind.name(), power))?;
+        Ok(StatusEffect::new(kind, power, turns))
+    }
+
+    /// Consume a `@slot: <slot>` entry naming one of the slots that take
+    /// items of `type_`.
+    fn field_slot(&mut self, type_: &ItemType) -> Result<Slot, ParseError> {
+        self.next_token();
+        self.expect(Token::Colon, "':' after @slot")?;
+        let slot = match self.at() {
+            Token::Str(name) => Slot::from_name(name).filter(|slot| slot.accepts(type_)),
+            _ => None,
+        };
+        let Some(slot) = slot else {
+            let names: Vec<&str> = Slot::ALL.iter().filter(|slot| slot.accepts(type_)).map(Slot::name).collect();
+            return Err(self.error(&format!("slot ({}) after @slot:", names.join(", "))));
+        };
+        self.next_token();
+        Ok(slot)
+    }
+
+    fn fetch_weapon(&mut self) -> Result<Item, ParseError> {
+        let mut name = String::new();
+        let mut atk = 0;
+        let mut description = String::new();
+        let mut value = 0;
+        let mut inflicts = None;
+        let mut slot = None;
+
+        while !self.at_block_end() {
+            match self.at() {
+                Token::Name => name = self.field_str()?,
+                Token::Atk => atk = self.field_int()?,
+                Token::Slot => slot = Some(self.field_slot(&ItemType::Weapon(0))?),
+                Token::Description => description = self.field_str()?,
+                Token::Value => value = self.field_int()?,
+                Token::Effect => {
                     self.next_token();
-                    match self.next_token() {
-                        Token::Int(i) => {
-                            value = *i;
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                _ => {
-                    break;
+                    self.expect(Token::Colon, "':' after @effect")?;
+                    inflicts = Some(self.status_value("@effect:")?);
                 },
+                _ => return Err(self.error("weapon field (@name, @atk, @description, @value, @effect or @slot)")),
             }
         }
 
-        Some(Item::new_weapon(&name, &description, value, atk))
+        let mut weapon = Item::new_weapon(&name, &description, value, atk);
+        if let Some(status) = inflicts {
+            weapon = weapon.with_inflicts(status);
+        }
+        if let Some(slot) = slot {
+            weapon = weapon.with_slot(slot);
+        }
+        Ok(weapon)
     }
 
-    fn fetch_armor(&mut self) -> Option<Item> {
+    fn fetch_armor(&mut self) -> Result<Item, ParseError> {
         let mut name = String::new();
         let mut def = 0;
         let mut description = String::new();
         let mut value = 0;
+        let mut slot = None;
 
-        while self.at() != &Token::Eof {
-            match self.next_token() {
-                Token::Name => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Str(s) => {
-                            name = s.to_string();
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                Token::Def => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Int(i) => {
-                            def = *i;
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                Token::Description => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Str(s) => {
-                            description = s.to_string();
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                Token::Value => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Int(i) => {
-                            value = *i;
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                _ => {
-                    break;
-                },
+        while !self.at_block_end() {
+            match self.at() {
+                Token::Name => name = self.field_str()?,
+                Token::Def => def = self.field_int()?,
+                Token::Description => description = self.field_str()?,
+                Token::Value => value = self.field_int()?,
+                Token::Slot => slot = Some(self.field_slot(&ItemType::Armor(0))?),
+                _ => return Err(self.error("armor field (@name, @def, @description, @value or @slot)")),
             }
         }
 
-        Some(Item::new_armor(&name, &description, value, def))
+        let armor = Item::new_armor(&name, &description, value, def);
+        Ok(match slot {
+            Some(slot) => armor.with_slot(slot),
+            None => armor,
+        })
+    }
+
+    fn fetch_effect_kind(&mut self) -> Result<Effect, ParseError> {
+        self.expect(Token::Colon, "':' after @effect")?;
+        match self.at() {
+            Token::Heal => Ok(Effect::Heal(self.field_int()?)),
+            Token::Damage => Ok(Effect::Damage(self.field_int()?)),
+            Token::Teleport => {
+                self.next_token();
+                self.expect(Token::Colon, "':' after @teleport")?;
+                match self.at() {
+                    Token::Start => {
+                        self.next_token();
+                        Ok(Effect::Teleport(Position::new(0, 0)))
+                    },
+                    Token::Random => {
+                        self.next_token();
+                        Ok(Effect::RandomTeleport)
+                    },
+                    Token::Int(_) => Ok(Effect::Teleport(self.position_value("@teleport:")?)),
+                    _ => Err(self.error("@start, @random or a position after @teleport:")),
+                }
+            },
+            Token::Str(_) => Ok(Effect::Status(self.status_value("@effect:")?)),
+            _ => Err(self.error("effect (@heal, @damage, @teleport or a status) after @effect:")),
+        }
     }
 
-    fn fetch_effect(&mut self) -> Option<Item> {
+    fn fetch_effect(&mut self) -> Result<Item, ParseError> {
         let mut name = String::new();
         let mut description = String::new();
         let mut value = 0;
         let mut effect = Effect::None;
 
-        while self.at() != &Token::Eof {
-            match self.next_token() {
-                Token::Name => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Str(s) => {
-                            name = s.to_string();
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                Token::Description => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Str(s) => {
-                            description = s.to_string();
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                Token::Value => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Int(i) => {
-                            value = *i;
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
+        while !self.at_block_end() {
+            match self.at() {
+                Token::Name => name = self.field_str()?,
+                Token::Description => description = self.field_str()?,
+                Token::Value => value = self.field_int()?,
                 Token::Effect => {
                     self.next_token();
-                    match self.next_token() {
-                        Token::Heal => {
-                            self.next_token();
-                            if let Token::Int(i) = self.next_token() {
-                                effect = Effect::Heal(*i);
-                            }
-                        },
-                        Token::Damage => {
-                            self.next_token();
-                            if let Token::Int(i) = self.next_token() {
-                                effect = Effect::Damage(*i);
-                            }
-                        },
-                        Token::Teleport => {
-                            self.next_token();
-                            match self.next_token() {
-                                Token::Start => {
-                                    effect = Effect::Teleport(Position::new(0, 0));
-                                },
-                                Token::Random => {
-                                    effect = Effect::RandomTeleport;
-                                }
-                                _ => {
-                                    break;
-                                }
-                            }
-                        }
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                _ => {
-                    break;
+                    effect = self.fetch_effect_kind()?;
                 },
+                _ => return Err(self.error("effect field (@name, @description, @value or @effect)")),
             }
         }
 
-        Some(Item::new_effect(&name, &description, value, effect))
+        Ok(Item::new_effect(&name, &description, value, effect))
     }
 
-    fn fetch_gold(&mut self) -> Option<Item> {
+    fn fetch_gold(&mut self) -> Result<Item, ParseError> {
         let mut value = 0;
 
-        match self.next_token() {
-            Token::Value => {
-                self.next_token();
-                match self.next_token() {
-                    Token::Int(i) => {
-                        value = *i;
-                    },
-                    _ => {
-                        return None;
-                    }
-                }
-            },
-            _ => {
-                return None;
+        while !self.at_block_end() {
+            match self.at() {
+                Token::Value => value = self.field_int()?,
+                _ => return Err(self.error("gold field (@value)")),
             }
         }
 
-        Some(Item::new_gold(value))
+        Ok(Item::new_gold(value))
     }
 
-    fn fetch_exp(&mut self) -> Option<Item> {
+    fn fetch_exp(&mut self) -> Result<Item, ParseError> {
         let mut name = "".to_string();
         let mut value = 0;
         let mut description = "".to_string();
 
-        while self.at() != &Token::Eof {
-            match self.next_token() {
-                Token::Value => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Int(i) => {
-                            value = *i;
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                Token::Name => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Str(s) => {
-                            name = s.to_string();
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                Token::Description => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Str(s) => {
-                            description = s.to_string();
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                _ => {
-                    break;
-                },
+        while !self.at_block_end() {
+            match self.at() {
+                Token::Value => value = self.field_int()?,
+                Token::Name => name = self.field_str()?,
+                Token::Description => description = self.field_str()?,
+                _ => return Err(self.error("exp field (@name, @description or @value)")),
             }
         }
 
-        Some(Item::new_exp(&name, &description, value))
+        Ok(Item::new_exp(&name, &description, value))
     }
 
-    pub fn parse_item(&mut self) -> Option<Item> {
-        match self.next_token() {
-            Token::Item => {
-                match self.next_token() {
-                    Token::Weapon => {
-                        self.fetch_weapon()
-                    },
-                    Token::Armor => {
-                        self.fetch_armor()
-                    },
-                    Token::Effect => {
-                        self.fetch_effect()
-                    },
-                    Token::Gold => {
-                        self.fetch_gold()
-                    },
-                    Token::Exp => {
-                        self.fetch_exp()
-                    },
-                    _ => None,
-                }
-            },
-            _ => None,
+    pub fn parse_item(&mut self) -> Result<Item, ParseError> {
+        self.parse_item_def().map(|definition| definition.item)
+    }
+
+    /// Parse `@item @<kind> [@treasure[: <rareness>]] <fields>`.
+    pub fn parse_item_def(&mut self) -> Result<ItemDef, ParseError> {
+        let origin = self.origin();
+        self.expect(Token::Item, "@item")?;
+        let kind = self.at().clone();
+        if !matches!(kind, Token::Weapon | Token::Armor | Token::Effect | Token::Gold | Token::Exp) {
+            return Err(self.error("item kind (@weapon, @armor, @effect, @gold or @exp) after @item"));
+        }
+        self.next_token();
+        let mut treasure = None;
+        if self.at() == &Token::Treasure {
+            self.next_token();
+            treasure = Some(1);
+            if self.at() == &Token::Colon {
+                self.next_token();
+                treasure = Some(self.expect_count("integer rareness after @treasure")?);
+            }
         }
+
+        let item = match kind {
+            Token::Weapon => self.fetch_weapon(),
+            Token::Armor => self.fetch_armor(),
+            Token::Effect => self.fetch_effect(),
+            Token::Gold => self.fetch_gold(),
+            _ => self.fetch_exp(),
+        }?;
+
+        Ok(ItemDef { item, treasure, origin })
+    }
+
+    /// Parse every top-level block of the source, in order.
+    pub fn parse_all(&mut self) -> Result<Vec<Definition>, ParseError> {
+        let mut definitions = vec![];
+        while self.at() != &Token::Eof {
+            let definition = match self.at() {
+                Token::Item => Definition::Item(self.parse_item_def()?),
+                Token::Monster => Definition::Monster(self.parse_monster()?),
+                Token::Player => Definition::Player(self.parse_player()?),
+                Token::Save => Definition::Save(self.parse_save()?),
+                Token::Hints => Definition::Hints(self.parse_hints()?),
+                Token::Generator => Definition::Generator(self.parse_generator()?),
+                Token::Combat => Definition::Combat(self.parse_combat()?),
+                Token::Map => Definition::Map(self.parse_map()?),
+                _ => return Err(self.error("definition (@item, @monster, @player, @save, @hints, @generator, @map or @combat)")),
+            };
+            definitions.push(definition);
+        }
+        Ok(definitions)
+    }
+
+    /// Consume a `@field: <name>` entry naming another definition.
+    fn field_reference(&mut self) -> Result<Reference, ParseError> {
+        let field = self.next_token();
+        self.expect(Token::Colon, &format!("':' after {}", field))?;
+        let origin = self.origin();
+        let name = self.expect_str(&format!("name after {}", field))?;
+        Ok(Reference { name, origin })
     }
 
-    pub fn load_monster(&mut self, items: &Vec<Item>) -> Option<Monster> {
+    pub fn parse_monster(&mut self) -> Result<MonsterDef, ParseError> {
+        let origin = self.origin();
         let mut name = "".to_string();
         let mut life = 0;
-        let mut weapon = Item::new_weapon("", "", 0, 0);
+        let mut weapon = None;
+        let mut armor = None;
         let mut drop = None;
         let mut rareness = 0;
+        let mut behaviour = Behaviour::Idle;
 
-        let is_monster = match self.next_token() {
-            Token::Monster => true,
-            _ => false,
-        };
-
-        if !is_monster {
-            println!("Not a monster");
-            return None;
-        }
+        self.expect(Token::Monster, "@monster")?;
 
-        while self.at() != &Token::Eof {
-            match self.next_token() {
-                Token::Name => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Str(s) => {
-                            name = s.to_string();
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                Token::Life => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Int(i) => {
-                            life = *i;
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                Token::Weapon => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Str(s) => {
-                            let item = items.iter().find(|item| item.name() == s);
-                            if let Some(item) = item {
-                                weapon = item.clone();
-                            }
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
+        while !self.at_block_end() {
+            match self.at() {
+                Token::Name => name = self.field_str()?,
+                Token::Life => life = self.field_int()?,
+                Token::Weapon => weapon = Some(self.field_reference()?),
+                Token::Armor => armor = Some(self.field_reference()?),
                 Token::Drop => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Str(s) => {
-                            let item = items.iter().find(|item| item.name() == s);
-                            if let Some(item) = item {
-                                if let Token::Int(qte) = self.next_token() {
-                                    drop = Some((item.clone(), *qte as u32));
-                                }
-                            }
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                Token::Rareness => {
-                    self.next_token();
-                    match self.next_token() {
-                        Token::Int(i) => {
-                            rareness = *i;
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
-                },
-                _ => {
-                    break;
+                    let item = self.field_reference()?;
+                    let qte = self.expect_count("integer after @drop item name")?;
+                    drop = Some((item, qte));
                 },
+                Token::Rareness => rareness = self.field_count()?,
+                Token::Behaviour => behaviour = self.parse_behaviour()?,
+                _ => return Err(self.error("monster field (@name, @life, @weapon, @armor, @drop, @rareness or @behaviour)")),
             }
         }
 
-        let (item, qte) = drop.unwrap_or((Item::new_gold(0), 0));
-        Some(Monster::new(&name, life, weapon, Some(item), qte, rareness as u32))
+        Ok(MonsterDef { name, life, weapon, armor, drop, rareness, behaviour, origin })
     }
 
-    fn parse_player_inventory(&mut self, items: &Vec<Item>) -> Vec<Item> {
+    /// Consume a `@behaviour: <name> [steps]` entry; patrol and chase take
+    /// their number of steps.
+    fn parse_behaviour(&mut self) -> Result<Behaviour, ParseError> {
+        self.next_token();
+        self.expect(Token::Colon, "':' after @behaviour")?;
+        let name = match self.at() {
+            Token::Str(name) if Behaviour::NAMES.contains(&name.as_str()) => name.clone(),
+            _ => return Err(self.error("behaviour (idle, wander, patrol <steps> or chase <steps>)")),
+        };
+        self.next_token();
+        let steps = match name.as_str() {
+            "patrol" | "chase" => Some(self.expect_count(&format!("number of steps after {}", name))?),
+            _ => None,
+        };
+        Behaviour::new(&name, steps).ok_or_else(|| self.error("behaviour"))
+    }
+
+    fn parse_player_inventory(&mut self) -> Result<Vec<(Reference, u32)>, ParseError> {
         let mut inventory = Vec::new();
 
         // Inventory look like this:
         // Token::Str("Gold") Token::Int(10) the int is the quantity
 
-        while self.at() != &Token::Eof {
-            match self.next_token() {
-                Token::Str(s) => {
-                    let item = items.iter().find(|item| item.name() == s);
-                    if let Some(item) = item {
-                        if let Token::Int(qte) = self.next_token() {
-                            for _ in 0..*qte {
-                                inventory.push(item.clone());
-                            }
-                        }
-                    }
-                },
-                _ => {
-                    break;
-                },
-            }
+        while let Token::Str(s) = self.at() {
+            let reference = Reference { name: s.clone(), origin: self.origin() };
+            self.next_token();
+            let qte = self.expect_count("count after @inventory item name")?;
+            inventory.push((reference, qte));
         }
 
-        inventory
+        Ok(inventory)
     }
 
-    pub fn parse_player(&mut self, item: &Vec<Item>) -> Player {
-        self.next_token();
+    pub fn parse_player(&mut self) -> Result<PlayerDef, ParseError> {
+        let origin = self.origin();
         let mut name = "".to_string();
         let mut life = 0;
         let mut max_life = 0;
         let mut weapon = None;
         let mut armor = None;
-        let mut exp = 0;
+        let mut equipment = Vec::new();
+        let mut experience = 0;
         let mut level = 0;
         let mut next_level = 0;
         let mut inventory = Vec::new();
-        let position = Position::new(0, 0);
+        let mut capacity = Inventory::DEFAULT_CAPACITY;
+        let mut position = Position::new(0, 0);
+        let mut statuses = Vec::new();
 
-        while self.at() != &Token::Eof {
-            match self.next_token() {
-                Token::Name => {
+        self.expect(Token::Player, "@player")?;
+
+        while !self.at_block_end() {
+            match self.at() {
+                Token::Name => name = self.field_str()?,
+                Token::Life => life = self.field_int()?,
+                Token::MaxLife => max_life = self.field_int()?,
+                Token::Weapon => weapon = Some(self.field_reference()?),
+                Token::Armor => armor = Some(self.field_reference()?),
+                Token::Equip => equipment.push(self.field_reference()?),
+                Token::Exp => experience = self.field_int()?,
+                Token::Level => level = self.field_int()?,
+                Token::ExpToLevelUp => next_level = self.field_int()?,
+                Token::Inventory => {
                     self.next_token();
-                    match self.next_token() {
-                        Token::Str(s) => {
-                            name = s.to_string();
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
+                    self.expect(Token::Colon, "':' after @inventory")?;
+                    inventory = self.parse_player_inventory()?;
                 },
-                Token::Life => {
+                Token::Capacity => capacity = self.field_count()? as usize,
+                Token::Position => {
                     self.next_token();
-                    match self.next_token() {
-                        Token::Int(i) => {
-                            life = *i;
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
+                    self.expect(Token::Colon, "':' after @position")?;
+                    position = self.position_value("@position:")?;
                 },
-                Token::MaxLife => {
+                Token::Status => {
                     self.next_token();
-                    match self.next_token() {
-                        Token::Int(i) => {
-                            max_life = *i;
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
+                    self.expect(Token::Colon, "':' after @status")?;
+                    statuses.push(self.status_value("@status:")?);
                 },
-                Token::Weapon => {
+                _ => return Err(self.error("player field (@name, @life, @max-life, @weapon, @armor, @equip, @exp, @level, @exp-to-level-up, @inventory, @capacity, @position or @status)")),
+            }
+        }
+
+        Ok(PlayerDef { name, life, max_life, weapon, armor, equipment, experience, level, next_level, inventory, capacity, position, statuses, origin })
+    }
+
+    /// Parse the event of a `@tile: <x> <y> <event>` entry.
+    fn parse_tile_event(&mut self) -> Result<TileDef, ParseError> {
+        match self.at() {
+            Token::Empty => {
+                self.next_token();
+                Ok(TileDef::Empty)
+            },
+            Token::End => {
+                self.next_token();
+                Ok(TileDef::End)
+            },
+            Token::StairsDown => {
+                self.next_token();
+                Ok(TileDef::StairsDown)
+            },
+            Token::StairsUp => {
+                self.next_token();
+                Ok(TileDef::StairsUp)
+            },
+            Token::Monster => {
+                let monster = self.field_reference()?;
+                let life = self.expect_int("integer life after @monster name")?;
+                let walked = match self.at() {
+                    Token::Int(_) => self.expect_count("steps walked after @monster life")?,
+                    _ => 0,
+                };
+                Ok(TileDef::Monster(monster, life, walked))
+            },
+            Token::Treasure => {
+                let item = self.field_reference()?;
+                let count = self.expect_count("count after @treasure item name")?;
+                Ok(TileDef::Treasure(item, count))
+            },
+            Token::Teleport => {
+                self.next_token();
+                self.expect(Token::Colon, "':' after @teleport")?;
+                Ok(TileDef::Teleport(self.position_value("@teleport:")?))
+            },
+            _ => Err(self.error("tile event (@empty, @monster, @treasure, @teleport, @end, @stairs-down or @stairs-up)")),
+        }
+    }
+
+    /// Parse the optional terrain keyword of a `@tile` entry, floor being
+    /// the default when none is given.
+    fn parse_terrain(&mut self) -> Terrain {
+        let terrain = match self.at() {
+            Token::Floor => Terrain::Floor,
+            Token::Wall => Terrain::Wall,
+            Token::Door => Terrain::Door,
+            Token::Water => Terrain::Water,
+            Token::Pit => Terrain::Pit,
+            _ => return Terrain::Floor,
+        };
+        self.next_token();
+        terrain
+    }
+
+    pub fn parse_save(&mut self) -> Result<SaveDef, ParseError> {
+        let origin = self.origin();
+        let mut seed = 0;
+        let mut turn = 0;
+        let mut current = 0;
+        let mut floors: Vec<FloorDef> = vec![];
+
+        self.expect(Token::Save, "@save")?;
+
+        while !self.at_block_end() {
+            match self.at() {
+                Token::Seed => {
+                    self.next_token();
+                    self.expect(Token::Colon, "':' after @seed")?;
+                    seed = match self.at() {
+                        Token::Int(i) => u64::try_from(*i).map_err(|_| self.error("integer after @seed (0 or more)"))?,
+                        _ => return Err(self.error("integer after @seed")),
+                    };
                     self.next_token();
-                    match self.next_token() {
-                        Token::Str(s) => {
-                            let item = item.iter().find(|item| item.name() == s);
-                            if let Some(item) = item {
-                                weapon = Some(item.clone());
-                            }
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
                 },
-                Token::Armor => {
+                Token::Turn => turn = self.field_count()?,
+                Token::Current => current = self.field_count()? as usize,
+                Token::Depth => {
                     self.next_token();
-                    match self.next_token() {
-                        Token::Str(s) => {
-                            let item = item.iter().find(|item| item.name() == s);
-                            if let Some(item) = item {
-                                armor = Some(item.clone());
-                            }
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
+                    self.expect(Token::Colon, "':' after @depth")?;
+                    self.expect(Token::Int(floors.len() as i64), &format!("{} after @depth (floors in order)", floors.len()))?;
+                    floors.push(FloorDef::default());
                 },
-                Token::Exp => {
+                Token::Tile => {
                     self.next_token();
-                    match self.next_token() {
-                        Token::Int(i) => {
-                            exp = *i;
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
+                    self.expect(Token::Colon, "':' after @tile")?;
+                    let position = self.position_value("@tile:")?;
+                    let terrain = self.parse_terrain();
+                    let event = self.parse_tile_event()?;
+                    Parser::last_floor(&mut floors).tiles.push((position, terrain, event));
                 },
-                Token::Level => {
+                Token::Visited => {
                     self.next_token();
-                    match self.next_token() {
-                        Token::Int(i) => {
-                            level = *i;
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
+                    self.expect(Token::Colon, "':' after @visited")?;
+                    let position = self.position_value("@visited:")?;
+                    Parser::last_floor(&mut floors).visited.push(position);
                 },
-                Token::ExpToLevelUp => {
+                Token::Sensed => {
                     self.next_token();
-                    match self.next_token() {
-                        Token::Int(i) => {
-                            next_level = *i;
-                        },
-                        _ => {
-                            break;
-                        }
-                    }
+                    self.expect(Token::Colon, "':' after @sensed")?;
+                    let position = self.position_value("@sensed:")?;
+                    Parser::last_floor(&mut floors).sensed.push(position);
                 },
-                Token::Inventory => {
+                _ => return Err(self.error("save field (@seed, @turn, @current, @depth, @tile, @visited or @sensed)")),
+            }
+        }
+        if floors.is_empty() {
+            floors.push(FloorDef::default());
+        }
+
+        Ok(SaveDef { seed, turn, floors, current, origin })
+    }
+
+    /// The floor `@save` entries go to, the top one until a `@depth` is read.
+    fn last_floor(floors: &mut Vec<FloorDef>) -> &mut FloorDef {
+        if floors.is_empty() {
+            floors.push(FloorDef::default());
+        }
+        floors.last_mut().unwrap()
+    }
+
+    pub fn parse_hints(&mut self) -> Result<HintsDef, ParseError> {
+        let origin = self.origin();
+        let mut radius = None;
+        let mut messages = vec![];
+
+        self.expect(Token::Hints, "@hints")?;
+
+        while !self.at_block_end() {
+            match self.at() {
+                Token::Radius => {
+                    self.next_token();
+                    self.expect(Token::Colon, "':' after @radius")?;
+                    radius = match self.at() {
+                        Token::Int(i) if (0..=Hints::MAX_RADIUS as i64).contains(i) => Some(*i as u32),
+                        _ => return Err(self.error(&format!("radius from 0 to {} after @radius", Hints::MAX_RADIUS))),
+                    };
                     self.next_token();
-                    inventory = self.parse_player_inventory(&item);
                 },
-                _ => {
-                    break;
+                Token::Hint => {
+                    self.next_token();
+                    self.expect(Token::Colon, "':' after @hint")?;
+                    let kind = match self.at() {
+                        Token::Monster => HintKind::Monster,
+                        Token::Treasure => HintKind::Treasure,
+                        Token::Teleport => HintKind::Teleport,
+                        Token::End => HintKind::End,
+                        _ => return Err(self.error("hint kind (@monster, @treasure, @teleport or @end)")),
+                    };
+                    self.next_token();
+                    messages.push((kind, self.expect_str("message after the hint kind")?));
                 },
+                _ => return Err(self.error("hints field (@radius or @hint)")),
             }
         }
 
-        let mut player = Player::new_all(
-            &name, 
-            life, 
-            max_life, 
-            position, 
-            weapon, 
-            armor, 
-            vec![],
-            exp, 
-            level, 
-            next_level, 
-        );
+        Ok(HintsDef { radius, messages, origin })
+    }
+
+    pub fn parse_generator(&mut self) -> Result<GeneratorDef, ParseError> {
+        let origin = self.origin();
+        let mut algorithm = String::new();
+        let mut options = vec![];
 
-        for item in inventory {
-            player.add_item(item);
+        self.expect(Token::Generator, "@generator")?;
+
+        while !self.at_block_end() {
+            match self.at() {
+                Token::Algorithm => algorithm = self.field_str()?,
+                Token::Option => {
+                    self.next_token();
+                    self.expect(Token::Colon, "':' after @option")?;
+                    let option = self.expect_str("option name after @option")?;
+                    options.push((option, self.expect_i64("integer value after the option name")?));
+                },
+                _ => return Err(self.error("generator field (@algorithm or @option)")),
+            }
         }
 
-        player
+        Ok(GeneratorDef { algorithm, options, origin })
+    }
+
+    pub fn parse_combat(&mut self) -> Result<CombatDef, ParseError> {
+        let origin = self.origin();
+        let mut min_damage = None;
+        let mut critical = None;
+        let mut variance = None;
+
+        self.expect(Token::Combat, "@combat")?;
+
+        while !self.at_block_end() {
+            match self.at() {
+                Token::MinDamage => min_damage = Some(self.field_count()?),
+                Token::Critical => {
+                    let chance = self.field_percent()?;
+                    let multiplier = self.expect_count("damage percent after the @critical chance")?;
+                    critical = Some((chance, multiplier));
+                },
+                Token::Variance => variance = Some(self.field_percent()?),
+                _ => return Err(self.error("combat field (@min-damage, @critical or @variance)")),
+            }
+        }
+
+        Ok(CombatDef { min_damage, critical, variance, origin })
+    }
+
+    pub fn parse_map(&mut self) -> Result<MapDef, ParseError> {
+        let origin = self.origin();
+        let mut name = String::new();
+        let mut rows = vec![];
+        let mut legend = vec![];
+        let mut origin_cell = None;
+
+        self.expect(Token::Map, "@map")?;
+
+        while !self.at_block_end() {
+            match self.at() {
+                Token::Name => name = self.field_str()?,
+                Token::Row => rows.push(self.field_str()?),
+                Token::Origin => {
+                    self.next_token();
+                    self.expect(Token::Colon, "':' after @origin")?;
+                    origin_cell = Some(self.position_value("@origin:")?);
+                },
+                Token::Legend => legend.push(self.parse_legend()?),
+                _ => return Err(self.error("map field (@name, @row, @origin or @legend)")),
+            }
+        }
+
+        Ok(MapDef { name, rows, legend, origin_cell, origin })
+    }
+
+    /// Parse `@legend: <symbol> [terrain] <tile event>`, where a teleport may
+    /// also lead to another symbol: `@teleport: <symbol>`.
+    fn parse_legend(&mut self) -> Result<LegendDef, ParseError> {
+        self.expect(Token::Legend, "@legend")?;
+        self.expect(Token::Colon, "':' after @legend")?;
+        let origin = self.origin();
+        let symbol = match self.at() {
+            Token::Str(symbol) if symbol.chars().count() == 1 => symbol.chars().next().unwrap(),
+            _ => return Err(self.error("single character symbol after @legend (quote digits and punctuation)")),
+        };
+        self.next_token();
+        let terrain = self.parse_terrain();
+
+        let event = match self.at() {
+            Token::Teleport => {
+                self.next_token();
+                self.expect(Token::Colon, "':' after @teleport")?;
+                match self.at() {
+                    Token::Str(target) if target.chars().count() == 1 => {
+                        let target = target.chars().next().unwrap();
+                        self.next_token();
+                        LegendEvent::TeleportTo(target)
+                    },
+                    Token::Int(_) => LegendEvent::Tile(TileDef::Teleport(self.position_value("@teleport:")?)),
+                    _ => return Err(self.error("symbol or coordinates after @teleport")),
+                }
+            },
+            _ => LegendEvent::Tile(self.parse_tile_event()?),
+        };
+        Ok(LegendDef { symbol, terrain, event, origin })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::data_loader::Lexer;
 
     #[test]
     fn test_parser_weapon() {
         let input = "@item @weapon @name: Sword @atk: 10 @description: A sword @value: 100";
         let mut lexer = Lexer::new(input.to_string());
-        let tokens = lexer.lex();
+        let tokens = lexer.lex_spanned();
         println!("{:?}", tokens);
         let mut parser = Parser::new(tokens);
         let item = parser.parse_item();
-        assert_eq!(item, Some(Item::new_weapon("Sword", "A sword", 100, 10)));
+        assert_eq!(item, Ok(Item::new_weapon("Sword", "A sword", 100, 10)));
     }
 
     #[test]
     fn test_parser_armor() {
         let input = "@item @armor @name: Shield @def: 10 @description: A shield @value: 100";
         let mut lexer = Lexer::new(input.to_string());
-        let tokens = lexer.lex();
+        let tokens = lexer.lex_spanned();
         println!("{:?}", tokens);
         let mut parser = Parser::new(tokens);
         let item = parser.parse_item();
-        assert_eq!(item, Some(Item::new_armor("Shield", "A shield", 100, 10)));
+        assert_eq!(item, Ok(Item::new_armor("Shield", "A shield", 100, 10)));
     }
 
     #[test]
     fn test_parser_gold() {
         let input = "@item @gold @value: 100";
         let mut lexer = Lexer::new(input.to_string());
-        let tokens = lexer.lex();
+        let tokens = lexer.lex_spanned();
         println!("{:?}", tokens);
         let mut parser = Parser::new(tokens);
         let item = parser.parse_item();
-        assert_eq!(item, Some(Item::new_gold(100)));
+        assert_eq!(item, Ok(Item::new_gold(100)));
     }
 
     #[test]
     fn test_parser_exp() {
         let input = "@item @exp @name: Exp @value: 100 @description: An experience point";
         let mut lexer = Lexer::new(input.to_string());
-        let tokens = lexer.lex();
+        let tokens = lexer.lex_spanned();
         println!("{:?}", tokens);
         let mut parser = Parser::new(tokens);
         let item = parser.parse_item();
-        assert_eq!(item, Some(Item::new_exp("Exp", "An experience point", 100)));
+        assert_eq!(item, Ok(Item::new_exp("Exp", "An experience point", 100)));
     }
 
     #[test]
     fn test_parser_effect() {
         let input = "@item @effect @name: Heal @value: 10 @description: Heal ten HP @effect: @heal: 10";
         let mut lexer = Lexer::new(input.to_string());
-        let tokens = lexer.lex();
+        let tokens = lexer.lex_spanned();
         println!("{:?}", tokens);
         let mut parser = Parser::new(tokens);
         let item = parser.parse_item();
-        assert_eq!(item, Some(Item::new_effect("Heal", "Heal ten HP", 10, Effect::Heal(10))));
+        assert_eq!(item, Ok(Item::new_effect("Heal", "Heal ten HP", 10, Effect::Heal(10))));
     }
 
     #[test]
     fn test_parser_monster() {
-        let input_item = "@item @gold @value: 10";
-        let input_weapon = "@item @weapon @name: Sword @atk: 10 @description: A sword @value: 100";
-        
-        let mut lexer = Lexer::new(input_item.to_string());
-        let tokens = lexer.lex();
-        let mut parser = Parser::new(tokens);
-        let mut items = vec![parser.parse_item().unwrap()];
-        assert_eq!(items[0], Item::new_gold(10));
-        
-        let mut lexer = Lexer::new(input_weapon.to_string());
-        let tokens = lexer.lex();
-        let mut parser = Parser::new(tokens);
-        items.push(parser.parse_item().unwrap());
-        assert_eq!(items[1], Item::new_weapon("Sword", "A sword", 100, 10));
-        
-        
-        let input = "@monster @name: Goblin @life: 10 @weapon: Sword @drop: Gold 10 @rareness: 1";
+        let input = "@monster @name: Goblin @life: 10 @weapon: Sword @armor: Shield @drop: Gold 10 @rareness: 1 @behaviour: chase 5";
         let mut lexer = Lexer::new(input.to_string());
-        let tokens = lexer.lex();
+        let tokens = lexer.lex_spanned();
         println!("{:?}", tokens);
         let mut parser = Parser::new(tokens);
-        let monster = parser.load_monster(&items);
-        assert_eq!(monster, Some(Monster::new("Goblin", 10, Item::new_weapon("Sword", "A sword", 100, 10), Some(Item::new_gold(10)), 10, 1)));
+        let monster = parser.parse_monster().unwrap();
+        assert_eq!(monster.name, "Goblin");
+        assert_eq!(monster.life, 10);
+        assert_eq!(monster.weapon.map(|weapon| weapon.name), Some("Sword".to_string()));
+        assert_eq!(monster.armor.map(|armor| armor.name), Some("Shield".to_string()));
+        assert_eq!(monster.drop.map(|(drop, qte)| (drop.name, qte)), Some(("Gold".to_string(), 10)));
+        assert_eq!(monster.rareness, 1);
+        assert_eq!(monster.behaviour, Behaviour::Chase(5));
+    }
+
+    #[test]
+    fn test_parser_negative_counts() {
+        let monster = |input: &str| Parser::new(Lexer::new(input.to_string()).lex_spanned()).parse_monster().unwrap_err().to_string();
+        assert_eq!(monster("@monster @drop: Gold -1"), "<input>:1:22: expected integer after @drop item name (0 or more), found integer -1");
+        assert_eq!(monster("@monster @rareness: -3"), "<input>:1:21: expected count after @rareness (0 or more), found integer -3");
+        let item = Parser::new(Lexer::new("@item @gold @treasure: -2".to_string()).lex_spanned()).parse_item_def().unwrap_err();
+        assert_eq!(item.to_string(), "<input>:1:24: expected integer rareness after @treasure (0 or more), found integer -2");
+
+        let save = |input: &str| Parser::new(Lexer::new(input.to_string()).lex_spanned()).parse_save().unwrap_err().to_string();
+        assert_eq!(save("@save @seed: -5"), "<input>:1:14: expected integer after @seed (0 or more), found integer -5");
+        assert_eq!(save("@save @turn: -1"), "<input>:1:14: expected count after @turn (0 or more), found integer -1");
+        assert_eq!(save("@save @current: -1"), "<input>:1:17: expected count after @current (0 or more), found integer -1");
+        let turn = Parser::new(Lexer::new("@save @seed: 7 @turn: 4000000000".to_string()).lex_spanned()).parse_save().unwrap();
+        assert_eq!((turn.seed, turn.turn), (7, 4000000000));
+    }
+
+    #[test]
+    fn test_parser_monster_behaviour() {
+        let parse = |input: &str| Parser::new(Lexer::new(input.to_string()).lex_spanned()).parse_monster().map(|monster| monster.behaviour);
+        assert_eq!(parse("@monster @name: Rat"), Ok(Behaviour::Idle));
+        assert_eq!(parse("@monster @behaviour: wander @name: Rat"), Ok(Behaviour::Wander));
+        assert_eq!(parse("@monster @behaviour: patrol 4"), Ok(Behaviour::Patrol(4)));
+        assert_eq!(parse("@monster @behaviour: fly").unwrap_err().to_string(), "<input>:1:22: expected behaviour (idle, wander, patrol <steps> or chase <steps>), found 'fly'");
+        assert_eq!(parse("@monster @behaviour: chase @name: Rat").unwrap_err().to_string(), "<input>:1:28: expected number of steps after chase, found @name");
+        assert_eq!(parse("@monster @behaviour: patrol -3").unwrap_err().to_string(), "<input>:1:29: expected number of steps after patrol (0 or more), found integer -3");
+    }
+
+    #[test]
+    fn test_parser_status() {
+        let parse = |input: &str| Parser::new(Lexer::new(input.to_string()).lex_spanned()).parse_item();
+        let poison = StatusEffect::new(StatusKind::Poison, 3, 5);
+        assert_eq!(parse("@item @effect @name: Venom @effect: poison 3 for 5"), Ok(Item::new_effect("Venom", "", 0, Effect::Status(poison))));
+        assert_eq!(parse("@item @weapon @name: Fang @atk: 2 @effect: poison 3 for 5 @value: 1"), Ok(Item::new_weapon("Fang", "", 1, 2).with_inflicts(poison)));
+        assert_eq!(parse("@item @weapon @effect: sleep 3 for 5").unwrap_err().to_string(), "<input>:1:24: expected status (poison, regeneration, blindness, haste, stun, strength) after @effect:, found 'sleep'");
+        assert_eq!(parse("@item @effect @effect: haste 0 5").unwrap_err().to_string(), "<input>:1:32: expected 'for' after the power of haste, found integer 5");
+        assert_eq!(parse("@item @effect @effect: poison 3 for -2").unwrap_err().to_string(), "<input>:1:37: expected number of turns after poison 3 for (0 or more), found integer -2");
+
+        let player = Parser::new(Lexer::new("@player @status: stun 0 for 2 @status: strength 4 for 9".to_string()).lex_spanned()).parse_player().unwrap();
+        assert_eq!(player.statuses, vec![StatusEffect::new(StatusKind::Stun, 0, 2), StatusEffect::new(StatusKind::Strength, 4, 9)]);
+    }
+
+    #[test]
+    fn test_parser_slot() {
+        let parse = |input: &str| Parser::new(Lexer::new(input.to_string()).lex_spanned()).parse_item();
+        assert_eq!(parse("@item @armor @name: Helmet @slot: head @def: 2"), Ok(Item::new_armor("Helmet", "", 0, 2).with_slot(Slot::Head)));
+        assert_eq!(parse("@item @weapon @slot: off-hand @name: Dagger"), Ok(Item::new_weapon("Dagger", "", 0, 0).with_slot(Slot::OffHand)));
+        assert_eq!(parse("@item @weapon @slot: head").unwrap_err().to_string(), "<input>:1:22: expected slot (main-hand, off-hand) after @slot:, found 'head'");
+        assert_eq!(parse("@item @armor @slot: main-hand").unwrap_err().to_string(), "<input>:1:21: expected slot (off-hand, head, body, ring) after @slot:, found 'main-hand'");
+
+        let player = Parser::new(Lexer::new("@player @equip: Helmet @weapon: Sword @equip: Ring".to_string()).lex_spanned()).parse_player().unwrap();
+        let equipment: Vec<String> = player.equipment.into_iter().map(|reference| reference.name).collect();
+        assert_eq!(equipment, vec!["Helmet", "Ring"]);
     }
 
     #[test]
     fn test_parser_player() {
-        let input_item = "@item @gold @value: 10";
-        let input_weapon = "@item @weapon @name: Sword @atk: 10 @description: A sword @value: 100";
-        
-        let mut lexer = Lexer::new(input_item.to_string());
-        let tokens = lexer.lex();
-        let mut parser = Parser::new(tokens);
-        let mut items = vec![parser.parse_item().unwrap()];
-        assert_eq!(items[0], Item::new_gold(10));
-        
-        let mut lexer = Lexer::new(input_weapon.to_string());
-        let tokens = lexer.lex();
-        let mut parser = Parser::new(tokens);
-        items.push(parser.parse_item().unwrap());
-        assert_eq!(items[1], Item::new_weapon("Sword", "A sword", 100, 10));
-        
         let input = "@player @name: Player @life: 10 @max-life: 100 @weapon: Sword @exp: 10 @level: 1 @exp-to-level-up: 100 @inventory: Gold 10";
         let mut lexer = Lexer::new(input.to_string());
-        let tokens = lexer.lex();
+        let tokens = lexer.lex_spanned();
         println!("{:?}", tokens);
         let mut parser = Parser::new(tokens);
-        let player = parser.parse_player(&items);
-        assert_eq!(player, 
-            Player::new_all(
-                "Player", 
-                10, 
-                100, 
-                Position::new(0, 0), 
-                Some(Item::new_weapon("Sword", "A sword", 100, 10)), 
-                None, 
-                vec![Item::new_gold(100)], 
-                10, 
-                1, 
-                100
-            ));
-    }
-}
\ No newline at end of file
+        let player = parser.parse_player().unwrap();
+        assert_eq!(player.name, "Player");
+        assert_eq!((player.life, player.max_life), (10, 100));
+        assert_eq!(player.weapon.map(|weapon| weapon.name), Some("Sword".to_string()));
+        assert_eq!(player.armor, None);
+        assert_eq!((player.experience, player.level, player.next_level), (10, 1, 100));
+        assert_eq!(player.inventory.len(), 1);
+        assert_eq!(player.inventory[0].0.name, "Gold");
+        assert_eq!(player.inventory[0].0.origin.span, Span::new(1, 116));
+        assert_eq!(player.inventory[0].1, 10);
+
+        let error = Parser::new(Lexer::new("@player @inventory: Gold 2 heal -1".to_string()).lex_spanned()).parse_player().unwrap_err();
+        assert_eq!(error.to_string(), "<input>:1:33: expected count after @inventory item name (0 or more), found integer -1");
+        let error = Parser::new(Lexer::new("@player @capacity: -1".to_string()).lex_spanned()).parse_player().unwrap_err();
+        assert_eq!(error.to_string(), "<input>:1:20: expected count after @capacity (0 or more), found integer -1");
+    }
+
+    #[test]
+    fn test_parser_error_position() {
+        let input = "@monster\n@name: slime\n@life: 10\n@drop: exp @rareness: 1";
+        let tokens = Lexer::new(input.to_string()).lex_spanned();
+        let mut parser = Parser::new(tokens).with_file(Path::new("slime.blind"));
+        let error = parser.parse_monster().unwrap_err();
+        assert_eq!(error.line(), 4);
+        assert_eq!(error.column(), 12);
+        assert_eq!(error.found(), &Token::Rareness);
+        assert_eq!(error.to_string(), "slime.blind:4:12: expected integer after @drop item name, found @rareness");
+    }
+
+    #[test]
+    fn test_parser_error_unknown_field() {
+        let input = "@item @weapon @name: Sword @def: 10";
+        let tokens = Lexer::new(input.to_string()).lex_spanned();
+        let mut parser = Parser::new(tokens);
+        let error = parser.parse_item().unwrap_err();
+        assert_eq!(error.to_string(), "<input>:1:28: expected weapon field (@name, @atk, @description, @value, @effect or @slot), found @def");
+    }
+
+    #[test]
+    fn test_parser_error_eof() {
+        let input = "@item @armor @def:";
+        let tokens = Lexer::new(input.to_string()).lex_spanned();
+        let mut parser = Parser::new(tokens);
+        let error = parser.parse_item().unwrap_err();
+        assert_eq!(error.found(), &Token::Eof);
+        assert_eq!(error.expected(), "integer after @def");
+    }
+
+    #[test]
+    fn test_parser_parse_all() {
+        let input = "@item @gold @value: 1\n@monster @name: slime @life: 10\n@item @weapon @treasure: 3 @name: stick @atk: 5";
+        let tokens = Lexer::new(input.to_string()).lex_spanned();
+        let definitions = Parser::new(tokens).parse_all().unwrap();
+        assert_eq!(definitions.len(), 3);
+        match &definitions[0] {
+            Definition::Item(definition) => {
+                assert_eq!(definition.item, Item::new_gold(1));
+                assert_eq!(definition.treasure, None);
+            },
+            other => panic!("unexpected definition {:?}", other),
+        }
+        match &definitions[1] {
+            Definition::Monster(definition) => assert_eq!(definition.origin.span, Span::new(2, 1)),
+            other => panic!("unexpected definition {:?}", other),
+        }
+        match &definitions[2] {
+            Definition::Item(definition) => {
+                assert_eq!(definition.item, Item::new_weapon("stick", "", 0, 5));
+                assert_eq!(definition.treasure, Some(3));
+            },
+            other => panic!("unexpected definition {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parser_parse_all_example() {
+        let input = include_str!("../../item.blind.example");
+        let tokens = Lexer::new(input.to_string()).lex_spanned();
+        let definitions = Parser::new(tokens).parse_all().unwrap();
+        let names: Vec<&str> = definitions.iter().map(|definition| match definition {
+            Definition::Item(definition) => definition.item.name(),
+            other => panic!("unexpected definition {:?}", other),
+        }).collect();
+        assert_eq!(names, vec!["stick", "helmet", "Gold", "heal", "bomb", "warp", "return", "experience", "dagger", "swiftness"]);
+    }
+
+    #[test]
+    fn test_parser_parse_all_unknown_block() {
+        let input = "@item @gold @value: 1\nstray";
+        let tokens = Lexer::new(input.to_string()).lex_spanned();
+        let error = Parser::new(tokens).parse_all().unwrap_err();
+        assert_eq!(error.to_string(), "<input>:2:1: expected gold field (@value), found 'stray'");
+    }
+
+    #[test]
+    fn test_parser_hints() {
+        let input = include_str!("../../data/hints.blind");
+        let tokens = Lexer::new(input.to_string()).lex_spanned();
+        let hints = Parser::new(tokens).parse_hints().unwrap();
+        assert_eq!(hints.radius, Some(1));
+        assert_eq!(hints.messages.len(), 4);
+        assert_eq!(hints.messages[0], (HintKind::Monster, "You hear growling.".to_string()));
+        assert_eq!(hints.messages[3], (HintKind::End, "You feel a draft.".to_string()));
+    }
+
+    #[test]
+    fn test_parser_hints_unknown_kind() {
+        let input = "@hints @hint: @empty \"nothing\"";
+        let tokens = Lexer::new(input.to_string()).lex_spanned();
+        let error = Parser::new(tokens).parse_hints().unwrap_err();
+        assert_eq!(error.to_string(), "<input>:1:15: expected hint kind (@monster, @treasure, @teleport or @end), found @empty");
+    }
+
+    #[test]
+    fn test_parser_hints_radius() {
+        let parse = |input: &str| Parser::new(Lexer::new(input.to_string()).lex_spanned()).parse_hints();
+        assert_eq!(parse("@hints @radius: 10").unwrap().radius, Some(10));
+        assert_eq!(parse("@hints @radius: 2000000000").unwrap_err().to_string(), "<input>:1:17: expected radius from 0 to 10 after @radius, found integer 2000000000");
+        assert_eq!(parse("@hints @radius: -1").unwrap_err().to_string(), "<input>:1:17: expected radius from 0 to 10 after @radius, found integer -1");
+    }
+
+    #[test]
+    fn test_parser_combat() {
+        let input = include_str!("../../data/combat.blind");
+        let tokens = Lexer::new(input.to_string()).lex_spanned();
+        let combat = Parser::new(tokens).parse_combat().unwrap();
+        assert_eq!(combat.min_damage, Some(1));
+        assert_eq!(combat.critical, Some((5, 200)));
+        assert_eq!(combat.variance, Some(20));
+
+        let combat = |input: &str| Parser::new(Lexer::new(input.to_string()).lex_spanned()).parse_combat().unwrap_err().to_string();
+        assert_eq!(combat("@combat @critical: 150 50"), "<input>:1:20: expected percent after @critical (0 to 100), found integer 150");
+        assert_eq!(combat("@combat @critical: 5 -3"), "<input>:1:22: expected damage percent after the @critical chance (0 or more), found integer -3");
+        assert_eq!(combat("@combat @variance: -4"), "<input>:1:20: expected percent after @variance (0 to 100), found integer -4");
+        assert_eq!(combat("@combat @min-damage: -1"), "<input>:1:22: expected count after @min-damage (0 or more), found integer -1");
+        let error = Parser::new(Lexer::new("@combat @critical: 5".to_string()).lex_spanned()).parse_combat().unwrap_err();
+        assert_eq!(error.to_string(), "<input>:1:21: expected damage percent after the @critical chance, found end of file");
+    }
+
+    #[test]
+    fn test_parser_map() {
+        let input = include_str!("../../data/maps/tutorial.blind");
+        let tokens = Lexer::new(input.to_string()).lex_spanned();
+        let map = Parser::new(tokens).parse_map().unwrap();
+        assert_eq!(map.name, "tutorial");
+        assert_eq!(map.rows.len(), 8);
+        assert_eq!(map.rows[1], "#P.g#..s.#");
+        assert_eq!(map.origin_cell, None);
+        assert_eq!(map.legend.len(), 6);
+        assert_eq!(map.legend[3].event, LegendEvent::TeleportTo('b'));
+        assert_eq!(map.legend[4].event, LegendEvent::Tile(TileDef::Empty));
+        assert!(matches!(&map.legend[1].event, LegendEvent::Tile(TileDef::Treasure(item, 10)) if item.name == "heal"));
+    }
+
+    #[test]
+    fn test_parser_map_errors() {
+        let error = Parser::new(Lexer::new("@map @legend: ab @end".to_string()).lex_spanned()).parse_map().unwrap_err();
+        assert_eq!(error.to_string(), "<input>:1:15: expected single character symbol after @legend (quote digits and punctuation), found 'ab'");
+        let error = Parser::new(Lexer::new("@map @legend: a @teleport: @end".to_string()).lex_spanned()).parse_map().unwrap_err();
+        assert_eq!(error.to_string(), "<input>:1:28: expected symbol or coordinates after @teleport, found @end");
+        let error = Parser::new(Lexer::new("@map @legend: s @monster: slime 5 -1".to_string()).lex_spanned()).parse_map().unwrap_err();
+        assert_eq!(error.to_string(), "<input>:1:35: expected steps walked after @monster life (0 or more), found integer -1");
+        let error = Parser::new(Lexer::new("@map @legend: a @treasure: heal -1".to_string()).lex_spanned()).parse_map().unwrap_err();
+        assert_eq!(error.to_string(), "<input>:1:33: expected count after @treasure item name (0 or more), found integer -1");
+    }
+
+    #[test]
+    fn test_parser_generator() {
+        let input = include_str!("../../data/generator.blind");
+        let tokens = Lexer::new(input.to_string()).lex_spanned();
+        let generator = Parser::new(tokens).parse_generator().unwrap();
+        assert_eq!(generator.algorithm, "walk");
+        assert_eq!(generator.options, vec![("steps".to_string(), 1000), ("exit".to_string(), 20)]);
+    }
+}
@@ -0,0 +1,805 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use super::definition::{CombatDef, Definition, FloorDef, GeneratorDef, HintsDef, ItemDef, LegendEvent, MapDef, MonsterDef, Origin, PlayerDef, Reference, SaveDef, TileDef};
+use crate::combat::damage::Formula;
+use crate::map::{hints::Hints, item::{Item, ItemType}, knowledge::Knowledge, monster::Monster, Event, Map, Position};
+use crate::map::dungeon::{Dungeon, Floor};
+use crate::map::generator::{Generator, GeneratorError};
+use crate::map::population::{Density, Population};
+use crate::map::tile::{Terrain, Tile};
+use crate::player::{equipment::{Equipment, Slot}, inventory::Inventory, Player};
+
+/// The kinds of definitions a `.blind` file can contain.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum Kind {
+    Item,
+    Monster,
+    Player,
+    Save,
+    Hints,
+    Generator,
+    Map,
+    Combat,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Item => write!(f, "item"),
+            Kind::Monster => write!(f, "monster"),
+            Kind::Player => write!(f, "player"),
+            Kind::Save => write!(f, "save"),
+            Kind::Hints => write!(f, "hints"),
+            Kind::Generator => write!(f, "generator"),
+            Kind::Map => write!(f, "map"),
+            Kind::Combat => write!(f, "combat"),
+        }
+    }
+}
+
+/// Problem with the grid or the legend of a `@map` block.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MapError {
+    /// A grid character that is neither built in nor in the legend, at its
+    /// column and row.
+    UnknownSymbol { symbol: char, cell: Position },
+    DuplicateSymbol(char),
+    /// More than one `P`, or a `P` and an `@origin`.
+    SeveralStarts,
+    /// A teleport to a symbol drawn no or several times.
+    AmbiguousTarget(char),
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::UnknownSymbol { symbol, cell } => {
+                write!(f, "unknown symbol '{}' at column {}, row {}", symbol, cell.x(), cell.y())
+            },
+            MapError::DuplicateSymbol(symbol) => write!(f, "symbol '{}' is in the legend twice", symbol),
+            MapError::SeveralStarts => write!(f, "more than one start (P or @origin)"),
+            MapError::AmbiguousTarget(symbol) => write!(f, "teleport target '{}' must be drawn exactly once", symbol),
+        }
+    }
+}
+
+/// Problem found while resolving the definitions collected in a `Registry`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ValidationError {
+    DanglingReference { kind: Kind, reference: Reference },
+    DuplicateName { kind: Kind, name: String, first: Origin, second: Origin },
+    InvalidGenerator { origin: Origin, error: GeneratorError },
+    InvalidMap { name: String, origin: Origin, error: MapError },
+    MissingFloor { origin: Origin, floor: usize },
+    /// The `@inventory` of the player needs more slots than its capacity.
+    InventoryFull { origin: Origin, capacity: usize },
+    /// The player is given an item to equip that is neither a weapon nor an
+    /// armor.
+    NotEquipment { origin: Origin, item: String },
+    /// The player is given more than one item to equip in the same slot.
+    SlotTaken { origin: Origin, slot: Slot },
+    MissingPlayer,
+    MissingSave,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::DanglingReference { kind, reference } => {
+                write!(f, "{}: unknown {} '{}'", reference.origin, kind, reference.name)
+            },
+            ValidationError::DuplicateName { kind, name, first, second } => {
+                write!(f, "{}: duplicate {} '{}', first defined at {}", second, kind, name, first)
+            },
+            ValidationError::InvalidGenerator { origin, error } => write!(f, "{}: {}", origin, error),
+            ValidationError::InvalidMap { name, origin, error } => write!(f, "{}: map '{}': {}", origin, name, error),
+            ValidationError::MissingFloor { origin, floor } => write!(f, "{}: current floor {} is not saved", origin, floor),
+            ValidationError::InventoryFull { origin, capacity } => write!(f, "{}: the inventory does not fit in {} slots", origin, capacity),
+            ValidationError::NotEquipment { origin, item } => write!(f, "{}: '{}' cannot be equipped", origin, item),
+            ValidationError::SlotTaken { origin, slot } => write!(f, "{}: more than one item equipped in the {} slot", origin, slot),
+            ValidationError::MissingPlayer => write!(f, "no @player definition"),
+            ValidationError::MissingSave => write!(f, "no @save definition"),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// The state of an interrupted run, read from a `@save` block.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SavedRun {
+    pub seed: u64,
+    pub turn: u32,
+    pub dungeon: Dungeon,
+}
+
+/// Everything loaded from the content directory, with references resolved.
+#[derive(Debug)]
+pub struct Catalog {
+    items: Vec<Item>,
+    treasures: HashMap<String, u32>,
+    monsters: Vec<Monster>,
+    player: Player,
+    hints: Hints,
+    generator: Option<Generator>,
+    maps: BTreeMap<String, Map>,
+    formula: Formula,
+    saved: Option<SavedRun>,
+}
+
+impl Catalog {
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    /// Whether the item was marked `@treasure`.
+    pub fn is_treasure(&self, item: &Item) -> bool {
+        self.treasures.contains_key(item.name())
+    }
+
+    /// The rareness given to `@treasure`, if the item is one.
+    pub fn treasure_rareness(&self, item: &Item) -> Option<u32> {
+        self.treasures.get(item.name()).copied()
+    }
+
+    /// Items marked `@treasure`, in definition order.
+    pub fn treasures(&self) -> impl Iterator<Item = &Item> {
+        self.items.iter().filter(|item| self.is_treasure(item))
+    }
+
+    pub fn monsters(&self) -> &[Monster] {
+        &self.monsters
+    }
+
+    /// Every monster and treasure, to populate generated maps with.
+    pub fn population(&self, density: Density) -> Population {
+        let mut population = Population::new(density);
+        for monster in &self.monsters {
+            population.add_monster(monster.clone());
+        }
+        for item in self.treasures() {
+            population.add_treasure(item.clone(), self.treasures[item.name()]);
+        }
+        population
+    }
+
+    pub fn player(&self) -> &Player {
+        &self.player
+    }
+
+    /// The `@hints` block applied over `Hints::default`.
+    pub fn hints(&self) -> &Hints {
+        &self.hints
+    }
+
+    /// The map generator chosen by a `@generator` block, if any.
+    pub fn generator(&self) -> Option<&Generator> {
+        self.generator.as_ref()
+    }
+
+    /// The `@combat` block applied over `Formula::default`.
+    pub fn formula(&self) -> &Formula {
+        &self.formula
+    }
+
+    /// The hand-drawn `@map` blocks, by name.
+    pub fn maps(&self) -> &BTreeMap<String, Map> {
+        &self.maps
+    }
+
+    pub fn map(&self, name: &str) -> Option<&Map> {
+        self.maps.get(name)
+    }
+
+    pub fn into_player(self) -> Player {
+        self.player
+    }
+
+    /// The `@save` block, if one was loaded.
+    pub fn take_saved(&mut self) -> Option<SavedRun> {
+        self.saved.take()
+    }
+}
+
+/// Collects every definition first, then resolves references by name in a
+/// second pass so the order files are loaded in does not matter.
+#[derive(Debug, Default)]
+pub struct Registry {
+    items: Vec<ItemDef>,
+    monsters: Vec<MonsterDef>,
+    players: Vec<PlayerDef>,
+    saves: Vec<SaveDef>,
+    hints: Vec<HintsDef>,
+    generators: Vec<GeneratorDef>,
+    maps: Vec<MapDef>,
+    combats: Vec<CombatDef>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    pub fn add_item(&mut self, item: ItemDef) {
+        self.items.push(item);
+    }
+
+    pub fn add_monster(&mut self, monster: MonsterDef) {
+        self.monsters.push(monster);
+    }
+
+    pub fn add_player(&mut self, player: PlayerDef) {
+        self.players.push(player);
+    }
+
+    pub fn add(&mut self, definition: Definition) {
+        match definition {
+            Definition::Item(item) => self.add_item(item),
+            Definition::Monster(monster) => self.add_monster(monster),
+            Definition::Player(player) => self.add_player(player),
+            Definition::Save(save) => self.saves.push(save),
+            Definition::Hints(hints) => self.hints.push(hints),
+            Definition::Generator(generator) => self.generators.push(generator),
+            Definition::Map(map) => self.maps.push(map),
+            Definition::Combat(combat) => self.combats.push(combat),
+        }
+    }
+
+    fn index<'a, T>(kind: Kind, definitions: impl Iterator<Item = (&'a str, &'a Origin, &'a T)>, errors: &mut Vec<ValidationError>) -> HashMap<&'a str, (&'a Origin, &'a T)> {
+        let mut index: HashMap<&str, (&Origin, &T)> = HashMap::new();
+        for (name, origin, definition) in definitions {
+            match index.get(name) {
+                Some((first, _)) => errors.push(ValidationError::DuplicateName {
+                    kind,
+                    name: name.to_string(),
+                    first: (*first).clone(),
+                    second: origin.clone(),
+                }),
+                None => {
+                    index.insert(name, (origin, definition));
+                },
+            }
+        }
+        index
+    }
+
+    /// Check every definition and build the resolved `Catalog`, or report
+    /// all the problems found at once.
+    pub fn resolve(&self) -> Result<Catalog, Vec<ValidationError>> {
+        let mut errors = vec![];
+
+        let items = Registry::index(
+            Kind::Item,
+            self.items.iter().map(|definition| (definition.item.name(), &definition.origin, &definition.item)),
+            &mut errors,
+        );
+        Registry::index(
+            Kind::Monster,
+            self.monsters.iter().map(|definition| (definition.name.as_str(), &definition.origin, definition)),
+            &mut errors,
+        );
+        for player in self.players.iter().skip(1) {
+            errors.push(ValidationError::DuplicateName {
+                kind: Kind::Player,
+                name: player.name.clone(),
+                first: self.players[0].origin.clone(),
+                second: player.origin.clone(),
+            });
+        }
+        for save in self.saves.iter().skip(1) {
+            errors.push(ValidationError::DuplicateName {
+                kind: Kind::Save,
+                name: "@save".to_string(),
+                first: self.saves[0].origin.clone(),
+                second: save.origin.clone(),
+            });
+        }
+        for hints in self.hints.iter().skip(1) {
+            errors.push(ValidationError::DuplicateName {
+                kind: Kind::Hints,
+                name: "@hints".to_string(),
+                first: self.hints[0].origin.clone(),
+                second: hints.origin.clone(),
+            });
+        }
+        for generator in self.generators.iter().skip(1) {
+            errors.push(ValidationError::DuplicateName {
+                kind: Kind::Generator,
+                name: "@generator".to_string(),
+                first: self.generators[0].origin.clone(),
+                second: generator.origin.clone(),
+            });
+        }
+        for combat in self.combats.iter().skip(1) {
+            errors.push(ValidationError::DuplicateName {
+                kind: Kind::Combat,
+                name: "@combat".to_string(),
+                first: self.combats[0].origin.clone(),
+                second: combat.origin.clone(),
+            });
+        }
+        Registry::index(
+            Kind::Map,
+            self.maps.iter().map(|definition| (definition.name.as_str(), &definition.origin, definition)),
+            &mut errors,
+        );
+
+        let mut monsters = vec![];
+        for definition in &self.monsters {
+            let weapon = definition.weapon.as_ref().and_then(|reference| lookup(&items, reference, &mut errors));
+            let armor = definition.armor.as_ref().and_then(|reference| lookup(&items, reference, &mut errors));
+            let drop = definition.drop.as_ref().and_then(|(reference, qte)| lookup(&items, reference, &mut errors).map(|item| (item, *qte)));
+            let qte = drop.as_ref().map_or(0, |(_, qte)| *qte);
+            let mut monster = Monster::new(
+                &definition.name,
+                definition.life,
+                weapon.unwrap_or(Item::new_weapon("", "", 0, 0)),
+                drop.map(|(item, _)| item),
+                qte,
+                definition.rareness,
+            );
+            if let Some(armor) = armor {
+                monster = monster.with_armor(armor);
+            }
+            monsters.push(monster.with_behaviour(definition.behaviour));
+        }
+
+        let mut player = None;
+        if let Some(definition) = self.players.first() {
+            let equipped: Vec<Item> = definition.weapon.iter()
+                .chain(definition.armor.iter())
+                .chain(definition.equipment.iter())
+                .filter_map(|reference| lookup(&items, reference, &mut errors))
+                .collect();
+            let inventory: Vec<(Item, u32)> = definition.inventory.iter()
+                .filter_map(|(reference, qte)| Some((lookup(&items, reference, &mut errors)?, *qte)))
+                .collect();
+
+            let mut resolved = Player::new_all(
+                &definition.name,
+                definition.life,
+                definition.max_life,
+                definition.position,
+                Equipment::new(),
+                Inventory::with_capacity(definition.capacity),
+                definition.experience,
+                definition.level,
+                definition.next_level,
+            );
+            for item in equipped {
+                let origin = definition.origin.clone();
+                match resolved.equip(item.clone()) {
+                    Ok(None) => {},
+                    Ok(Some(_)) => errors.push(ValidationError::SlotTaken { origin, slot: item.slot().unwrap() }),
+                    Err(_) => errors.push(ValidationError::NotEquipment { origin, item: item.name().to_string() }),
+                }
+            }
+            for (item, qte) in inventory {
+                // Gold is counted in coins, whatever a pile of it is worth.
+                let item = match item.type_() {
+                    ItemType::Gold(_) => Item::new_gold(1),
+                    _ => item,
+                };
+                if resolved.add_items(item, qte).is_err() {
+                    errors.push(ValidationError::InventoryFull { origin: definition.origin.clone(), capacity: definition.capacity });
+                    break;
+                }
+            }
+            for status in &definition.statuses {
+                resolved.add_status(*status);
+            }
+            player = Some(resolved);
+        }
+
+        let mut saved = None;
+        if let Some(save) = self.saves.first() {
+            let floors = save.floors.iter().map(|floor| resolve_floor(floor, &items, &monsters, &mut errors)).collect();
+            if save.current >= save.floors.len() {
+                errors.push(ValidationError::MissingFloor { origin: save.origin.clone(), floor: save.current });
+            }
+            saved = Some(SavedRun { seed: save.seed, turn: save.turn, dungeon: Dungeon::new(floors, save.current) });
+        }
+
+        let mut hints = Hints::default();
+        if let Some(definition) = self.hints.first() {
+            if let Some(radius) = definition.radius {
+                hints.set_radius(radius);
+            }
+            for (kind, message) in &definition.messages {
+                hints.set_message(*kind, message);
+            }
+        }
+
+        let mut formula = Formula::default();
+        if let Some(definition) = self.combats.first() {
+            if let Some(min_damage) = definition.min_damage {
+                formula = formula.with_minimum(i32::try_from(min_damage).unwrap_or(i32::MAX));
+            }
+            if let Some((chance, multiplier)) = definition.critical {
+                formula = formula.with_critical(chance, multiplier);
+            }
+            if let Some(variance) = definition.variance {
+                formula = formula.with_variance(variance);
+            }
+        }
+
+        let mut generator = None;
+        if let Some(definition) = self.generators.first() {
+            match Generator::new(&definition.algorithm, &definition.options) {
+                Ok(resolved) => generator = Some(resolved),
+                Err(error) => errors.push(ValidationError::InvalidGenerator { origin: definition.origin.clone(), error }),
+            }
+        }
+
+        let mut maps = BTreeMap::new();
+        for definition in &self.maps {
+            let map = resolve_map(definition, &items, &monsters, &mut errors);
+            maps.entry(definition.name.clone()).or_insert(map);
+        }
+
+        if player.is_none() {
+            errors.push(ValidationError::MissingPlayer);
+        }
+
+        match player {
+            Some(player) if errors.is_empty() => Ok(Catalog {
+                items: self.items.iter().map(|definition| definition.item.clone()).collect(),
+                treasures: self.items.iter()
+                    .filter_map(|definition| definition.treasure.map(|rareness| (definition.item.name().to_string(), rareness)))
+                    .collect(),
+                monsters,
+                player,
+                hints,
+                generator,
+                maps,
+                formula,
+                saved,
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
+fn lookup(items: &HashMap<&str, (&Origin, &Item)>, reference: &Reference, errors: &mut Vec<ValidationError>) -> Option<Item> {
+    match items.get(reference.name.as_str()) {
+        Some((_, item)) => Some((*item).clone()),
+        None => {
+            errors.push(ValidationError::DanglingReference { kind: Kind::Item, reference: reference.clone() });
+            None
+        },
+    }
+}
+
+/// The event of a tile, with its monster or item looked up by name.
+fn resolve_event(tile: &TileDef, items: &HashMap<&str, (&Origin, &Item)>, monsters: &[Monster], errors: &mut Vec<ValidationError>) -> Option<Event> {
+    match tile {
+        TileDef::Empty => Some(Event::Empty),
+        TileDef::Monster(reference, life, walked) => {
+            let monster = monsters.iter().find(|monster| monster.name() == reference.name);
+            if monster.is_none() {
+                errors.push(ValidationError::DanglingReference { kind: Kind::Monster, reference: reference.clone() });
+            }
+            monster.map(|monster| {
+                let mut monster = monster.clone();
+                monster.set_life(*life);
+                monster.set_walked(*walked);
+                Event::Monster(monster)
+            })
+        },
+        TileDef::Treasure(reference, count) => lookup(items, reference, errors).map(|item| match item.type_() {
+            ItemType::Gold(_) => Event::Treasure(Item::new_gold(1), *count),
+            _ => Event::Treasure(item, *count),
+        }),
+        TileDef::Teleport(target) => Some(Event::Teleport(*target)),
+        TileDef::End => Some(Event::End),
+        TileDef::StairsDown => Some(Event::StairsDown),
+        TileDef::StairsUp => Some(Event::StairsUp),
+    }
+}
+
+/// Grid characters of a `@map` that need no legend, besides `#` and spaces
+/// for rock and `P` for the start.
+const MAP_SYMBOLS: [(char, Terrain, Event); 7] = [
+    ('.', Terrain::Floor, Event::Empty),
+    ('+', Terrain::Door, Event::Empty),
+    ('~', Terrain::Water, Event::Empty),
+    ('^', Terrain::Pit, Event::Empty),
+    ('E', Terrain::Floor, Event::End),
+    ('>', Terrain::Floor, Event::StairsDown),
+    ('<', Terrain::Floor, Event::StairsUp),
+];
+
+/// The grid character drawing `tile` without a legend, if there is one.
+pub(super) fn map_symbol(tile: &Tile) -> Option<char> {
+    MAP_SYMBOLS.iter()
+        .find(|(_, terrain, event)| *terrain == tile.terrain && *event == tile.event)
+        .map(|(symbol, _, _)| *symbol)
+}
+
+/// Build a `@map` grid. Columns and rows count from the top-left corner;
+/// the map is moved so the start (`P`, or the cell set by `@origin`, or else
+/// the corner) is at the origin, teleport targets included.
+fn resolve_map(definition: &MapDef, items: &HashMap<&str, (&Origin, &Item)>, monsters: &[Monster], errors: &mut Vec<ValidationError>) -> Map {
+    let mut fail = |error| errors.push(ValidationError::InvalidMap {
+        name: definition.name.clone(),
+        origin: definition.origin.clone(),
+        error,
+    });
+
+    let cells: Vec<(Position, char)> = definition.rows.iter().enumerate()
+        .flat_map(|(row, line)| line.chars().enumerate().map(move |(column, symbol)| (Position::new(column as i32, row as i32), symbol)))
+        .collect();
+    let starts: Vec<Position> = cells.iter().filter(|(_, symbol)| *symbol == 'P').map(|(cell, _)| *cell).collect();
+    let start = match (definition.origin_cell, starts.as_slice()) {
+        (Some(cell), []) => cell,
+        (None, [cell]) => *cell,
+        (None, []) => Position::new(0, 0),
+        (origin_cell, _) => {
+            fail(MapError::SeveralStarts);
+            origin_cell.unwrap_or(starts[0])
+        },
+    };
+    let moved = |cell: &Position| Position::new(cell.x() - start.x(), cell.y() - start.y());
+
+    let mut legend: HashMap<char, Option<Tile>> = HashMap::new();
+    let mut resolve_errors = vec![];
+    for entry in &definition.legend {
+        if legend.contains_key(&entry.symbol) {
+            fail(MapError::DuplicateSymbol(entry.symbol));
+            continue;
+        }
+        let event = match &entry.event {
+            LegendEvent::Tile(TileDef::Teleport(cell)) => Some(Event::Teleport(moved(cell))),
+            LegendEvent::Tile(tile) => resolve_event(tile, items, monsters, &mut resolve_errors),
+            LegendEvent::TeleportTo(target) => {
+                let targets: Vec<&Position> = cells.iter().filter(|(_, symbol)| symbol == target).map(|(cell, _)| cell).collect();
+                match targets.as_slice() {
+                    [cell] => Some(Event::Teleport(moved(cell))),
+                    _ => {
+                        fail(MapError::AmbiguousTarget(*target));
+                        None
+                    },
+                }
+            },
+        };
+        legend.insert(entry.symbol, event.map(|event| Tile::new(entry.terrain, event)));
+    }
+
+    let mut map = Map::new();
+    for (cell, symbol) in &cells {
+        let builtin = MAP_SYMBOLS.iter().find(|(builtin, _, _)| builtin == symbol);
+        let tile = match (symbol, legend.get(symbol), builtin) {
+            (' ' | '#', _, _) => None,
+            ('P', _, _) => Some(Tile::floor(Event::Empty)),
+            (_, Some(tile), _) => tile.clone(),
+            (_, None, Some((_, terrain, event))) => Some(Tile::new(*terrain, event.clone())),
+            (_, None, None) => {
+                fail(MapError::UnknownSymbol { symbol: *symbol, cell: *cell });
+                None
+            },
+        };
+        if let Some(tile) = tile {
+            map.add_tile(moved(cell), tile);
+        }
+    }
+    errors.extend(resolve_errors);
+    map
+}
+
+/// A saved floor with its monsters and treasures looked up by name.
+fn resolve_floor(floor: &FloorDef, items: &HashMap<&str, (&Origin, &Item)>, monsters: &[Monster], errors: &mut Vec<ValidationError>) -> Floor {
+    let mut map = Map::new();
+    for (position, terrain, tile) in &floor.tiles {
+        if let Some(event) = resolve_event(tile, items, monsters, errors) {
+            map.add_tile(*position, Tile::new(*terrain, event));
+        }
+    }
+    let mut knowledge = Knowledge::new();
+    for position in &floor.visited {
+        knowledge.visit(*position);
+    }
+    for position in &floor.sensed {
+        knowledge.sense(*position);
+    }
+    Floor { map, knowledge }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::data_loader::{Lexer, Parser};
+    use crate::map::Position;
+
+    fn parser(input: &str, file: &str) -> Parser {
+        Parser::new(Lexer::new(input.to_string()).lex_spanned()).with_file(Path::new(file))
+    }
+
+    fn registry(monster: &str, player: &str) -> Registry {
+        let mut registry = Registry::new();
+        registry.add_monster(parser(monster, "goblin.blind").parse_monster().unwrap());
+        registry.add_player(parser(player, "player.blind").parse_player().unwrap());
+        registry.add_item(parser("@item @gold @value: 10", "gold.blind").parse_item_def().unwrap());
+        registry.add_item(parser("@item @weapon @name: Sword @atk: 10 @description: A sword @value: 100", "sword.blind").parse_item_def().unwrap());
+        registry
+    }
+
+    #[test]
+    fn test_registry_resolve_out_of_order() {
+        let registry = registry(
+            "@monster @name: Goblin @life: 10 @weapon: Sword @drop: Gold 10 @rareness: 1",
+            "@player @name: Player @life: 10 @max-life: 100 @weapon: Sword @exp: 10 @level: 1 @exp-to-level-up: 100 @inventory: Gold 10",
+        );
+        let catalog = registry.resolve().unwrap();
+        let mut inventory = Inventory::new();
+        inventory.add(Item::new_gold(1), 10).unwrap();
+        let mut equipment = Equipment::new();
+        equipment.equip(Item::new_weapon("Sword", "A sword", 100, 10)).unwrap();
+        assert_eq!(catalog.items(), &[Item::new_gold(10), Item::new_weapon("Sword", "A sword", 100, 10)]);
+        assert_eq!(catalog.monsters(), &[Monster::new("Goblin", 10, Item::new_weapon("Sword", "A sword", 100, 10), Some(Item::new_gold(10)), 10, 1)]);
+        assert_eq!(catalog.player(), &Player::new_all(
+            "Player",
+            10,
+            100,
+            Position::new(0, 0),
+            equipment,
+            inventory,
+            10,
+            1,
+            100
+        ));
+    }
+
+    #[test]
+    fn test_registry_dangling_reference() {
+        let registry = registry(
+            "@monster @name: Goblin @weapon: Axe",
+            "@player @name: Player @inventory: Gold 1 potion 2",
+        );
+        let errors = registry.resolve().unwrap_err();
+        let messages: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(messages, vec![
+            "goblin.blind:1:33: unknown item 'Axe'",
+            "player.blind:1:42: unknown item 'potion'",
+        ]);
+    }
+
+    #[test]
+    fn test_registry_duplicate_names() {
+        let mut registry = registry("@monster @name: Goblin", "@player @name: Player");
+        registry.add_item(parser("@item @weapon @name: Sword", "other.blind").parse_item_def().unwrap());
+        registry.add_monster(parser("@monster @name: Goblin", "other.blind").parse_monster().unwrap());
+        registry.add_player(parser("@player @name: Other", "other.blind").parse_player().unwrap());
+        let errors = registry.resolve().unwrap_err();
+        let messages: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(messages, vec![
+            "other.blind:1:1: duplicate item 'Sword', first defined at sword.blind:1:1",
+            "other.blind:1:1: duplicate monster 'Goblin', first defined at goblin.blind:1:1",
+            "other.blind:1:1: duplicate player 'Other', first defined at player.blind:1:1",
+        ]);
+    }
+
+    #[test]
+    fn test_registry_inventory_capacity() {
+        let catalog = registry("@monster @name: Goblin @life: 10", "@player @name: Hero @capacity: 2 @inventory: Gold 3 Sword 1 Gold 2").resolve().unwrap();
+        assert_eq!(catalog.player().inventory().capacity(), 2);
+        assert_eq!(catalog.player().gold(), 5);
+
+        let full = registry("@monster @name: Goblin @life: 10", "@player @name: Hero @capacity: 2 @inventory: Gold 1 Sword 2");
+        let errors: Vec<String> = full.resolve().unwrap_err().iter().map(|error| error.to_string()).collect();
+        assert_eq!(errors, vec!["player.blind:1:1: the inventory does not fit in 2 slots"]);
+    }
+
+    #[test]
+    fn test_registry_equipment() {
+        let equip = |player: &str| {
+            let mut registry = registry("@monster @name: Goblin @life: 10", player);
+            registry.add_item(parser("@item @armor @name: Buckler @def: 2 @slot: off-hand", "buckler.blind").parse_item_def().unwrap());
+            registry.resolve()
+        };
+        let catalog = equip("@player @name: Hero @weapon: Sword @equip: Buckler").unwrap();
+        assert_eq!(catalog.player().equipment().get(Slot::OffHand).map(Item::name), Some("Buckler"));
+        assert_eq!((catalog.player().attack(), catalog.player().defense()), (10, 2));
+
+        let errors: Vec<String> = equip("@player @name: Hero @equip: Gold @armor: Buckler @equip: Sword @weapon: Sword").unwrap_err().iter().map(|error| error.to_string()).collect();
+        assert_eq!(errors, vec![
+            "player.blind:1:1: 'Gold' cannot be equipped",
+            "player.blind:1:1: more than one item equipped in the main hand slot",
+        ]);
+    }
+
+    #[test]
+    fn test_registry_missing_player() {
+        let mut registry = Registry::new();
+        registry.add_item(parser("@item @gold @value: 10", "gold.blind").parse_item_def().unwrap());
+        assert_eq!(registry.resolve().unwrap_err(), vec![ValidationError::MissingPlayer]);
+    }
+
+    #[test]
+    fn test_registry_invalid_generator() {
+        let mut registry = registry("@monster @name: Goblin @life: 10", "@player @name: Hero");
+        for definition in parser("@generator @algorithm: caves @option: fill 200", "generator.blind").parse_all().unwrap() {
+            registry.add(definition);
+        }
+        let errors: Vec<String> = registry.resolve().unwrap_err().iter().map(|error| error.to_string()).collect();
+        assert_eq!(errors, vec!["generator.blind:1:1: invalid value 200 for option 'fill'"]);
+    }
+
+    #[test]
+    fn test_registry_combat() {
+        let mut valid = registry("@monster @name: Goblin @life: 10 @weapon: Sword @armor: Mail", "@player @name: Hero");
+        valid.add_item(parser("@item @armor @name: Mail @def: 3", "mail.blind").parse_item_def().unwrap());
+        for definition in parser("@combat @critical: 10 150", "combat.blind").parse_all().unwrap() {
+            valid.add(definition);
+        }
+        let catalog = valid.resolve().unwrap();
+        assert_eq!(catalog.formula(), &Formula::default().with_critical(10, 150));
+        assert_eq!(catalog.monsters()[0].defense(), 3);
+        assert_eq!(catalog.monsters()[0].armor(), Some(&Item::new_armor("Mail", "", 0, 3)));
+
+        let mut invalid = registry("@monster @name: Goblin @armor: Plate", "@player @name: Hero");
+        for definition in parser("@combat @variance: 10\n@combat", "combat.blind").parse_all().unwrap() {
+            invalid.add(definition);
+        }
+        let errors: Vec<String> = invalid.resolve().unwrap_err().iter().map(|error| error.to_string()).collect();
+        assert_eq!(errors, vec![
+            "combat.blind:2:1: duplicate combat '@combat', first defined at combat.blind:1:1",
+            "goblin.blind:1:32: unknown item 'Plate'",
+        ]);
+    }
+
+    #[test]
+    fn test_registry_map() {
+        let mut registry = registry("@monster @name: Goblin @life: 10", "@player @name: Hero");
+        let input = concat!(
+            "@map @name: cave\n",
+            "@row: \"#####\"\n",
+            "@row: \"#.P1#\"\n",
+            "@row: \"#g+a#\"\n",
+            "@row: \"##E##\"\n",
+            "@legend: g @water @monster: Goblin 3\n",
+            "@legend: \"1\" @teleport: a\n",
+            "@legend: a @treasure: Gold 7\n",
+        );
+        for definition in parser(input, "cave.blind").parse_all().unwrap() {
+            registry.add(definition);
+        }
+        let catalog = registry.resolve().unwrap();
+        let map = catalog.map("cave").unwrap();
+        let mut goblin = catalog.monsters()[0].clone();
+        goblin.set_life(3);
+        assert_eq!(map.tiles().len(), 7);
+        assert_eq!(map.tile_at(&Position::new(0, 0)), Some(&Tile::floor(Event::Empty)));
+        assert_eq!(map.tile_at(&Position::new(-1, 0)), Some(&Tile::floor(Event::Empty)));
+        assert_eq!(map.tile_at(&Position::new(1, 0)), Some(&Tile::floor(Event::Teleport(Position::new(1, 1)))));
+        assert_eq!(map.tile_at(&Position::new(-1, 1)), Some(&Tile::new(Terrain::Water, Event::Monster(goblin))));
+        assert_eq!(map.terrain_at(&Position::new(0, 1)), Terrain::Door);
+        assert_eq!(map.event_at(&Position::new(1, 1)), Some(&Event::Treasure(Item::new_gold(1), 7)));
+        assert_eq!(map.event_at(&Position::new(0, 2)), Some(&Event::End));
+        assert!(map.validate(&Position::new(0, 0)).is_empty());
+    }
+
+    #[test]
+    fn test_registry_invalid_map() {
+        let mut registry = registry("@monster @name: Goblin @life: 10", "@player @name: Hero");
+        let input = concat!(
+            "@map @name: bad @row: \"P.xa@\" @row: \"P\"\n",
+            "@legend: a @teleport: b @legend: a @end @legend: \"@\" @monster: Orc 1\n",
+            "@map @name: bad",
+        );
+        for definition in parser(input, "bad.blind").parse_all().unwrap() {
+            registry.add(definition);
+        }
+        let errors: Vec<String> = registry.resolve().unwrap_err().iter().map(|error| error.to_string()).collect();
+        assert_eq!(errors, vec![
+            "bad.blind:3:1: duplicate map 'bad', first defined at bad.blind:1:1",
+            "bad.blind:1:1: map 'bad': more than one start (P or @origin)",
+            "bad.blind:1:1: map 'bad': teleport target 'b' must be drawn exactly once",
+            "bad.blind:1:1: map 'bad': symbol 'a' is in the legend twice",
+            "bad.blind:1:1: map 'bad': unknown symbol 'x' at column 2, row 0",
+            "bad.blind:2:64: unknown monster 'Orc'",
+        ]);
+    }
+}
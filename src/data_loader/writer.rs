@@ -0,0 +1,684 @@
+use super::lexer::Token;
+use super::registry::{map_symbol, Catalog};
+use crate::combat::damage::Formula;
+use crate::map::dungeon::Dungeon;
+use crate::map::item::{Effect, Item, ItemType};
+use crate::map::monster::{Behaviour, Monster};
+use crate::map::status::StatusEffect;
+use crate::map::generator::Generator;
+use crate::map::hints::{HintKind, Hints};
+use crate::map::knowledge::Knowledge;
+use crate::map::tile::{Terrain, Tile};
+use crate::map::{Event, Map, Position};
+use crate::player::{equipment::Slot, inventory::Inventory, Player};
+
+/// Writes game data as canonical `.blind` text that the `Parser` reads back.
+///
+/// Every text value is quoted so names and descriptions may hold any
+/// character, and blocks are separated by an empty line. Monsters and
+/// players refer to their items by name, so their output only resolves in a
+/// `Registry` that also holds those items.
+#[derive(Debug, Default)]
+pub struct Writer {
+    output: String,
+}
+
+impl Writer {
+    pub fn new() -> Writer {
+        Writer::default()
+    }
+
+    pub fn finish(self) -> String {
+        self.output
+    }
+
+    /// Quote and escape `value` as a `.blind` string.
+    pub fn quote(value: &str) -> String {
+        let mut quoted = String::with_capacity(value.len() + 2);
+        quoted.push('"');
+        for c in value.chars() {
+            match c {
+                '"' => quoted.push_str("\\\""),
+                '\\' => quoted.push_str("\\\\"),
+                '\n' => quoted.push_str("\\n"),
+                '\t' => quoted.push_str("\\t"),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    fn keyword(token: Token) -> &'static str {
+        token.keyword().expect("only keyword tokens are written as is")
+    }
+
+    fn begin(&mut self, tokens: &[Token]) {
+        if !self.output.is_empty() {
+            self.output.push('\n');
+        }
+        let keywords: Vec<&str> = tokens.iter().cloned().map(Writer::keyword).collect();
+        self.output.push_str(&keywords.join("\n"));
+        self.output.push('\n');
+    }
+
+    fn field(&mut self, field: Token, value: &str) {
+        self.output.push_str(&format!("{}: {}\n", Writer::keyword(field), value));
+    }
+
+    fn field_int(&mut self, field: Token, value: i32) {
+        self.field(field, &value.to_string());
+    }
+
+    fn field_str(&mut self, field: Token, value: &str) {
+        self.field(field, &Writer::quote(value));
+    }
+
+    /// A status as `"poison" 3 "for" 5`.
+    fn status(status: &StatusEffect) -> String {
+        format!("{} {} {} {}", Writer::quote(status.kind.name()), status.power, Writer::quote("for"), status.turns)
+    }
+
+    fn effect(effect: &Effect) -> Option<String> {
+        match effect {
+            Effect::None => None,
+            Effect::Heal(heal) => Some(format!("{}: {}", Writer::keyword(Token::Heal), heal)),
+            Effect::Damage(damage) => Some(format!("{}: {}", Writer::keyword(Token::Damage), damage)),
+            Effect::Teleport(position) if position == &Position::new(0, 0) => {
+                Some(format!("{}: {}", Writer::keyword(Token::Teleport), Writer::keyword(Token::Start)))
+            },
+            Effect::Teleport(position) => {
+                Some(format!("{}: {} {}", Writer::keyword(Token::Teleport), position.x(), position.y()))
+            },
+            Effect::RandomTeleport => {
+                Some(format!("{}: {}", Writer::keyword(Token::Teleport), Writer::keyword(Token::Random)))
+            },
+            Effect::Status(status) => Some(Writer::status(status)),
+        }
+    }
+
+    pub fn item(&mut self, item: &Item) -> &mut Self {
+        self.item_with(item, None)
+    }
+
+    /// Write an item, marking it `@treasure` with the given rareness, if any.
+    pub fn item_with(&mut self, item: &Item, treasure: Option<u32>) -> &mut Self {
+        let kind = match item.type_() {
+            ItemType::Weapon(_) => Token::Weapon,
+            ItemType::Armor(_) => Token::Armor,
+            ItemType::Gold(_) => Token::Gold,
+            ItemType::Effect(_) => Token::Effect,
+            ItemType::Exp(_) => Token::Exp,
+        };
+        match treasure {
+            None => self.begin(&[Token::Item, kind]),
+            Some(1) => self.begin(&[Token::Item, kind, Token::Treasure]),
+            Some(rareness) => {
+                self.begin(&[Token::Item, kind]);
+                self.field_int(Token::Treasure, rareness.min(i32::MAX as u32) as i32);
+            },
+        }
+
+        if let ItemType::Gold(_) = item.type_() {
+            self.field_int(Token::Value, item.value());
+            return self;
+        }
+
+        self.field_str(Token::Name, item.name());
+        self.field_str(Token::Description, item.description());
+        self.field_int(Token::Value, item.value());
+        match item.type_() {
+            ItemType::Weapon(atk) => {
+                self.field_int(Token::Atk, *atk);
+                if let Some(status) = item.inflicts() {
+                    self.field(Token::Effect, &Writer::status(status));
+                }
+            },
+            ItemType::Armor(def) => self.field_int(Token::Def, *def),
+            ItemType::Effect(effect) => {
+                if let Some(effect) = Writer::effect(effect) {
+                    self.field(Token::Effect, &effect);
+                }
+            },
+            _ => {},
+        }
+        if let Some(slot) = item.explicit_slot() {
+            self.field(Token::Slot, &Writer::quote(slot.name()));
+        }
+        self
+    }
+
+    /// Write a monster. An unnamed weapon is the default the `Registry`
+    /// fills in, so it is left out.
+    pub fn monster(&mut self, monster: &Monster) -> &mut Self {
+        self.begin(&[Token::Monster]);
+        self.field_str(Token::Name, monster.name());
+        self.field_int(Token::Life, monster.life());
+        if !monster.weapon().name().is_empty() {
+            self.field_str(Token::Weapon, monster.weapon().name());
+        }
+        if let Some(armor) = monster.armor() {
+            self.field_str(Token::Armor, armor.name());
+        }
+        if let Some((drop, qte)) = monster.drop() {
+            self.field(Token::Drop, &format!("{} {}", Writer::quote(drop.name()), qte));
+        }
+        self.field(Token::Rareness, &monster.rareness().to_string());
+        match monster.behaviour() {
+            Behaviour::Idle => {},
+            Behaviour::Wander => self.field(Token::Behaviour, &Writer::quote("wander")),
+            Behaviour::Patrol(steps) => self.field(Token::Behaviour, &format!("{} {}", Writer::quote("patrol"), steps)),
+            Behaviour::Chase(steps) => self.field(Token::Behaviour, &format!("{} {}", Writer::quote("chase"), steps)),
+        }
+        self
+    }
+
+    /// Write a player. The main hand and body are written as `@weapon` and
+    /// `@armor`, the other slots as `@equip`. The inventory is written slot
+    /// by slot as item names with their count; gold is counted in coins,
+    /// whatever the `Gold` item of the content is worth.
+    pub fn player(&mut self, player: &Player) -> &mut Self {
+        self.begin(&[Token::Player]);
+        self.field_str(Token::Name, player.name());
+        self.field_int(Token::Life, player.life());
+        self.field_int(Token::MaxLife, player.max_life());
+        for (slot, item) in player.equipment().iter() {
+            let field = match slot {
+                Slot::MainHand => Token::Weapon,
+                Slot::Body => Token::Armor,
+                _ => Token::Equip,
+            };
+            self.field_str(field, item.name());
+        }
+        self.field_int(Token::Exp, player.experience());
+        self.field_int(Token::Level, player.level());
+        self.field_int(Token::ExpToLevelUp, player.next_level());
+        if player.position() != &Position::new(0, 0) {
+            let position = player.position();
+            self.field(Token::Position, &format!("{} {}", position.x(), position.y()));
+        }
+        for status in player.statuses().iter() {
+            self.field(Token::Status, &Writer::status(status));
+        }
+
+        if player.inventory().capacity() != Inventory::DEFAULT_CAPACITY {
+            self.field_int(Token::Capacity, player.inventory().capacity().min(i32::MAX as usize) as i32);
+        }
+        self.output.push_str(&format!("{}:\n", Writer::keyword(Token::Inventory)));
+        for stack in player.inventory().iter() {
+            self.output.push_str(&format!("{} {}\n", Writer::quote(stack.item().name()), stack.count()));
+        }
+        self
+    }
+
+    /// Write a `@save` block holding the seed, the turn, the floor the player
+    /// is on and, floor by floor, every tile and what the player knows of it.
+    /// Monsters and treasures refer to their definitions by name, so the
+    /// items and monsters must be written alongside. The seed must fit a
+    /// `.blind` integer, which is signed 64-bit.
+    pub fn save(&mut self, seed: u64, turn: u32, dungeon: &Dungeon) -> &mut Self {
+        self.begin(&[Token::Save]);
+        self.field(Token::Seed, &seed.to_string());
+        self.field(Token::Turn, &turn.to_string());
+        self.field_int(Token::Current, dungeon.depth() as i32);
+        for (depth, floor) in dungeon.floors().iter().enumerate() {
+            self.field_int(Token::Depth, depth as i32);
+            self.floor(&floor.map, &floor.knowledge);
+        }
+        self
+    }
+
+    /// The terrain keyword, unless it is floor, and the event of a tile, as
+    /// `@tile` and `@legend` entries write them. Teleport targets are written
+    /// relative to `corner`.
+    fn tile(tile: &Tile, corner: &Position) -> String {
+        let terrain = match tile.terrain {
+            Terrain::Floor => None,
+            Terrain::Wall => Some(Token::Wall),
+            Terrain::Door => Some(Token::Door),
+            Terrain::Water => Some(Token::Water),
+            Terrain::Pit => Some(Token::Pit),
+        };
+        let event = match &tile.event {
+            Event::Empty => Writer::keyword(Token::Empty).to_string(),
+            Event::Monster(monster) => match monster.walked() {
+                0 => format!("{}: {} {}", Writer::keyword(Token::Monster), Writer::quote(monster.name()), monster.life()),
+                walked => format!("{}: {} {} {}", Writer::keyword(Token::Monster), Writer::quote(monster.name()), monster.life(), walked),
+            },
+            Event::Treasure(item, count) => {
+                format!("{}: {} {}", Writer::keyword(Token::Treasure), Writer::quote(item.name()), count)
+            },
+            Event::Teleport(target) => {
+                format!("{}: {} {}", Writer::keyword(Token::Teleport), target.x() - corner.x(), target.y() - corner.y())
+            },
+            Event::End => Writer::keyword(Token::End).to_string(),
+            Event::StairsDown => Writer::keyword(Token::StairsDown).to_string(),
+            Event::StairsUp => Writer::keyword(Token::StairsUp).to_string(),
+        };
+        match terrain {
+            Some(terrain) => format!("{} {}", Writer::keyword(terrain), event),
+            None => event,
+        }
+    }
+
+    fn floor(&mut self, map: &Map, knowledge: &Knowledge) {
+        for (position, tile) in map.tiles() {
+            let tile = Writer::tile(tile, &Position::new(0, 0));
+            self.field(Token::Tile, &format!("{} {} {}", position.x(), position.y(), tile));
+        }
+        for position in knowledge.visited() {
+            self.field(Token::Visited, &format!("{} {}", position.x(), position.y()));
+        }
+        for position in knowledge.sensed() {
+            self.field(Token::Sensed, &format!("{} {}", position.x(), position.y()));
+        }
+    }
+
+    /// Write a `@map` block drawing `map` as a grid with `#` for rock. Tiles
+    /// without a built-in symbol get a letter of the legend each, and the
+    /// start is drawn `P` when it is an empty floor, or set by `@origin`.
+    pub fn map(&mut self, name: &str, map: &Map) -> &mut Self {
+        self.begin(&[Token::Map]);
+        self.field_str(Token::Name, name);
+
+        let origin = Position::new(0, 0);
+        let positions: Vec<&Position> = map.tiles().into_iter().map(|(position, _)| position).chain([&origin]).collect();
+        let corner = Position::new(
+            positions.iter().map(|position| position.x()).min().unwrap_or(0),
+            positions.iter().map(|position| position.y()).min().unwrap_or(0),
+        );
+        let far = Position::new(
+            positions.iter().map(|position| position.x()).max().unwrap_or(0),
+            positions.iter().map(|position| position.y()).max().unwrap_or(0),
+        );
+        let start = map.tile_at(&origin) == Some(&Tile::floor(Event::Empty));
+        if !start {
+            self.field(Token::Origin, &format!("{} {}", -corner.x(), -corner.y()));
+        }
+
+        let mut letters = ('a'..='z').chain('A'..='Z').filter(|letter| !matches!(letter, 'E' | 'P')).chain((0xC0..).filter_map(char::from_u32));
+        let mut legend: Vec<(char, &Tile)> = vec![];
+        for y in corner.y()..=far.y() {
+            let mut row = String::new();
+            for x in corner.x()..=far.x() {
+                let position = Position::new(x, y);
+                let symbol = match map.tile_at(&position) {
+                    _ if start && position == origin => 'P',
+                    None => '#',
+                    Some(tile) => match map_symbol(tile) {
+                        Some(symbol) => symbol,
+                        None => match legend.iter().find(|(_, known)| *known == tile) {
+                            Some((symbol, _)) => *symbol,
+                            None => {
+                                let symbol = letters.next().expect("there are more letters than tiles");
+                                legend.push((symbol, tile));
+                                symbol
+                            },
+                        },
+                    },
+                };
+                row.push(symbol);
+            }
+            self.field_str(Token::Row, &row);
+        }
+        for (symbol, tile) in legend {
+            self.field(Token::Legend, &format!("{} {}", Writer::quote(&symbol.to_string()), Writer::tile(tile, &corner)));
+        }
+        self
+    }
+
+    /// Write a `@hints` block with the radius and every message.
+    pub fn hints(&mut self, hints: &Hints) -> &mut Self {
+        self.begin(&[Token::Hints]);
+        self.field_int(Token::Radius, hints.radius().min(i32::MAX as u32) as i32);
+        for kind in HintKind::ALL {
+            let token = match kind {
+                HintKind::Monster => Token::Monster,
+                HintKind::Treasure => Token::Treasure,
+                HintKind::Teleport => Token::Teleport,
+                HintKind::End => Token::End,
+            };
+            self.field(Token::Hint, &format!("{} {}", Writer::keyword(token), Writer::quote(hints.message(kind))));
+        }
+        self
+    }
+
+    /// Write a `@generator` block with every option of the generator.
+    pub fn generator(&mut self, generator: &Generator) -> &mut Self {
+        self.begin(&[Token::Generator]);
+        self.field(Token::Algorithm, &Writer::quote(generator.algorithm()));
+        for (option, value) in generator.options() {
+            self.field(Token::Option, &format!("{} {}", Writer::quote(option), value));
+        }
+        self
+    }
+
+    /// Write a `@combat` block with every part of the damage formula.
+    pub fn combat(&mut self, formula: &Formula) -> &mut Self {
+        self.begin(&[Token::Combat]);
+        self.field_int(Token::MinDamage, formula.minimum());
+        self.field(Token::Critical, &format!("{} {}", formula.critical_chance(), formula.critical_multiplier().min(i32::MAX as u32)));
+        self.field_int(Token::Variance, formula.variance() as i32);
+        self
+    }
+
+    /// Write every item and monster of the catalog, its player, its hints,
+    /// its damage formula and its generator.
+    pub fn catalog(&mut self, catalog: &Catalog) -> &mut Self {
+        for item in catalog.items() {
+            self.item_with(item, catalog.treasure_rareness(item));
+        }
+        for monster in catalog.monsters() {
+            self.monster(monster);
+        }
+        self.player(catalog.player());
+        self.hints(catalog.hints());
+        self.combat(catalog.formula());
+        if let Some(generator) = catalog.generator() {
+            self.generator(generator);
+        }
+        for (name, map) in catalog.maps() {
+            self.map(name, map);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::data_loader::{Definition, Lexer, Parser, Registry};
+    use crate::map::status::StatusKind;
+    use crate::player::equipment::Equipment;
+
+    fn parse(input: &str) -> Vec<Definition> {
+        match Parser::new(Lexer::new(input.to_string()).lex_spanned()).parse_all() {
+            Ok(definitions) => definitions,
+            Err(error) => panic!("{}\n{}", error, input),
+        }
+    }
+
+    fn random_string(rng: &mut StdRng) -> String {
+        const CHARS: &[char] = &['a', 'Z', '7', ' ', ',', '\'', '"', '\\', '#', ':', '@', '-', 'é', '竜', '\n', '\t'];
+        let length = rng.gen_range(0..12);
+        (0..length).map(|_| CHARS[rng.gen_range(0..CHARS.len())]).collect()
+    }
+
+    fn random_status(rng: &mut StdRng) -> StatusEffect {
+        let kind = StatusKind::ALL[rng.gen_range(0..StatusKind::ALL.len())];
+        StatusEffect::new(kind, rng.gen_range(-50..50), rng.gen_range(1..20))
+    }
+
+    /// The item, half of the time in a random slot that takes it.
+    fn random_slot(rng: &mut StdRng, item: Item) -> Item {
+        let slots: Vec<Slot> = Slot::ALL.into_iter().filter(|slot| slot.accepts(item.type_())).collect();
+        match rng.gen_bool(0.5) {
+            true => item.with_slot(slots[rng.gen_range(0..slots.len())]),
+            false => item,
+        }
+    }
+
+    fn random_item(rng: &mut StdRng, name: &str) -> Item {
+        let description = random_string(rng);
+        let value = rng.gen_range(-1000..1000);
+        match rng.gen_range(0..5) {
+            0 => {
+                let weapon = Item::new_weapon(name, &description, value, rng.gen_range(-50..50));
+                let weapon = match rng.gen_bool(0.5) {
+                    true => weapon.with_inflicts(random_status(rng)),
+                    false => weapon,
+                };
+                random_slot(rng, weapon)
+            },
+            1 => {
+                let armor = Item::new_armor(name, &description, value, rng.gen_range(-50..50));
+                random_slot(rng, armor)
+            },
+            2 => Item::new_exp(name, &description, value),
+            3 => Item::new_gold(value),
+            _ => {
+                let effect = match rng.gen_range(0..6) {
+                    0 => Effect::None,
+                    1 => Effect::Heal(rng.gen_range(-50..50)),
+                    2 => Effect::Damage(rng.gen_range(-50..50)),
+                    3 => Effect::Teleport(Position::new(rng.gen_range(-50..50), rng.gen_range(-50..50))),
+                    4 => Effect::Status(random_status(rng)),
+                    _ => Effect::RandomTeleport,
+                };
+                Item::new_effect(name, &description, value, effect)
+            },
+        }
+    }
+
+    #[test]
+    fn test_writer_weapon() {
+        let mut writer = Writer::new();
+        writer.item(&Item::new_weapon("Big \"Sword\"", "Sharp, shiny", 100, 10));
+        assert_eq!(writer.finish(), "@item\n@weapon\n@name: \"Big \\\"Sword\\\"\"\n@description: \"Sharp, shiny\"\n@value: 100\n@atk: 10\n");
+    }
+
+    #[test]
+    fn test_writer_slots() {
+        let mut writer = Writer::new();
+        writer.item(&Item::new_armor("Helmet", "", 0, 2).with_slot(Slot::Head));
+        writer.item(&Item::new_armor("Mail", "", 0, 4));
+        let mut player = Player::new("Hero");
+        player.equip(Item::new_armor("Helmet", "", 0, 2).with_slot(Slot::Head)).unwrap();
+        player.equip(Item::new_armor("Mail", "", 0, 4)).unwrap();
+        writer.player(&player);
+        let text = writer.finish();
+        assert!(text.contains("@def: 2\n@slot: \"head\"\n"), "{}", text);
+        assert_eq!(text.matches("@slot").count(), 1, "{}", text);
+        assert!(text.contains("@weapon: \"Stick\"\n@equip: \"Helmet\"\n@armor: \"Mail\"\n"), "{}", text);
+    }
+
+    #[test]
+    fn test_writer_statuses() {
+        let poison = StatusEffect::new(StatusKind::Poison, 3, 5);
+        let mut writer = Writer::new();
+        writer.item(&Item::new_weapon("Fang", "", 0, 2).with_inflicts(poison));
+        writer.item(&Item::new_effect("Venom", "", 0, Effect::Status(poison)));
+        let text = writer.finish();
+        assert!(text.contains("@atk: 2\n@effect: \"poison\" 3 \"for\" 5\n"), "{}", text);
+        assert!(text.contains("@value: 0\n@effect: \"poison\" 3 \"for\" 5\n"), "{}", text);
+    }
+
+    #[test]
+    fn test_writer_round_trip_items() {
+        let mut rng = StdRng::seed_from_u64(6);
+        for _ in 0..200 {
+            let name = random_string(&mut rng);
+            let item = random_item(&mut rng, &name);
+            let treasure = rng.gen_bool(0.5).then(|| rng.gen_range(0..5));
+            let mut writer = Writer::new();
+            writer.item_with(&item, treasure);
+            let text = writer.finish();
+
+            match parse(&text).as_slice() {
+                [Definition::Item(definition)] => {
+                    assert_eq!(definition.item, item, "{}", text);
+                    assert_eq!(definition.treasure, treasure, "{}", text);
+                },
+                other => panic!("unexpected definitions {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_writer_map() {
+        let mut map = Map::new();
+        map.add_event(Position::new(0, 0), Event::Empty);
+        map.add_event(Position::new(1, 0), Event::Teleport(Position::new(-1, 1)));
+        map.add_event(Position::new(2, 0), Event::End);
+        map.add_event(Position::new(-1, 1), Event::Treasure(Item::new_gold(1), 5));
+        map.add_event(Position::new(0, 1), Event::Treasure(Item::new_gold(1), 5));
+        map.set_terrain(&Position::new(1, 1), Terrain::Water);
+        let mut writer = Writer::new();
+        writer.map("small", &map);
+        assert_eq!(writer.finish(), concat!(
+            "@map\n",
+            "@name: \"small\"\n",
+            "@row: \"#PaE\"\n",
+            "@row: \"bb~#\"\n",
+            "@legend: \"a\" @teleport: 0 1\n",
+            "@legend: \"b\" @treasure: \"Gold\" 5\n",
+        ));
+
+        map.change_event(&Position::new(0, 0), Event::StairsUp);
+        let mut writer = Writer::new();
+        writer.map("small", &map);
+        assert!(writer.finish().contains("@origin: 1 0\n@row: \"#<aE\"\n"));
+    }
+
+    #[test]
+    fn test_writer_round_trip_catalog() {
+        let mut rng = StdRng::seed_from_u64(66);
+        for _ in 0..50 {
+            let mut items = vec![Item::new_gold(rng.gen_range(1..10))];
+            for i in 0..rng.gen_range(1..6) {
+                let name = format!("{}{}", random_string(&mut rng), i);
+                let item = random_item(&mut rng, &name);
+                if !matches!(item.type_(), ItemType::Gold(_)) {
+                    items.push(item);
+                }
+            }
+            let pick = |rng: &mut StdRng| items[rng.gen_range(0..items.len())].clone();
+
+            let mut writer = Writer::new();
+            for item in &items {
+                writer.item_with(item, rng.gen_bool(0.5).then(|| rng.gen_range(0..5)));
+            }
+            let mut monsters = vec![];
+            for i in 0..rng.gen_range(0..4) {
+                let (drop, qte) = match rng.gen_bool(0.5) {
+                    true => (Some(pick(&mut rng)), rng.gen_range(0..10)),
+                    false => (None, 0),
+                };
+                let mut monster = Monster::new(
+                    &format!("{}{}", random_string(&mut rng), i),
+                    rng.gen_range(-100..100),
+                    pick(&mut rng),
+                    drop,
+                    qte,
+                    rng.gen_range(0..100),
+                );
+                if rng.gen_bool(0.5) {
+                    monster = monster.with_armor(pick(&mut rng));
+                }
+                monster = monster.with_behaviour(match rng.gen_range(0..4) {
+                    0 => Behaviour::Idle,
+                    1 => Behaviour::Wander,
+                    2 => Behaviour::Patrol(rng.gen_range(0..10)),
+                    _ => Behaviour::Chase(rng.gen_range(0..10)),
+                });
+                writer.monster(&monster);
+                monsters.push(monster);
+            }
+            let mut inventory = vec![];
+            for _ in 0..rng.gen_range(0..5) {
+                let item = pick(&mut rng);
+                let owned = inventory.iter().any(|owned: &Item| owned.name() == item.name());
+                if !owned && !matches!(item.type_(), ItemType::Exp(_)) {
+                    inventory.push(item);
+                }
+            }
+            let mut equipment = Equipment::new();
+            for _ in 0..rng.gen_range(0..4) {
+                let item = pick(&mut rng);
+                if Equipment::slot_for(&item).is_ok_and(|slot| equipment.get(slot).is_none()) {
+                    equipment.equip(item).unwrap();
+                }
+            }
+            let mut player = Player::new_all(
+                &random_string(&mut rng),
+                rng.gen_range(1..100),
+                rng.gen_range(1..100),
+                Position::new(0, 0),
+                equipment,
+                Inventory::with_capacity(rng.gen_range(5..30)),
+                rng.gen_range(0..100),
+                rng.gen_range(1..10),
+                rng.gen_range(100..200),
+            );
+            for item in inventory {
+                player.add_item(item).unwrap();
+            }
+            for _ in 0..rng.gen_range(0..3) {
+                player.add_status(random_status(&mut rng));
+            }
+            writer.player(&player);
+            let mut hints = Hints::new(rng.gen_range(0..5));
+            for kind in HintKind::ALL {
+                hints.set_message(kind, &random_string(&mut rng));
+            }
+            writer.hints(&hints);
+            let formula = Formula::default()
+                .with_minimum(rng.gen_range(0..5))
+                .with_critical(rng.gen_range(0..=100), rng.gen_range(0..500))
+                .with_variance(rng.gen_range(0..=100));
+            writer.combat(&formula);
+            let generator = match rng.gen_bool(0.5) {
+                true => Some(Generator::new(Generator::ALGORITHMS[rng.gen_range(0..Generator::ALGORITHMS.len())], &[]).unwrap()),
+                false => None,
+            };
+            if let Some(generator) = &generator {
+                writer.generator(generator);
+            }
+            let mut maps = BTreeMap::new();
+            for i in 0..rng.gen_range(0..3) {
+                let mut map = Map::new();
+                for _ in 0..rng.gen_range(0..12) {
+                    let position = Position::new(rng.gen_range(-4..4), rng.gen_range(-4..4));
+                    let terrain = [Terrain::Floor, Terrain::Wall, Terrain::Door, Terrain::Water, Terrain::Pit][rng.gen_range(0..5)];
+                    let event = match rng.gen_range(0..7) {
+                        0 if !monsters.is_empty() => {
+                            let mut monster = monsters[rng.gen_range(0..monsters.len())].clone();
+                            monster.set_life(rng.gen_range(1..50));
+                            monster.set_walked(rng.gen_range(0..5));
+                            Event::Monster(monster)
+                        },
+                        1 => match pick(&mut rng) {
+                            item if matches!(item.type_(), ItemType::Gold(_)) => {
+                                Event::Treasure(Item::new_gold(1), rng.gen_range(0..100))
+                            },
+                            item => Event::Treasure(item, rng.gen_range(0..100)),
+                        },
+                        2 => Event::Teleport(Position::new(rng.gen_range(-6..6), rng.gen_range(-6..6))),
+                        3 => Event::End,
+                        4 => Event::StairsDown,
+                        5 => Event::StairsUp,
+                        _ => Event::Empty,
+                    };
+                    map.add_tile(position, Tile::new(terrain, event));
+                }
+                writer.map(&format!("map{}", i), &map);
+                maps.insert(format!("map{}", i), map);
+            }
+            let text = writer.finish();
+
+            let mut registry = Registry::new();
+            for definition in parse(&text) {
+                registry.add(definition);
+            }
+            let catalog = match registry.resolve() {
+                Ok(catalog) => catalog,
+                Err(errors) => panic!("{:?}\n{}", errors, text),
+            };
+            assert_eq!(catalog.items(), items.as_slice(), "{}", text);
+            assert_eq!(catalog.monsters(), monsters.as_slice(), "{}", text);
+            assert_eq!(catalog.player(), &player, "{}", text);
+            assert_eq!(catalog.hints(), &hints, "{}", text);
+            assert_eq!(catalog.formula(), &formula, "{}", text);
+            assert_eq!(catalog.generator(), generator.as_ref(), "{}", text);
+            assert_eq!(catalog.maps(), &maps, "{}", text);
+
+            let mut rewriter = Writer::new();
+            rewriter.catalog(&catalog);
+            assert_eq!(rewriter.finish(), text);
+        }
+    }
+}
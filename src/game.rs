@@ -0,0 +1,788 @@
+pub mod command;
+pub mod save;
+
+use std::io::{self, BufRead, Write};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use command::{Command, COMBAT_HELP, HELP};
+use crate::combat::{self, damage::Formula, Action, CombatLog, Ending, Side, Strategy};
+use crate::map::item::{Item, ItemType};
+use crate::map::dungeon::Dungeon;
+use crate::map::hints::Hints;
+use crate::map::knowledge::Knowledge;
+use crate::map::tile::Terrain;
+use crate::map::monster::Monster;
+use crate::map::status::StatusKind;
+use crate::map::{derive_seed, Event, Map, Position, Resolution};
+use crate::player::{equipment::Slot, Direction, Player};
+
+/// Stream of `derive_seed` the fights and the monsters of a turn draw from,
+/// offset by the turn.
+const TURN_STREAM: u64 = 2 << 32;
+
+/// How a command left the run.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Outcome {
+    Continue,
+    Escaped,
+    Died,
+    Quit,
+}
+
+/// Asks the player what to do every round of a fight, first telling them
+/// what happened in the rounds they have not seen yet.
+struct Prompt<'a, I, W> {
+    lines: &'a mut I,
+    output: &'a mut W,
+    shown: usize,
+    error: Option<io::Error>,
+}
+
+impl<'a, I: Iterator<Item = io::Result<String>>, W: Write> Prompt<'a, I, W> {
+    fn new(lines: &'a mut I, output: &'a mut W) -> Self {
+        Prompt { lines, output, shown: 0, error: None }
+    }
+
+    fn show(&mut self, log: &CombatLog) -> io::Result<()> {
+        while self.shown < log.rounds.len() {
+            for line in log.describe(self.shown) {
+                writeln!(self.output, "{}", line)?;
+            }
+            self.shown += 1;
+        }
+        Ok(())
+    }
+
+    /// The action typed by the player; attacking once the input runs out.
+    fn ask(&mut self, log: &CombatLog, player: &Player, monster: &Monster) -> io::Result<Action> {
+        if log.rounds.is_empty() {
+            match log.initiative {
+                Side::Player => writeln!(self.output, "You are quicker than the {}.", monster.name())?,
+                Side::Monster => writeln!(self.output, "The {} is quicker than you.", monster.name())?,
+            }
+        }
+        self.show(log)?;
+        loop {
+            let chance = combat::flee_chance(player, monster);
+            write!(self.output, "[{} {}/{} - {} {} - flee {}%] {}? ", player.name(), player.life(), player.max_life(), monster.name(), monster.life(), chance, COMBAT_HELP)?;
+            self.output.flush()?;
+            let line = match self.lines.next() {
+                Some(line) => line?,
+                None => return Ok(Action::Attack),
+            };
+            match Command::parse_action(&line) {
+                Ok(action) => return Ok(action),
+                Err(error) => writeln!(self.output, "{}", error)?,
+            }
+        }
+    }
+}
+
+impl<I: Iterator<Item = io::Result<String>>, W: Write> Strategy for Prompt<'_, I, W> {
+    fn choose(&mut self, log: &CombatLog, player: &Player, monster: &Monster) -> Action {
+        if self.error.is_some() {
+            return Action::Attack;
+        }
+        self.ask(log, player, monster).unwrap_or_else(|error| {
+            self.error = Some(error);
+            Action::Attack
+        })
+    }
+}
+
+/// A run in progress: the floors of the labyrinth and what the player knows
+/// of them, the player walking them and how many turns have been played
+/// since the dungeon was generated from `seed`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Game {
+    seed: u64,
+    turn: u32,
+    dungeon: Dungeon,
+    player: Player,
+    hints: Hints,
+    formula: Formula,
+    debug_map: bool,
+}
+
+impl Game {
+    /// Start a run on the current floor of `dungeon`, which may be a single map.
+    pub fn new(seed: u64, dungeon: impl Into<Dungeon>, player: Player) -> Game {
+        let mut dungeon = dungeon.into();
+        dungeon.knowledge_mut().visit(*player.position());
+        Game::restore(seed, 0, dungeon, player)
+    }
+
+    /// Resume a run at a given turn, e.g. from a save file.
+    pub fn restore(seed: u64, turn: u32, dungeon: Dungeon, player: Player) -> Game {
+        Game {
+            seed,
+            turn,
+            dungeon,
+            player,
+            hints: Hints::default(),
+            formula: Formula::default(),
+            debug_map: false,
+        }
+    }
+
+    /// Describe the surroundings after each move with `hints`.
+    pub fn with_hints(mut self, hints: Hints) -> Game {
+        self.hints = hints;
+        self
+    }
+
+    /// Deal the blows of every fight with `formula`.
+    pub fn with_formula(mut self, formula: Formula) -> Game {
+        self.formula = formula;
+        self
+    }
+
+    /// Draw the whole map after each move instead of only what the player
+    /// knows of it.
+    pub fn with_debug_map(mut self, debug_map: bool) -> Game {
+        self.debug_map = debug_map;
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    pub fn dungeon(&self) -> &Dungeon {
+        &self.dungeon
+    }
+
+    /// The floor the player is on.
+    pub fn map(&self) -> &Map {
+        self.dungeon.map()
+    }
+
+    pub fn map_mut(&mut self) -> &mut Map {
+        self.dungeon.map_mut()
+    }
+
+    pub fn player(&self) -> &Player {
+        &self.player
+    }
+
+    pub fn player_mut(&mut self) -> &mut Player {
+        &mut self.player
+    }
+
+    /// What the player knows of the floor they are on.
+    pub fn knowledge(&self) -> &Knowledge {
+        self.dungeon.knowledge()
+    }
+
+    pub fn hints(&self) -> &Hints {
+        &self.hints
+    }
+
+    pub fn formula(&self) -> &Formula {
+        &self.formula
+    }
+
+    /// The map as the player should see it.
+    pub fn render(&self) -> String {
+        if self.debug_map {
+            self.map().render_map(&self.player)
+        } else {
+            self.knowledge().render(self.map(), &self.player)
+        }
+    }
+
+    /// Carry out one command, describing what happens on `output`. Fights
+    /// read the player's action for every round from `input`.
+    pub fn execute(&mut self, command: Command, input: &mut impl Iterator<Item = io::Result<String>>, output: &mut impl Write) -> io::Result<Outcome> {
+        match command {
+            Command::Move(direction) => return self.move_player(direction, input, output),
+            Command::Use(index) => return self.use_item(index, input, output),
+            Command::Equip(index) => return self.equip(index, input, output),
+            Command::Unequip(slot) => return self.unequip(slot, input, output),
+            Command::Inventory => self.write_inventory(output)?,
+            Command::Save(path) => match save::save_game(self, &path) {
+                Ok(()) => writeln!(output, "Saved to {}.", path.display())?,
+                Err(error) => writeln!(output, "Could not save to {}: {}", path.display(), error)?,
+            },
+            Command::Help => writeln!(output, "{}", HELP)?,
+            Command::Quit => return Ok(Outcome::Quit),
+        }
+        Ok(Outcome::Continue)
+    }
+
+    fn move_player(&mut self, direction: Direction, input: &mut impl Iterator<Item = io::Result<String>>, output: &mut impl Write) -> io::Result<Outcome> {
+        let destination = self.player.position().step(direction);
+        let blocked = match self.map().terrain_at(&destination) {
+            Terrain::Pit => Some("A pit opens at your feet; you step back."),
+            terrain if !terrain.is_walkable() => Some("A wall blocks the way."),
+            _ => None,
+        };
+        if let Some(message) = blocked {
+            writeln!(output, "{}", message)?;
+            return Ok(Outcome::Continue);
+        }
+        let event = self.map().event_at(&destination).cloned().unwrap_or(Event::Empty);
+
+        let (life, mut rng) = self.start_turn();
+        if self.player.statuses().has(StatusKind::Stun) {
+            writeln!(output, "You are stunned and cannot move.")?;
+            return self.end_turn(life, &mut rng, input, output);
+        }
+        let origin = *self.player.position();
+        self.player.move_to(destination);
+        if let Event::Monster(monster) = &event {
+            writeln!(output, "You fight the {}.", monster.name())?;
+        }
+        let resolution = self.encounter(&destination, &mut rng, input, output)?;
+        self.dungeon.knowledge_mut().visit(destination);
+        // Getting away, or breaking off a fight, means stepping back off the
+        // monster's tile, unless an item already took the player elsewhere.
+        let fled = matches!(&resolution, Resolution::Combat(log) if matches!(log.ending, Ending::Fled | Ending::Stalemate))
+            && self.player.position() == &destination;
+        if fled {
+            self.player.move_to(origin);
+        }
+
+        let arrival = match event {
+            Event::StairsDown => self.dungeon.descend(),
+            Event::StairsUp => self.dungeon.ascend(),
+            _ => None,
+        };
+        if let Some(arrival) = arrival {
+            self.player.move_to(arrival);
+        }
+        self.dungeon.knowledge_mut().visit(*self.player.position());
+
+        match (&resolution, event) {
+            (Resolution::Combat(log), _) => Game::write_ending(log, fled, output)?,
+            (Resolution::BagFull, Event::Treasure(item, count)) => writeln!(output, "You found {}, but your bag is full.", Game::quantity(&item, count))?,
+            (_, Event::Treasure(item, count)) => writeln!(output, "You found {}.", Game::quantity(&item, count))?,
+            (_, Event::Teleport(_)) => writeln!(output, "The floor shimmers and you are somewhere else.")?,
+            (_, Event::StairsDown) if arrival.is_some() => writeln!(output, "You climb down to floor {}.", self.dungeon.depth() + 1)?,
+            (_, Event::StairsUp) if arrival.is_some() => writeln!(output, "You climb back up to floor {}.", self.dungeon.depth() + 1)?,
+            _ => {},
+        }
+        if resolution == Resolution::Escaped {
+            return Ok(Outcome::Escaped);
+        }
+        self.end_turn(life, &mut rng, input, output)
+    }
+
+    /// Count a new turn, returning the player's life at its start and the
+    /// generator its fights and monsters draw from.
+    fn start_turn(&mut self) -> (i32, StdRng) {
+        self.turn += 1;
+        (self.player.life(), StdRng::seed_from_u64(derive_seed(self.seed, TURN_STREAM + self.turn as u64)))
+    }
+
+    /// Finish a turn the player started with `life`: the monsters act, only
+    /// every other turn around a hasted player, then statuses tick.
+    fn end_turn(&mut self, life: i32, rng: &mut StdRng, input: &mut impl Iterator<Item = io::Result<String>>, output: &mut impl Write) -> io::Result<Outcome> {
+        let hasted = self.player.statuses().has(StatusKind::Haste);
+        if !self.player.is_dead() && (!hasted || self.turn.is_multiple_of(2)) {
+            self.monsters_turn(rng, input, output)?;
+        }
+        if !self.player.is_dead() {
+            self.tick_statuses(output)?;
+        }
+        if self.player.life() < life {
+            writeln!(output, "You lose {} life ({}/{}).", life - self.player.life(), self.player.life().max(0), self.player.max_life())?;
+        }
+
+        if self.player.is_dead() {
+            Ok(Outcome::Died)
+        } else {
+            self.write_hints(output)?;
+            write!(output, "{}", self.render())?;
+            Ok(Outcome::Continue)
+        }
+    }
+
+    /// Resolve the event at `position`, asking the player what to do in
+    /// every round when it is a monster, and tell them how the fight went.
+    fn encounter(&mut self, position: &Position, rng: &mut StdRng, input: &mut impl Iterator<Item = io::Result<String>>, output: &mut impl Write) -> io::Result<Resolution> {
+        let mut prompt = Prompt::new(input, output);
+        let resolution = self.dungeon.map_mut().do_event(position, &mut self.player, &self.formula, &mut prompt, rng);
+        if let Some(error) = prompt.error.take() {
+            return Err(error);
+        }
+        if let Resolution::Combat(log) = &resolution {
+            prompt.show(log)?;
+        }
+        Ok(resolution)
+    }
+
+    /// Let the monsters of the current floor move, fighting those that
+    /// reach the player.
+    fn monsters_turn(&mut self, rng: &mut StdRng, input: &mut impl Iterator<Item = io::Result<String>>, output: &mut impl Write) -> io::Result<()> {
+        let attackers = self.dungeon.map_mut().move_monsters(self.player.position(), rng);
+        for position in attackers {
+            if let Some(Event::Monster(monster)) = self.map().event_at(&position) {
+                writeln!(output, "The {} attacks you!", monster.name())?;
+            }
+            if let Resolution::Combat(log) = self.encounter(&position, rng, input, output)? {
+                Game::write_ending(&log, false, output)?;
+            }
+            self.dungeon.knowledge_mut().visit(*self.player.position());
+            if self.player.is_dead() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Pass a turn of the statuses of the player and of the monsters of
+    /// the current floor, telling the player what wore off and which
+    /// monsters succumbed.
+    fn tick_statuses(&mut self, output: &mut impl Write) -> io::Result<()> {
+        for status in self.player.tick_statuses().expired {
+            writeln!(output, "You are no longer {}.", status.adjective())?;
+        }
+        for monster in self.dungeon.map_mut().tick_monsters() {
+            writeln!(output, "You hear the {} collapse.", monster.name())?;
+        }
+        Ok(())
+    }
+
+    /// Name `count` units of `item`, e.g. "heal" or "10 Gold".
+    fn quantity(item: &Item, count: u32) -> String {
+        match count {
+            1 => item.name().to_string(),
+            count => format!("{} {}", count, item.name()),
+        }
+    }
+
+    fn write_ending(log: &CombatLog, stepped_back: bool, output: &mut impl Write) -> io::Result<()> {
+        match log.ending {
+            Ending::Victory => {
+                writeln!(output, "The {} is defeated.", log.monster)?;
+                match &log.loot {
+                    Some((item, count)) if log.bag_full => writeln!(output, "It drops {} {}, but your bag is full.", count, item.name())?,
+                    Some((item, count)) => writeln!(output, "It drops {} {}.", count, item.name())?,
+                    None => {},
+                }
+            },
+            Ending::Stalemate => writeln!(output, "Neither of you can win; you break off the fight.")?,
+            Ending::Fled if stepped_back => writeln!(output, "You back away; the {} stays where it is.", log.monster)?,
+            Ending::Defeat | Ending::Fled => {},
+        }
+        Ok(())
+    }
+
+    /// Use an item from the inventory. Like a move, this takes a turn: the
+    /// monsters act and statuses tick. An item that cannot be used costs
+    /// nothing.
+    fn use_item(&mut self, index: usize, input: &mut impl Iterator<Item = io::Result<String>>, output: &mut impl Write) -> io::Result<Outcome> {
+        match self.player.use_item(index, self.dungeon.map_mut(), None) {
+            Ok(item) => {
+                writeln!(output, "You use the {}.", item.name())?;
+                let (life, mut rng) = self.start_turn();
+                self.end_turn(life, &mut rng, input, output)
+            },
+            Err(error) => {
+                writeln!(output, "{}", error)?;
+                Ok(Outcome::Continue)
+            },
+        }
+    }
+
+    /// Equip an item from the inventory, putting back what it replaces.
+    /// This takes a turn, like using an item.
+    fn equip(&mut self, index: usize, input: &mut impl Iterator<Item = io::Result<String>>, output: &mut impl Write) -> io::Result<Outcome> {
+        match self.player.equip_item(index) {
+            Ok(slot) => {
+                let item = self.player.equipment().get(slot).unwrap();
+                writeln!(output, "You equip the {}.", item.name())?;
+                self.write_stats(output)?;
+                let (life, mut rng) = self.start_turn();
+                self.end_turn(life, &mut rng, input, output)
+            },
+            Err(error) => {
+                writeln!(output, "{}", error)?;
+                Ok(Outcome::Continue)
+            },
+        }
+    }
+
+    /// Take off what is in `slot`, back to the inventory. This takes a
+    /// turn too.
+    fn unequip(&mut self, slot: Slot, input: &mut impl Iterator<Item = io::Result<String>>, output: &mut impl Write) -> io::Result<Outcome> {
+        match self.player.unequip(slot) {
+            Ok(item) => {
+                writeln!(output, "You take off the {}.", item.name())?;
+                self.write_stats(output)?;
+                let (life, mut rng) = self.start_turn();
+                self.end_turn(life, &mut rng, input, output)
+            },
+            Err(error) => {
+                writeln!(output, "{}", error)?;
+                Ok(Outcome::Continue)
+            },
+        }
+    }
+
+    fn write_stats(&self, output: &mut impl Write) -> io::Result<()> {
+        writeln!(output, "Attack: {}, defense: {}.", self.player.attack(), self.player.defense())
+    }
+
+    /// The hints around the player, unless they are blind.
+    fn write_hints(&self, output: &mut impl Write) -> io::Result<()> {
+        if self.player.statuses().has(StatusKind::Blindness) {
+            return Ok(());
+        }
+        for hint in self.hints.around(self.map(), self.player.position()) {
+            writeln!(output, "{}", hint)?;
+        }
+        Ok(())
+    }
+
+    fn write_inventory(&self, output: &mut impl Write) -> io::Result<()> {
+        for slot in Slot::ALL {
+            let item = self.player.equipment().get(slot).map_or("nothing", |item| item.name());
+            writeln!(output, "{}: {}", slot.name(), item)?;
+        }
+        self.write_stats(output)?;
+        for status in self.player.statuses().iter() {
+            writeln!(output, "Status: {} ({} left)", status.kind.adjective(), status.turns)?;
+        }
+        if self.player.inventory().is_empty() {
+            return writeln!(output, "Your bag is empty.");
+        }
+        for (i, stack) in self.player.inventory().iter().enumerate() {
+            let item = stack.item();
+            match item.type_() {
+                ItemType::Gold(_) => writeln!(output, "{}. {} gold", i + 1, stack.count())?,
+                _ if stack.count() > 1 => writeln!(output, "{}. {} x{} - {}", i + 1, item.name(), stack.count(), item.description())?,
+                _ => writeln!(output, "{}. {} - {}", i + 1, item.name(), item.description())?,
+            }
+        }
+        writeln!(output, "{}/{} slots used.", self.player.inventory().len(), self.player.inventory().capacity())?;
+        Ok(())
+    }
+
+    fn write_summary(&self, outcome: Outcome, output: &mut impl Write) -> io::Result<()> {
+        let title = match outcome {
+            Outcome::Escaped => "You escaped the labyrinth!",
+            Outcome::Died => "You died in the labyrinth.",
+            Outcome::Quit | Outcome::Continue => "You gave up.",
+        };
+        writeln!(output, "=== {} ===", title)?;
+        writeln!(output, "Turns: {}", self.turn)?;
+        if self.dungeon.floors().len() > 1 {
+            writeln!(output, "Floor: {}/{}", self.dungeon.depth() + 1, self.dungeon.floors().len())?;
+        }
+        writeln!(output, "Level: {} ({} exp)", self.player.level(), self.player.experience())?;
+        writeln!(output, "Life: {}/{}", self.player.life().max(0), self.player.max_life())?;
+        writeln!(output, "Gold: {}", self.player.gold())
+    }
+
+    /// Read commands from `input` until the player escapes, dies or quits,
+    /// then write the summary. Running out of input counts as quitting.
+    pub fn play(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<Outcome> {
+        writeln!(output, "{}", HELP)?;
+        self.write_hints(&mut output)?;
+        let mut lines = input.lines();
+        let outcome = loop {
+            write!(output, "> ")?;
+            output.flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => break Outcome::Quit,
+            };
+            let outcome = match Command::parse(&line) {
+                Ok(command) => self.execute(command, &mut lines, &mut output)?,
+                Err(error) => {
+                    writeln!(output, "{}", error)?;
+                    Outcome::Continue
+                },
+            };
+            if outcome != Outcome::Continue {
+                break outcome;
+            }
+        };
+        self.write_summary(outcome, &mut output)?;
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_loader::{Lexer, Parser, Registry};
+    use crate::map::dungeon::Floor;
+    use crate::map::hints::HintKind;
+    use crate::map::item::{Effect, Item};
+    use crate::map::monster::{Behaviour, Monster};
+    use crate::map::status::StatusEffect;
+    use crate::player::{equipment::Equipment, inventory::Inventory};
+
+    fn corridor() -> Game {
+        // (0,0) start, (1,0) treasure, (2,0) monster, (3,0) exit
+        let mut map = Map::new();
+        map.add_event(Position::new(0, 0), Event::Empty);
+        map.add_event(Position::new(1, 0), Event::Treasure(Item::new_effect("heal", "", 10, Effect::Heal(10)), 1));
+        map.add_event(Position::new(2, 0), Event::Monster(Monster::new("slime", 5, Item::new_weapon("goo", "", 0, 5), None, 0, 1)));
+        map.add_event(Position::new(3, 0), Event::End);
+        Game::new(1, map, Player::new("Hero"))
+    }
+
+    fn play(game: &mut Game, input: &str) -> (Outcome, String) {
+        let mut output = vec![];
+        let outcome = game.play(input.as_bytes(), &mut output).unwrap();
+        (outcome, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_game_wall_blocks_move() {
+        let mut game = corridor();
+        let (outcome, output) = play(&mut game, "up\n");
+        assert_eq!(outcome, Outcome::Quit);
+        assert!(output.contains("A wall blocks the way."));
+        assert_eq!(game.player().position(), &Position::new(0, 0));
+        assert_eq!(game.turn(), 0);
+    }
+
+    #[test]
+    fn test_game_terrain() {
+        let mut game = corridor();
+        game.map_mut().add_event(Position::new(0, 1), Event::Empty);
+        game.map_mut().set_terrain(&Position::new(0, 1), Terrain::Pit);
+        game.map_mut().set_terrain(&Position::new(1, 0), Terrain::Water);
+        game.map_mut().set_terrain(&Position::new(-1, 0), Terrain::Wall);
+        let (_, output) = play(&mut game, "down\nleft\nright\n");
+        assert!(output.contains("A pit opens at your feet; you step back."));
+        assert!(output.contains("A wall blocks the way."));
+        assert!(output.contains("You found heal."));
+        assert_eq!(game.player().position(), &Position::new(1, 0));
+        assert_eq!(game.turn(), 1);
+    }
+
+    #[test]
+    fn test_game_flee() {
+        let mut game = corridor();
+        let (outcome, output) = play(&mut game, &format!("right\nright\n{}", "flee\n".repeat(20)));
+        assert_eq!(outcome, Outcome::Quit);
+        assert!(output.contains("You get away from the slime."));
+        assert!(output.contains("You back away; the slime stays where it is."));
+        assert_eq!(game.player().position(), &Position::new(1, 0));
+        assert_eq!(game.map().event_at(&Position::new(2, 0)), Some(&Event::Monster(Monster::new("slime", 5, Item::new_weapon("goo", "", 0, 5), None, 0, 1))));
+        assert_eq!(game.turn(), 2);
+    }
+
+    #[test]
+    fn test_game_monster_attacks() {
+        let mut game = corridor();
+        let wolf = Monster::new("wolf", 4, Item::new_weapon("fangs", "", 0, 3), None, 0, 1).with_behaviour(Behaviour::Chase(5));
+        game.map_mut().add_event(Position::new(0, 1), Event::Empty);
+        game.map_mut().add_event(Position::new(0, 2), Event::Monster(wolf));
+        let (outcome, output) = play(&mut game, "right\nleft\n\n\n\n\n");
+        assert_eq!(outcome, Outcome::Quit);
+        let attack = output.find("The wolf attacks you!").unwrap();
+        assert!(output.find("You found heal.").unwrap() < attack);
+        assert!(output[attack..].contains("The wolf is defeated."));
+        assert_eq!(game.map().event_at(&Position::new(0, 1)), Some(&Event::Empty));
+        assert_eq!(game.map().event_at(&Position::new(0, 2)), Some(&Event::Empty));
+        assert_eq!(game.player().position(), &Position::new(0, 0));
+        assert_eq!(game.turn(), 2);
+    }
+
+    #[test]
+    fn test_game_escape() {
+        let mut game = corridor();
+        let (outcome, output) = play(&mut game, &format!("right\nright\n{}right\nd\n", "f\n".repeat(5)));
+        assert_eq!(outcome, Outcome::Escaped);
+        assert!(output.contains("You found heal."));
+        assert!(output.contains("You fight the slime."));
+        assert!(output.contains("You hit the slime for 1 (0 life left)."));
+        assert!(output.contains("The slime is defeated."));
+        assert!(output.contains("=== You escaped the labyrinth! ==="));
+        assert!(output.contains("Turns: 3"));
+        assert_eq!(game.player().position(), &Position::new(3, 0));
+    }
+
+    #[test]
+    fn test_game_monster_without_drop() {
+        let parser = |input: &str| Parser::new(Lexer::new(input.to_string()).lex_spanned());
+        let mut registry = Registry::new();
+        registry.add_monster(parser("@monster @name: Goblin @life: 1").parse_monster().unwrap());
+        registry.add_player(parser("@player @name: Hero").parse_player().unwrap());
+        let goblin = registry.resolve().unwrap().monsters()[0].clone();
+        assert_eq!(goblin.drop(), &None);
+
+        let mut game = corridor();
+        game.map_mut().change_event(&Position::new(1, 0), Event::Monster(goblin));
+        let (_, output) = play(&mut game, "right\nf\nf\n");
+        assert!(output.contains("The Goblin is defeated."));
+        assert!(!output.contains("It drops"));
+    }
+
+    #[test]
+    fn test_game_statuses() {
+        let mut game = corridor();
+        game.player_mut().add_status(StatusEffect::new(StatusKind::Stun, 0, 1));
+        game.player_mut().add_status(StatusEffect::new(StatusKind::Poison, 3, 2));
+        if let Some(Event::Monster(slime)) = game.map_mut().event_at_mut(&Position::new(2, 0)) {
+            slime.add_status(StatusEffect::new(StatusKind::Poison, 5, 1));
+        }
+        let (_, output) = play(&mut game, "right\ni\nright\n");
+        assert!(output.contains("You are stunned and cannot move.\nYou are no longer stunned.\nYou hear the slime collapse.\nYou lose 3 life (97/100)."));
+        assert!(output.contains("Status: poisoned (1 left)"));
+        assert!(output.contains("You found heal.\nYou are no longer poisoned.\nYou lose 3 life (94/100)."));
+        assert_eq!(game.map().event_at(&Position::new(2, 0)), Some(&Event::Empty));
+        assert_eq!(game.player().position(), &Position::new(1, 0));
+        assert_eq!(game.turn(), 2);
+    }
+
+    #[test]
+    fn test_game_death() {
+        let mut game = corridor();
+        game.map_mut().change_event(&Position::new(1, 0), Event::Monster(Monster::new("dragon", 1000, Item::new_weapon("fire", "", 0, 500), None, 0, 1)));
+        let (outcome, output) = play(&mut game, "right\n");
+        assert_eq!(outcome, Outcome::Died);
+        assert!(output.contains("=== You died in the labyrinth. ==="));
+        assert!(output.contains("Life: 0/100"));
+    }
+
+    #[test]
+    fn test_game_use_and_equip() {
+        let mut game = corridor();
+        game.player_mut().take_damage(30);
+        game.player_mut().add_item(Item::new_weapon("Sword", "", 10, 20)).unwrap();
+        let (_, output) = play(&mut game, "right\ni\nuse 2\nequip 1\nuse 1\nequip 9\nquit\n");
+        assert!(output.contains("1. Sword"));
+        assert!(output.contains("2. heal"));
+        assert!(output.contains("You use the heal."));
+        assert!(output.contains("You equip the Sword."));
+        assert!(output.contains("The Stick cannot be used."));
+        assert!(output.contains("You have no item 9."));
+        assert_eq!(game.player().life(), 80);
+        assert_eq!(game.player().weapon().map(|weapon| weapon.name()), Some("Sword"));
+        assert_eq!(game.player().inventory().get(0).map(|stack| stack.item().name()), Some("Stick"));
+        assert_eq!(game.turn(), 3);
+    }
+
+    #[test]
+    fn test_game_use_takes_a_turn() {
+        let mut game = corridor();
+        game.player_mut().take_damage(30);
+        game.player_mut().add_status(StatusEffect::new(StatusKind::Poison, 3, 2));
+        game.player_mut().add_item(Item::new_effect("heal", "", 10, Effect::Heal(10))).unwrap();
+        let (_, output) = play(&mut game, "use 1\nuse 2\n");
+        assert!(output.contains("You use the heal.\nYou lose 3 life (77/100)."));
+        assert!(output.contains("You have no item 2."));
+        assert_eq!(game.player().statuses().iter().map(|status| status.turns).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(game.turn(), 1);
+    }
+
+    #[test]
+    fn test_game_equip_takes_a_turn() {
+        let mut game = corridor();
+        game.player_mut().add_status(StatusEffect::new(StatusKind::Poison, 3, 3));
+        game.player_mut().add_item(Item::new_weapon("Sword", "", 10, 20)).unwrap();
+        let (_, output) = play(&mut game, "equip 1\nunequip main-hand\nunequip head\n");
+        assert!(output.contains("You equip the Sword.\nAttack: 20, defense: 0.\nYou lose 3 life (97/100)."));
+        assert!(output.contains("You take off the Sword.\nAttack: 0, defense: 0.\nYou lose 3 life (94/100)."));
+        assert!(output.contains("You have nothing in the head slot."));
+        assert_eq!(game.turn(), 2);
+    }
+
+    #[test]
+    fn test_game_equip_from_treasure() {
+        let mut game = corridor();
+        game.map_mut().change_event(&Position::new(1, 0), Event::Treasure(Item::new_armor("Helmet", "", 10, 3).with_slot(Slot::Head), 1));
+        game.player_mut().add_item(Item::new_gold(5)).unwrap();
+        let (_, output) = play(&mut game, "right\nequip 1\nequip 2\ni\nunequip head\nunequip ring\nunequip feet\nquit\n");
+        assert!(output.contains("The Gold cannot be equipped."));
+        assert!(output.contains("You equip the Helmet.\nAttack: 1, defense: 3."));
+        assert!(output.contains("main-hand: Stick\noff-hand: nothing\nhead: Helmet\nbody: nothing\nring: nothing\n"));
+        assert!(output.contains("You take off the Helmet.\nAttack: 1, defense: 0."));
+        assert!(output.contains("You have nothing in the ring slot."));
+        assert!(output.contains("'feet' is not an equipment slot"));
+        assert_eq!(game.player().inventory().get(1).map(|stack| stack.item().name()), Some("Helmet"));
+        assert_eq!(game.turn(), 3);
+    }
+
+    #[test]
+    fn test_game_bag_full() {
+        let mut game = corridor();
+        let mut player = Player::new_all("Hero", 100, 100, Position::new(0, 0), Equipment::new(), Inventory::with_capacity(1), 0, 1, 100);
+        player.add_item(Item::new_weapon("Sword", "", 10, 20)).unwrap();
+        *game.player_mut() = player;
+        let (_, output) = play(&mut game, "right\ni\n");
+        assert!(output.contains("You found heal, but your bag is full."));
+        assert!(output.contains("1. Sword - \n1/1 slots used."));
+        assert!(matches!(game.map().event_at(&Position::new(1, 0)), Some(Event::Treasure(..))));
+    }
+
+    #[test]
+    fn test_game_use_without_target() {
+        let mut game = corridor();
+        game.player_mut().add_item(Item::new_effect("bomb", "", 10, Effect::Damage(50))).unwrap();
+        let (_, output) = play(&mut game, "use 1\nright\nright\nuse 1\n");
+        assert!(output.contains("There is nothing for the bomb to act on."));
+        assert!(output.contains("You use the bomb.\nThe slime is defeated."));
+        assert_eq!(game.player().inventory().iter().map(|stack| stack.item().name()).collect::<Vec<_>>(), vec!["heal"]);
+        assert_eq!(game.turn(), 2);
+    }
+
+    #[test]
+    fn test_game_fog_of_war() {
+        let mut game = corridor();
+        assert_eq!(game.render(), " # \n#P?\n # \n");
+        let (_, output) = play(&mut game, "right\nquit\n");
+        assert!(output.contains(" ## \n#.P?\n ## \n"));
+        assert!(!output.contains('M'));
+        assert!(game.knowledge().is_visited(&Position::new(1, 0)));
+
+        let game = corridor().with_debug_map(true);
+        assert_eq!(game.render(), "PTME\n");
+    }
+
+    #[test]
+    fn test_game_stairs() {
+        let mut top = Map::new();
+        top.add_event(Position::new(0, 0), Event::Empty);
+        top.add_event(Position::new(1, 0), Event::StairsDown);
+        let mut below = Map::new();
+        below.add_event(Position::new(0, 0), Event::StairsUp);
+        below.add_event(Position::new(0, 1), Event::Treasure(Item::new_gold(1), 5));
+        below.add_event(Position::new(0, 2), Event::End);
+        let dungeon = Dungeon::new(vec![Floor::new(top), Floor::new(below)], 0);
+        let mut game = Game::new(1, dungeon, Player::new("Hero"));
+
+        let (outcome, output) = play(&mut game, "right\ndown\nup\nquit\n");
+        assert_eq!(outcome, Outcome::Quit);
+        assert!(output.contains("You climb down to floor 2."));
+        assert!(output.contains("You climb back up to floor 1."));
+        assert!(output.contains("Floor: 1/2"));
+        assert_eq!(game.dungeon().depth(), 0);
+        assert_eq!(game.player().position(), &Position::new(1, 0));
+        assert_eq!(game.dungeon().floors()[1].map.event_at(&Position::new(0, 1)), Some(&Event::Empty));
+        assert!(game.dungeon().floors()[1].knowledge.is_visited(&Position::new(0, 1)));
+
+        let (outcome, _) = play(&mut game, "left\nright\ndown\ndown\n");
+        assert_eq!(outcome, Outcome::Escaped);
+        assert_eq!(game.dungeon().depth(), 1);
+    }
+
+    #[test]
+    fn test_game_hints() {
+        let mut game = corridor();
+        let (_, output) = play(&mut game, "right\nquit\n");
+        assert!(output.contains("Something glints."));
+        assert!(output.contains("You hear growling."));
+        assert!(!output.contains("You feel a draft."));
+
+        let mut hints = Hints::new(2);
+        hints.set_message(HintKind::End, "Fresh air.");
+        let mut game = corridor().with_hints(hints);
+        let (_, output) = play(&mut game, "right\nquit\n");
+        assert!(output.contains("Fresh air."));
+        assert!(!output.contains("Something glints."));
+    }
+}
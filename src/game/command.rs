@@ -0,0 +1,162 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::combat::Action;
+use crate::player::{equipment::Slot, Direction};
+
+/// One action typed by the player at the prompt.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Command {
+    Move(Direction),
+    Use(usize),
+    Equip(usize),
+    Unequip(Slot),
+    Inventory,
+    Save(PathBuf),
+    Help,
+    Quit,
+}
+
+pub const HELP: &str = "\
+commands:
+  up | down | left | right (or w, s, a, d)   move one step
+  use <n>                                    use the n-th inventory item
+  equip <n>                                  equip the n-th inventory item
+  unequip <slot>                             take off what is in a slot, e.g. head
+  inventory (or i)                           list what you carry
+  save <file>                                save the run
+  help (or h)                                show this help
+  quit (or q)                                give up";
+
+pub const COMBAT_HELP: &str = "attack (or just enter), defend, use <n> or flee";
+
+/// Error produced when a line typed by the player is not a command.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CommandError {
+    Empty,
+    Unknown(String),
+    /// Not one of the actions offered during a fight.
+    UnknownAction(String),
+    MissingArgument(&'static str),
+    InvalidIndex(String),
+    UnknownSlot(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Empty => write!(f, "type a command, or help"),
+            CommandError::Unknown(command) => write!(f, "unknown command '{}', type help", command),
+            CommandError::UnknownAction(action) => write!(f, "'{}' is not something you can do in a fight", action),
+            CommandError::MissingArgument(command) => write!(f, "{} needs an argument", command),
+            CommandError::InvalidIndex(index) => write!(f, "'{}' is not an inventory number", index),
+            CommandError::UnknownSlot(slot) => write!(f, "'{}' is not an equipment slot", slot),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl Command {
+    /// An inventory number typed after `command`, as an index starting at 0.
+    fn index(command: &'static str, argument: Option<&str>) -> Result<usize, CommandError> {
+        let argument = argument.ok_or(CommandError::MissingArgument(command))?;
+        match argument.parse::<usize>() {
+            Ok(index) if index > 0 => Ok(index - 1),
+            _ => Err(CommandError::InvalidIndex(argument.to_string())),
+        }
+    }
+
+    /// Parse what to do in a round of a fight. An empty line attacks.
+    pub fn parse_action(line: &str) -> Result<Action, CommandError> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("attack").to_lowercase();
+        match command.as_str() {
+            "attack" | "fight" | "f" => Ok(Action::Attack),
+            "defend" | "block" | "b" => Ok(Action::Defend),
+            "use" | "u" => Ok(Action::Use(Command::index("use", words.next())?)),
+            "flee" | "run" | "r" => Ok(Action::Flee),
+            _ => Err(CommandError::UnknownAction(command)),
+        }
+    }
+
+    /// Parse a line of input. Inventory numbers start at 1, as listed by
+    /// the inventory command, and are returned as indices starting at 0.
+    pub fn parse(line: &str) -> Result<Command, CommandError> {
+        let mut words = line.split_whitespace();
+        let command = words.next().ok_or(CommandError::Empty)?.to_lowercase();
+        let argument = words.next();
+
+        let index = |name: &'static str| Command::index(name, argument);
+
+        match command.as_str() {
+            "up" | "w" | "north" => Ok(Command::Move(Direction::Up)),
+            "down" | "s" | "south" => Ok(Command::Move(Direction::Down)),
+            "left" | "a" | "west" => Ok(Command::Move(Direction::Left)),
+            "right" | "d" | "east" => Ok(Command::Move(Direction::Right)),
+            "use" | "u" => Ok(Command::Use(index("use")?)),
+            "equip" | "e" => Ok(Command::Equip(index("equip")?)),
+            "unequip" | "remove" => {
+                let argument = argument.ok_or(CommandError::MissingArgument("unequip"))?;
+                Slot::from_name(&argument.to_lowercase())
+                    .map(Command::Unequip)
+                    .ok_or_else(|| CommandError::UnknownSlot(argument.to_string()))
+            },
+            "inventory" | "i" => Ok(Command::Inventory),
+            "save" => Ok(Command::Save(PathBuf::from(argument.ok_or(CommandError::MissingArgument("save"))?))),
+            "help" | "h" | "?" => Ok(Command::Help),
+            "quit" | "q" | "exit" => Ok(Command::Quit),
+            _ => Err(CommandError::Unknown(command)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_parse_moves() {
+        assert_eq!(Command::parse("up"), Ok(Command::Move(Direction::Up)));
+        assert_eq!(Command::parse("  S "), Ok(Command::Move(Direction::Down)));
+        assert_eq!(Command::parse("left"), Ok(Command::Move(Direction::Left)));
+        assert_eq!(Command::parse("d"), Ok(Command::Move(Direction::Right)));
+    }
+
+    #[test]
+    fn test_command_parse_indices() {
+        assert_eq!(Command::parse("use 1"), Ok(Command::Use(0)));
+        assert_eq!(Command::parse("equip 3"), Ok(Command::Equip(2)));
+        assert_eq!(Command::parse("use"), Err(CommandError::MissingArgument("use")));
+        assert_eq!(Command::parse("use 0"), Err(CommandError::InvalidIndex("0".to_string())));
+        assert_eq!(Command::parse("equip sword"), Err(CommandError::InvalidIndex("sword".to_string())));
+    }
+
+    #[test]
+    fn test_command_parse_unequip() {
+        assert_eq!(Command::parse("unequip head"), Ok(Command::Unequip(Slot::Head)));
+        assert_eq!(Command::parse("remove Off-Hand"), Ok(Command::Unequip(Slot::OffHand)));
+        assert_eq!(Command::parse("unequip"), Err(CommandError::MissingArgument("unequip")));
+        assert_eq!(Command::parse("unequip feet"), Err(CommandError::UnknownSlot("feet".to_string())));
+    }
+
+    #[test]
+    fn test_command_parse_action() {
+        assert_eq!(Command::parse_action(""), Ok(Action::Attack));
+        assert_eq!(Command::parse_action("Fight"), Ok(Action::Attack));
+        assert_eq!(Command::parse_action("b"), Ok(Action::Defend));
+        assert_eq!(Command::parse_action("use 2"), Ok(Action::Use(1)));
+        assert_eq!(Command::parse_action("run"), Ok(Action::Flee));
+        assert_eq!(Command::parse_action("use"), Err(CommandError::MissingArgument("use")));
+        assert_eq!(Command::parse_action("up"), Err(CommandError::UnknownAction("up".to_string())));
+    }
+
+    #[test]
+    fn test_command_parse_others() {
+        assert_eq!(Command::parse("i"), Ok(Command::Inventory));
+        assert_eq!(Command::parse("save run.blind"), Ok(Command::Save(PathBuf::from("run.blind"))));
+        assert_eq!(Command::parse("quit"), Ok(Command::Quit));
+        assert_eq!(Command::parse(""), Err(CommandError::Empty));
+        assert_eq!(Command::parse("fly"), Err(CommandError::Unknown("fly".to_string())));
+    }
+}
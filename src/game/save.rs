@@ -0,0 +1,204 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::Game;
+use crate::data_loader::{ContentRoot, Lexer, LoadError, Parser, Registry, ValidationError, Writer};
+use crate::map::item::{Item, ItemType};
+use crate::map::monster::Monster;
+use crate::map::{Event, Position};
+use crate::player::inventory::Stack;
+
+/// The events of every floor, top to bottom.
+fn events(game: &Game) -> impl Iterator<Item = (&Position, &Event)> {
+    game.dungeon().floors().iter().flat_map(|floor| floor.map.events())
+}
+
+/// Items the save has to define so every name it mentions resolves. Gold is
+/// always defined as a single coin, amounts being written where it is used.
+fn items(game: &Game) -> Vec<Item> {
+    let player = game.player();
+    let mut items: Vec<Item> = player.equipment().iter().map(|(_, item)| item).chain(player.inventory().iter().map(Stack::item)).cloned().collect();
+    for (_, event) in events(game) {
+        match event {
+            Event::Monster(monster) => {
+                items.push(monster.weapon().clone());
+                items.extend(monster.armor().cloned());
+                if let Some((drop, _)) = monster.drop() {
+                    items.push(drop.clone());
+                }
+            },
+            Event::Treasure(item, _) => items.push(item.clone()),
+            _ => {},
+        }
+    }
+
+    let mut unique: Vec<Item> = vec![];
+    for item in items {
+        let item = match item.type_() {
+            ItemType::Gold(_) => Item::new_gold(1),
+            _ => item,
+        };
+        if !item.name().is_empty() && !unique.iter().any(|known| known.name() == item.name()) {
+            unique.push(item);
+        }
+    }
+    unique
+}
+
+fn monsters(game: &Game) -> Vec<&Monster> {
+    let mut monsters: Vec<&Monster> = vec![];
+    for (_, event) in events(game) {
+        if let Event::Monster(monster) = event {
+            if !monsters.iter().any(|known| known.name() == monster.name()) {
+                monsters.push(monster);
+            }
+        }
+    }
+    monsters
+}
+
+/// The whole run as `.blind` text: the items and monsters it mentions, the
+/// player, the hints, the damage formula and a `@save` block with the seed, the turn and, for
+/// every floor, its tiles and what the player knows of them. The player's
+/// statuses are kept but not those of monsters, as a tile only records a
+/// monster's name and life.
+pub fn to_blind(game: &Game) -> String {
+    let mut writer = Writer::new();
+    for item in items(game) {
+        writer.item(&item);
+    }
+    for monster in monsters(game) {
+        writer.monster(monster);
+    }
+    writer.player(game.player());
+    writer.hints(game.hints());
+    writer.combat(game.formula());
+    writer.save(game.seed(), game.turn(), game.dungeon());
+    writer.finish()
+}
+
+/// Read back a run written by `to_blind`.
+pub fn from_blind(source: String, file: Option<&Path>) -> Result<Game, LoadError> {
+    let mut parser = Parser::new(Lexer::new(source).lex_spanned());
+    if let Some(file) = file {
+        parser = parser.with_file(file);
+    }
+
+    let mut registry = Registry::new();
+    for definition in parser.parse_all()? {
+        registry.add(definition);
+    }
+    let mut catalog = registry.resolve()?;
+    let saved = catalog.take_saved().ok_or(LoadError::Invalid(vec![ValidationError::MissingSave]))?;
+    let hints = catalog.hints().clone();
+    let formula = *catalog.formula();
+    Ok(Game::restore(saved.seed, saved.turn, saved.dungeon, catalog.into_player()).with_hints(hints).with_formula(formula))
+}
+
+pub fn save_game(game: &Game, path: &Path) -> io::Result<()> {
+    fs::write(path, to_blind(game))
+}
+
+pub fn load_game(path: &Path) -> Result<Game, LoadError> {
+    from_blind(ContentRoot::read(path)?, Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::damage::Formula;
+    use crate::map::dungeon::{Dungeon, Floor};
+    use crate::map::knowledge::Knowledge;
+    use crate::map::monster::Behaviour;
+    use crate::map::tile::Terrain;
+    use crate::map::{Map, Position};
+    use crate::player::{equipment::{Equipment, Slot}, inventory::Inventory, Player};
+
+    fn game() -> Game {
+        let sword = Item::new_weapon("Sword", "A \"sharp\" sword", 100, 10);
+        let slime_weapon = Item::new_weapon("slimeWeapon", "", 0, 5);
+        let mut map = Map::new();
+        map.generate_map(4747515738017, 50);
+        map.change_event(&Position::new(0, 1), Event::Monster(Monster::new("slime", 7, slime_weapon.clone(), Some(Item::new_exp("experience", "", 1)), 30, 1)));
+        map.change_event(&Position::new(0, 2), Event::Monster(Monster::new("slime", 10, slime_weapon, Some(Item::new_exp("experience", "", 1)), 30, 1)));
+        map.change_event(&Position::new(1, 1), Event::Treasure(Item::new_gold(1), 25));
+        map.change_event(&Position::new(1, 2), Event::Treasure(sword.clone(), 1));
+        map.change_event(&Position::new(2, 2), Event::Teleport(Position::new(-3, 4)));
+        map.set_terrain(&Position::new(1, 1), Terrain::Water);
+        map.set_terrain(&Position::new(5, 5), Terrain::Door);
+        map.set_terrain(&Position::new(5, 6), Terrain::Pit);
+        map.set_terrain(&Position::new(5, 7), Terrain::Wall);
+
+        let mut equipment = Equipment::new();
+        equipment.equip(sword).unwrap();
+        equipment.equip(Item::new_armor("Helmet", "", 5, 2).with_slot(Slot::Head)).unwrap();
+        equipment.equip(Item::new_armor("Buckler", "", 5, 1).with_slot(Slot::OffHand)).unwrap();
+        let mut player = Player::new_all("Hero", 42, 110, Position::new(-1, 3), equipment, Inventory::new(), 50, 2, 150);
+        player.add_item(Item::new_gold(1)).unwrap();
+        player.add_item(Item::new_gold(12)).unwrap();
+        player.add_item(Item::new_effect("heal", "a heal potion", 10, crate::map::item::Effect::Heal(10))).unwrap();
+        player.add_item(Item::new_effect("heal", "a heal potion", 10, crate::map::item::Effect::Heal(10))).unwrap();
+
+        let mut knowledge = Knowledge::new();
+        knowledge.visit(Position::new(0, 0));
+        knowledge.visit(Position::new(-1, 3));
+        knowledge.sense(Position::new(5, 5));
+
+        map.change_event(&Position::new(3, 3), Event::StairsDown);
+
+        let mut below = Floor::new(Map::new());
+        below.map.add_event(Position::new(0, 0), Event::StairsUp);
+        below.map.add_event(Position::new(1, 0), Event::Monster(Monster::new("bat", 3, Item::new_weapon("fang", "", 0, 1), Some(Item::new_exp("experience", "", 1)), 10, 2).with_armor(Item::new_armor("hide", "", 4, 2)).with_behaviour(Behaviour::Chase(3))));
+        below.map.add_event(Position::new(2, 0), Event::End);
+        below.knowledge.visit(Position::new(0, 0));
+
+        Game::restore(i64::MAX as u64 - 7, 12, Dungeon::new(vec![Floor { map, knowledge }, below], 1), player)
+            .with_formula(Formula::default().with_critical(10, 150).with_variance(30))
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let game = game();
+        let text = to_blind(&game);
+        let loaded = match from_blind(text.clone(), None) {
+            Ok(loaded) => loaded,
+            Err(error) => panic!("{}\n{}", error, text),
+        };
+        assert_eq!(loaded, game);
+        assert_eq!(to_blind(&loaded), text);
+    }
+
+    #[test]
+    fn test_save_file_round_trip() {
+        let game = game();
+        let path = std::env::temp_dir().join(format!("blind_labyrinth_save_{}.blind", std::process::id()));
+        save_game(&game, &path).unwrap();
+        let loaded = load_game(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, game);
+    }
+
+    #[test]
+    fn test_save_single_floor() {
+        let text = "@player @name: Hero\n@save @seed: 3 @turn: 2 @tile: 0 0 @empty @tile: 1 0 @end @visited: 0 0";
+        let game = from_blind(text.to_string(), None).unwrap();
+        assert_eq!(game.dungeon().floors().len(), 1);
+        assert_eq!(game.map().event_at(&Position::new(1, 0)), Some(&Event::End));
+        assert!(game.knowledge().is_visited(&Position::new(0, 0)));
+    }
+
+    #[test]
+    fn test_save_missing_floor() {
+        let error = from_blind("@player @name: Hero\n@save @current: 1 @depth: 0 @tile: 0 0 @empty".to_string(), None).unwrap_err();
+        assert_eq!(error.to_string(), "<input>:2:1: current floor 1 is not saved");
+        let error = from_blind("@player @name: Hero\n@save @depth: 1".to_string(), None).unwrap_err();
+        assert!(error.to_string().contains("0 after @depth"), "{}", error);
+    }
+
+    #[test]
+    fn test_load_game_without_save_block() {
+        let error = from_blind("@player @name: Hero".to_string(), None).unwrap_err();
+        assert_eq!(error.to_string(), "no @save definition");
+    }
+}
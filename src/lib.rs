@@ -1,48 +1,57 @@
-mod player;
-mod map;
-mod data_loader;
+pub mod player;
+pub mod map;
+pub mod data_loader;
+pub mod config;
+pub mod game;
+pub mod combat;
 
-use std::{fs::{self, DirEntry}, io};
+use std::error::Error;
+use std::io;
 
-pub fn run() {
-    let items_dir : Vec<io::Result<DirEntry>> = fs::read_dir(".\\data\\items").unwrap().collect();
-    let items_length = items_dir.len();
-    let mut items = vec![];
+use config::Config;
+use game::Game;
+use map::dungeon::Dungeon;
+use map::Position;
 
-    let monsters_dir : Vec<io::Result<DirEntry>> = fs::read_dir(".\\data\\monsters").unwrap().collect();
-    let monsters_length = monsters_dir.len();
-    let mut monsters = vec![];
+const SEED: u64 = 4747515738017;
 
-    let player_path = ".\\data\\player.blind";
-    println!("Loading items");
-    for (i, path) in items_dir.iter().enumerate() {
-        let path = path.as_ref().unwrap().path();
-        let item_string = fs::read_to_string(path).unwrap();
-        let item: Option<map::item::Item> = data_loader::Parser::new(data_loader::Lexer::new(item_string).lex()).parse_item();
-        println!("{}/{} : {}", i + 1, items_length, item.as_ref().unwrap().name());
-        match item {
-            Some(item) => items.push(item),
-            None => println!("Error parsing item {}", i + 1),
-        }
-    }
+pub fn run(config: &Config) -> Result<(), Box<dyn Error>> {
+    let game = match config.load() {
+        Some(path) => {
+            println!("Loading save {}", path.display());
+            game::save::load_game(path)?
+        },
+        None => {
+            println!("Loading content from {}", config.content().root().display());
+            let catalog = config.content().load()?;
+            println!("{} items, {} monsters", catalog.items().len(), catalog.monsters().len());
+            let start = catalog.player().position();
+            let dungeon = match config.map() {
+                Some(name) => {
+                    println!("Playing the map {}", name);
+                    let map = catalog.map(name).ok_or_else(|| format!("no @map named '{}'", name))?;
+                    Dungeon::from(map.clone())
+                },
+                None => {
+                    let generator = config.generator().or(catalog.generator()).cloned().unwrap_or_default();
+                    println!("Generating the map with {}", generator);
+                    Dungeon::generate(SEED, config.floors(), &generator, &catalog.population(config.density()), start)
+                },
+            };
+            for (depth, floor) in dungeon.floors().iter().enumerate() {
+                let start = if depth == 0 { *start } else { Position::new(0, 0) };
+                for diagnostic in floor.map.validate(&start) {
+                    eprintln!("warning: floor {}: {}", depth + 1, diagnostic);
+                }
+            }
+            let hints = catalog.hints().clone();
+            let formula = *catalog.formula();
+            Game::new(SEED, dungeon, catalog.into_player()).with_hints(hints).with_formula(formula)
+        },
+    };
+    let mut game = game.with_debug_map(config.debug_map());
 
-    println!("Loading monsters");
-    for (i, path) in monsters_dir.iter().enumerate() {
-        let path = path.as_ref().unwrap().path();
-        let monster_string = fs::read_to_string(path).unwrap();
-        let monster: Option<map::monster::Monster> = data_loader::Parser::new(data_loader::Lexer::new(monster_string).lex()).load_monster(&items);
-        println!("{}/{} : {}", i + 1, monsters_length, monster.as_ref().unwrap().name());
-        match monster {
-            Some(monster) => monsters.push(monster),
-            None => println!("Error parsing monster {}", i + 1),
-        }
-    }
-    println!("Loading player");
-    let player_string = fs::read_to_string(player_path).unwrap();
-    let player = data_loader::Parser::new(data_loader::Lexer::new(player_string).lex()).parse_player(&items);
-    println!("1/1 : {:?}", player);
+    game.play(io::stdin().lock(), io::stdout())?;
 
-    let mut map = map::Map::new();
-    map.generate_map(4747515738017, 1000);
-    map.print_map(&player);
+    Ok(())
 }
@@ -1,3 +1,22 @@
-fn main() {
-    blind_labyrinth::run();
-}
\ No newline at end of file
+use std::process::ExitCode;
+
+use blind_labyrinth::config::Config;
+
+fn main() -> ExitCode {
+    let config = match Config::from_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("{}", Config::USAGE);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    match blind_labyrinth::run(&config) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        },
+    }
+}
@@ -1,12 +1,37 @@
+pub mod dungeon;
+pub mod generator;
+pub mod hints;
 pub mod item;
+pub mod knowledge;
 pub mod monster;
+pub mod movement;
+pub mod path;
+pub mod population;
+pub mod status;
+pub mod tile;
+pub mod validation;
 
 use std::collections::HashMap;
-use crate::player;
+use crate::player::{self, Direction};
 use item::Item;
 use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 
+use crate::combat::{self, damage::Formula, CombatLog, Ending, Strategy};
+
+use self::generator::{DrunkardsWalk, MapGenerator};
 use self::monster::Monster;
+use self::population::Population;
+use self::tile::{Terrain, Tile};
+
+/// A seed for an independent random stream derived from `seed`, so that
+/// e.g. populating a map does not replay the draws that shaped it.
+pub fn derive_seed(seed: u64, stream: u64) -> u64 {
+    // splitmix64
+    let mut z = seed ^ stream.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
 pub struct Position {
@@ -19,6 +44,14 @@ impl Position {
         Position { x, y }
     }
 
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
     pub fn add_x(&mut self, x: i32) {
         self.x += x;
     }
@@ -26,19 +59,57 @@ impl Position {
     pub fn add_y(&mut self, y: i32) {
         self.y += y;
     }
+
+    /// The neighbouring position one step towards `direction`.
+    pub fn step(&self, direction: Direction) -> Position {
+        let mut position = *self;
+        match direction {
+            Direction::Up => position.add_y(-1),
+            Direction::Down => position.add_y(1),
+            Direction::Left => position.add_x(-1),
+            Direction::Right => position.add_x(1),
+        }
+        position
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum Event {
     Empty,
     Monster(Monster),
-    Treasure(Item, i32),
+    /// An item and how many of it lie there. Gold lies as coins worth 1.
+    Treasure(Item, u32),
     Teleport(Position),
     End,
+    /// Stairs to the floor below, where the exit of the last floor would be.
+    StairsDown,
+    /// Stairs back to the floor above, where the player arrives.
+    StairsUp,
 }
 
+/// What stepping on a tile led to.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Resolution {
+    Done,
+    Combat(CombatLog),
+    /// The player reached the exit.
+    Escaped,
+    /// The player's bag had no room for the treasure, which stays where it
+    /// is.
+    BagFull,
+}
+
+/// The labyrinth: a tile for every position that is not solid rock.
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Map {
-    map: HashMap<Position, Event>
+    map: HashMap<Position, Tile>
+}
+
+impl Default for Map {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Map {
@@ -48,109 +119,146 @@ impl Map {
         }
     }
 
-    pub fn get_random_position(&self) -> &Position {
+    /// A random walkable position, `None` when there is none.
+    pub fn get_random_position(&self) -> Option<&Position> {
         let mut rng = rand::thread_rng();
-        let keys: Vec<&Position> = self.map.keys().collect();
-        *keys.choose(&mut rng).unwrap()
+        let keys: Vec<&Position> = self.map.iter()
+            .filter(|(_, tile)| tile.is_walkable())
+            .map(|(position, _)| position)
+            .collect();
+        keys.choose(&mut rng).copied()
+    }
+
+    /// Every tile, sorted by position.
+    pub fn tiles(&self) -> Vec<(&Position, &Tile)> {
+        let mut tiles: Vec<(&Position, &Tile)> = self.map.iter().collect();
+        tiles.sort_by_key(|(position, _)| **position);
+        tiles
+    }
+
+    /// Every tile with its event, sorted by position.
+    pub fn events(&self) -> Vec<(&Position, &Event)> {
+        self.tiles().into_iter().map(|(position, tile)| (position, &tile.event)).collect()
     }
 
+    pub fn tile_at(&self, position: &Position) -> Option<&Tile> {
+        self.map.get(position)
+    }
+
+    pub fn add_tile(&mut self, position: Position, tile: Tile) {
+        self.map.insert(position, tile);
+    }
+
+    /// The terrain at `position`, positions without a tile being walls.
+    pub fn terrain_at(&self, position: &Position) -> Terrain {
+        self.tile_at(position).map_or(Terrain::Wall, |tile| tile.terrain)
+    }
+
+    /// Change the terrain of the tile at `position`, adding an empty tile if
+    /// there is none.
+    pub fn set_terrain(&mut self, position: &Position, terrain: Terrain) {
+        self.map.entry(*position).or_insert_with(|| Tile::floor(Event::Empty)).terrain = terrain;
+    }
+
+    pub fn is_walkable(&self, position: &Position) -> bool {
+        self.terrain_at(position).is_walkable()
+    }
+
+    /// The four positions next to `position`.
+    pub fn neighbours(&self, position: &Position) -> [Position; 4] {
+        [Direction::Up, Direction::Down, Direction::Left, Direction::Right].map(|direction| position.step(direction))
+    }
+
+    /// The positions next to `position` the player can step on.
+    pub fn walkable_neighbours(&self, position: &Position) -> Vec<Position> {
+        self.neighbours(position).into_iter().filter(|neighbour| self.is_walkable(neighbour)).collect()
+    }
+
+    /// Add a floor tile holding `event`.
     pub fn add_event(&mut self, position: Position, event: Event) {
-        self.map.insert(position, event);
+        self.map.insert(position, Tile::floor(event));
     }
 
     pub fn event_at(&self, position: &Position) -> Option<&Event> {
-        self.map.get(position)
+        self.map.get(position).map(|tile| &tile.event)
     }
 
     pub fn event_at_mut(&mut self, position: &Position) -> Option<&mut Event> {
-        self.map.get_mut(position)
+        self.map.get_mut(position).map(|tile| &mut tile.event)
     }
 
+    /// Remove the whole tile, leaving solid rock.
     pub fn remove_event(&mut self, position: &Position) {
         self.map.remove(position);
     }
 
+    /// Replace the event at `position`, keeping its terrain, or add a floor
+    /// tile holding it.
     pub fn change_event(&mut self, position: &Position, event: Event) {
-        self.map.insert(*position, event);
-    }
-
-    pub fn do_event(&mut self, position: &Position, player: &mut player::Player) -> bool {
-        if let Some(event) = self.event_at_mut(position) {
-            match event {
-                Event::Empty => {},
-                Event::Monster(monster) => {
-                    player.take_damage_from(&monster.weapon_mut());
-                    if let Some(weapon) = player.weapon() {
-                        monster.take_damage_from(weapon);
-                    }
-                    
-                    if monster.life() <= 0 {
-                        if let Some((drop, qte)) = monster.drop_mut().take() {
-                            for _ in 0..qte {
-                                player.add_item(drop.clone());
-                            }
-                        }
-                        self.change_event(position, Event::Empty);
-                    }
-                },
-                Event::Treasure(item, _value) => {
-                    player.add_item(item.clone());
-                    self.change_event(position, Event::Empty);
-                },
-                Event::Teleport(new_position) => {
-                    player.move_to(*new_position);
-                },
-                Event::End => {
-                    return true;
-                },
-            }
+        match self.map.get_mut(position) {
+            Some(tile) => tile.event = event,
+            None => self.add_event(*position, event),
         }
-
-        false
     }
 
-    pub fn generate_map(&mut self, seed: u64, size: u32) {
-        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
-
-        let mut grid = HashMap::new();
-        let start = Position::new(0, 0);
-        // use random walk to generate map
-        let mut current = start;
-        for _ in 0..size {
-            let direction = rng.gen_range(0..4);
-            match direction {
-                0 => current.add_x(1),
-                1 => current.add_x(-1),
-                2 => current.add_y(1),
-                3 => current.add_y(-1),
-                _ => unreachable!(),
-            }
-            let event: Event = Event::Empty; // Change to random event
-            grid.insert(current, event);
+    /// Resolve the event of the tile the player stepped on. A monster is
+    /// fought with `combat::fight`, the player's actions chosen by
+    /// `strategy` and blows dealt by `formula`, and leaves the tile once
+    /// defeated.
+    pub fn do_event(&mut self, position: &Position, player: &mut player::Player, formula: &Formula, strategy: &mut impl Strategy, rng: &mut impl Rng) -> Resolution {
+        match self.event_at(position).cloned() {
+            Some(Event::Monster(mut monster)) => {
+                let log = combat::fight(player, &mut monster, self, formula, strategy, rng);
+                match log.ending {
+                    Ending::Victory => self.change_event(position, Event::Empty),
+                    _ => self.change_event(position, Event::Monster(monster)),
+                }
+                return Resolution::Combat(log);
+            },
+            Some(Event::Treasure(item, count)) => {
+                if player.add_items(item, count).is_err() {
+                    return Resolution::BagFull;
+                }
+                self.change_event(position, Event::Empty);
+            },
+            Some(Event::Teleport(new_position)) => {
+                player.move_to(new_position);
+            },
+            Some(Event::End) => return Resolution::Escaped,
+            Some(Event::Empty | Event::StairsDown | Event::StairsUp) | None => {},
         }
 
-        grid.insert(start, Event::Empty);
+        Resolution::Done
+    }
 
-        self.map = grid;
+    /// Replace the map with a random walk of `size` steps from the origin.
+    pub fn generate_map(&mut self, seed: u64, size: u32) {
+        *self = DrunkardsWalk::new(size).generate(seed);
+    }
 
-        let mut end = self.get_random_tile_seeded(&mut rng);
-        while end.1 != Event::Empty {
-            end = self.get_random_tile_seeded(&mut rng);
+    /// Place monsters and treasures from `population` on the empty floor
+    /// tiles, leaving `start` empty. The same seed always gives the same placement.
+    pub fn populate(&mut self, seed: u64, start: &Position, population: &Population) {
+        let mut rng = StdRng::seed_from_u64(derive_seed(seed, 1));
+        let mut positions: Vec<Position> = self.map.iter()
+            .filter(|(position, tile)| *position != start && **tile == Tile::floor(Event::Empty))
+            .map(|(position, _)| *position)
+            .collect();
+        positions.sort();
+
+        for position in positions {
+            if let Some(event) = population.roll(&mut rng) {
+                self.change_event(&position, event);
+            }
         }
-        
-        self.change_event(&end.0, Event::End);
     }
 
-    fn get_random_tile_seeded(&self, rng: &mut StdRng) -> (Position, Event) {
-        let index = rng.gen_range(0..self.map.len());
-        // sort keys to get deterministic result
-        let mut keys: Vec<Position> = self.map.keys().cloned().collect();
-        keys.sort();
-        let key = keys[index];
-        (key, self.map.get(&key).unwrap().clone())
+    pub fn print_map(&self, player: &player::Player) {
+        print!("{}", self.render_map(player));
     }
 
-    pub fn print_map(&self, player: &player::Player) {
+    /// Draw the map as text, one line per row, with the player as `P`.
+    pub fn render_map(&self, player: &player::Player) -> String {
         let mut min_x = 0;
         let mut max_x = 0;
         let mut min_y = 0;
@@ -171,30 +279,27 @@ impl Map {
             }
         }
 
+        let mut output = String::new();
         for y in min_y..=max_y {
             for x in min_x..=max_x {
                 let position = Position::new(x, y);
                 if &position == player.position() {
-                    print!("P");
+                    output.push('P');
                 } else {
-                    match self.event_at(&position) {
-                        Some(Event::Empty) => print!("."),
-                        Some(Event::Monster(_)) => print!("M"),
-                        Some(Event::Treasure(_, _)) => print!("T"),
-                        Some(Event::Teleport(_)) => print!("X"),
-                        Some(Event::End) => print!("E"),
-                        None => print!(" "),
-                    }
+                    output.push(self.tile_at(&position).map_or(' ', Tile::symbol));
                 }
             }
-            println!();
+            output.push('\n');
         }
+        output
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::player::Player;
+    use crate::combat::{Action, Aggressive, Side};
+    use crate::map::item::Effect;
+    use crate::player::{inventory::Stack, Player};
 
     use super::*;
 
@@ -217,6 +322,10 @@ mod tests {
         assert_eq!(map.event_at(&Position::new(0, 0)), None);
     }
 
+    fn rng() -> StdRng {
+        StdRng::seed_from_u64(0)
+    }
+
     #[test]
     fn test_map_do_event_empty() {
         let mut map = Map::new();
@@ -224,7 +333,7 @@ mod tests {
         let event = Event::Empty;
         map.add_event(position, event);
         let mut player = Player::new("Player 1");
-        map.do_event(&Position::new(0, 0), &mut player);
+        assert_eq!(map.do_event(&Position::new(0, 0), &mut player, &Formula::default(), &mut Aggressive, &mut rng()), Resolution::Done);
         assert_eq!(player.life(), 100);
     }
 
@@ -233,11 +342,13 @@ mod tests {
         let mut map = Map::new();
         let position = Position::new(0, 0);
         let weapon = Item::new_weapon("Weapon 1", "",10, 10);
-        let event = Event::Monster(Monster::new("Monster 1", 100,  weapon, None, 0, 1));
+        let event = Event::Monster(Monster::new("Monster 1", 100,  weapon.clone(), None, 0, 1));
         map.add_event(position, event);
         let mut player = Player::new("Player 1");
-        map.do_event(&Position::new(0, 0), &mut player);
-        assert_eq!(player.life(), 90);
+        let resolution = map.do_event(&Position::new(0, 0), &mut player, &Formula::default(), &mut Aggressive, &mut rng());
+        assert!(matches!(resolution, Resolution::Combat(CombatLog { ending: Ending::Defeat, .. })));
+        assert!(player.is_dead());
+        assert_eq!(map.event_at(&Position::new(0, 0)), Some(&Event::Monster(Monster::new("Monster 1", 90, weapon, None, 0, 1))));
     }
 
     #[test]
@@ -248,9 +359,10 @@ mod tests {
         let event = Event::Monster(Monster::new("Monster 1", 100, weapon.clone(), None, 0, 1));
         map.add_event(position, event);
         let mut player = Player::new("Player 1");
-        player.equip_weapon(Item::new_weapon("Weapon 2", "", 20, 20));
-        map.do_event(&Position::new(0, 0), &mut player);
-        assert_eq!(player.life(), 90);
+        player.equip(Item::new_weapon("Weapon 2", "", 20, 20)).unwrap();
+        let mut strategy = |log: &CombatLog, _: &Player, _: &Monster| if log.rounds.is_empty() { Action::Attack } else { Action::Flee };
+        let resolution = map.do_event(&Position::new(0, 0), &mut player, &Formula::default(), &mut strategy, &mut rng());
+        assert!(matches!(resolution, Resolution::Combat(CombatLog { ending: Ending::Fled, .. })));
         assert_eq!(map.event_at(&Position::new(0, 0)), Some(&Event::Monster(Monster::new("Monster 1", 80, weapon, None, 0, 1))));
     }
 
@@ -261,9 +373,11 @@ mod tests {
         let event = Event::Monster(Monster::new("Monster 1", 10,  Item::new_weapon("Weapon 1", "", 10, 10), None, 0, 1));
         map.add_event(position, event);
         let mut player = Player::new("Player 1");
-        player.equip_weapon(Item::new_weapon("Weapon 2","", 20, 20));
-        map.do_event(&Position::new(0, 0), &mut player);
-        assert_eq!(player.life(), 90);
+        player.equip(Item::new_weapon("Weapon 2","", 20, 20)).unwrap();
+        let resolution = map.do_event(&Position::new(0, 0), &mut player, &Formula::default(), &mut Aggressive, &mut rng());
+        let Resolution::Combat(log) = resolution else { panic!("no fight") };
+        assert_eq!(log.ending, Ending::Victory);
+        assert_eq!(player.life(), if log.initiative == Side::Player { 100 } else { 90 });
         assert_eq!(map.event_at(&Position::new(0, 0)), Some(&Event::Empty));
     }
 
@@ -272,12 +386,20 @@ mod tests {
         let mut map = Map::new();
         let position = Position::new(0, 0);
         let item = Item::new_weapon("Weapon 1","", 10, 10);
-        let event = Event::Treasure(item.clone(), 10);
+        let event = Event::Treasure(item.clone(), 1);
         map.add_event(position, event);
         let mut player = Player::new("Player 1");
-        map.do_event(&Position::new(0, 0), &mut player);
-        assert_eq!(player.inventory()[0], item);
+        map.do_event(&Position::new(0, 0), &mut player, &Formula::default(), &mut Aggressive, &mut rng());
+        assert_eq!(player.inventory().get(0).map(Stack::item), Some(&item));
         assert_eq!(map.event_at(&Position::new(0, 0)), Some(&Event::Empty));
+
+        let heal = Item::new_effect("heal", "", 10, Effect::Heal(10));
+        map.add_event(position, Event::Treasure(heal.clone(), 10));
+        map.do_event(&position, &mut player, &Formula::default(), &mut Aggressive, &mut rng());
+        assert_eq!(player.inventory().count("heal"), 10);
+        map.add_event(position, Event::Treasure(Item::new_gold(1), 25));
+        map.do_event(&position, &mut player, &Formula::default(), &mut Aggressive, &mut rng());
+        assert_eq!(player.gold(), 25);
     }
 
     #[test]
@@ -287,7 +409,7 @@ mod tests {
         let event = Event::Teleport(Position::new(1, 1));
         map.add_event(position, event);
         let mut player = Player::new("Player 1");
-        map.do_event(&Position::new(0, 0), &mut player);
+        map.do_event(&Position::new(0, 0), &mut player, &Formula::default(), &mut Aggressive, &mut rng());
         assert_eq!(player.position(), &Position::new(1, 1));
     }
 
@@ -298,7 +420,7 @@ mod tests {
         let event = Event::End;
         map.add_event(position, event);
         let mut player = Player::new("Player 1");
-        assert_eq!(map.do_event(&Position::new(0, 0), &mut player), true);
+        assert_eq!(map.do_event(&Position::new(0, 0), &mut player, &Formula::default(), &mut Aggressive, &mut rng()), Resolution::Escaped);
     }
 
     #[test]
@@ -316,6 +438,18 @@ mod tests {
         assert_eq!(monster.life(), 80);
     }
 
+    #[test]
+    fn test_monster_armor() {
+        let monster = Monster::new("Monster 1", 100, Item::new_weapon("Weapon 1", "", 10, 10), None, 0, 1);
+        assert_eq!((monster.attack(), monster.defense()), (10, 0));
+        let mut monster = monster.with_armor(Item::new_armor("Shell", "", 100, 6));
+        assert_eq!(monster.defense(), 6);
+        monster.take_damage_from(&Item::new_weapon("Weapon 2", "", 20, 20));
+        assert_eq!(monster.life(), 86);
+        monster.take_damage_from(&Item::new_weapon("Weapon 3", "", 1, 1));
+        assert_eq!(monster.life(), 85);
+    }
+
     #[test]
     fn test_monster_drop() {
         let monster = Monster::new("Monster 1", 100, Item::new_weapon("Weapon 1", "", 10, 10), Some(Item::new_weapon("Weapon 2", "", 20, 20)), 1, 1);
@@ -328,8 +462,48 @@ mod tests {
         let mut player = Player::new("Player 1");
         monster.take_damage(100);
         if let Some((drop, _)) = monster.drop_mut().take() {
-            player.add_item(drop);
+            player.add_item(drop).unwrap();
         }
-        assert_eq!(player.inventory()[0], Item::new_weapon("Weapon 2", "", 20, 20));
+        assert_eq!(player.inventory().get(0).map(Stack::item), Some(&Item::new_weapon("Weapon 2", "", 20, 20)));
+    }
+
+    #[test]
+    fn test_map_populate() {
+        let mut population = Population::new(population::Density { monsters: 30, treasures: 30 });
+        population.add_monster(Monster::new("slime", 10, Item::new_weapon("goo", "", 0, 5), None, 0, 1));
+        population.add_treasure(Item::new_weapon("stick", "", 1, 5), 1);
+
+        let start = Position::new(0, 0);
+        let mut map = Map::new();
+        map.generate_map(42, 300);
+        map.populate(42, &start, &population);
+        let mut again = Map::new();
+        again.generate_map(42, 300);
+        again.populate(42, &start, &population);
+        assert_eq!(map, again);
+
+        let events: Vec<&Event> = map.events().into_iter().map(|(_, event)| event).collect();
+        assert!(events.iter().any(|event| matches!(event, Event::Monster(_))));
+        assert!(events.iter().any(|event| matches!(event, Event::Treasure(_, _))));
+        assert_eq!(events.iter().filter(|event| ***event == Event::End).count(), 1);
+        assert_eq!(map.event_at(&start), Some(&Event::Empty));
+    }
+
+    #[test]
+    fn test_map_terrain() {
+        let mut map = Map::new();
+        map.add_event(Position::new(0, 0), Event::Empty);
+        map.add_event(Position::new(1, 0), Event::End);
+        map.set_terrain(&Position::new(1, 0), Terrain::Door);
+        map.set_terrain(&Position::new(0, 1), Terrain::Pit);
+        assert_eq!(map.event_at(&Position::new(1, 0)), Some(&Event::End));
+        assert_eq!(map.terrain_at(&Position::new(0, 1)), Terrain::Pit);
+        assert_eq!(map.terrain_at(&Position::new(-1, 0)), Terrain::Wall);
+        assert!(map.is_walkable(&Position::new(1, 0)));
+        assert!(!map.is_walkable(&Position::new(0, 1)));
+        assert_eq!(map.walkable_neighbours(&Position::new(0, 0)), vec![Position::new(1, 0)]);
+
+        map.change_event(&Position::new(1, 0), Event::Empty);
+        assert_eq!(map.tile_at(&Position::new(1, 0)), Some(&Tile::new(Terrain::Door, Event::Empty)));
     }
-}
\ No newline at end of file
+}
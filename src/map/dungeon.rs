@@ -0,0 +1,210 @@
+use super::generator::MapGenerator;
+use super::knowledge::Knowledge;
+use super::population::Population;
+use super::{derive_seed, Event, Map, Position};
+
+/// One level of the dungeon and what the player knows of it.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Floor {
+    pub map: Map,
+    pub knowledge: Knowledge,
+}
+
+impl Floor {
+    pub fn new(map: Map) -> Floor {
+        Floor { map, knowledge: Knowledge::new() }
+    }
+}
+
+/// The floors of a run, linked by stairs, and the one the player is on.
+/// Floor 0 is the top; only the last floor has an exit.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Dungeon {
+    floors: Vec<Floor>,
+    current: usize,
+}
+
+impl From<Map> for Dungeon {
+    fn from(map: Map) -> Dungeon {
+        Dungeon::new(vec![Floor::new(map)], 0)
+    }
+}
+
+impl Dungeon {
+    /// A dungeon with the player on floor `current`, which is clamped to the
+    /// floors there are. There is always at least one floor.
+    pub fn new(mut floors: Vec<Floor>, current: usize) -> Dungeon {
+        if floors.is_empty() {
+            floors.push(Floor::default());
+        }
+        let current = current.min(floors.len() - 1);
+        Dungeon { floors, current }
+    }
+
+    /// `floors` levels laid out by `generator`. The top floor uses `seed`
+    /// itself and every other floor a seed derived from it, so the same seed
+    /// always gives the same dungeon. The exit of every floor but the last is
+    /// replaced by stairs down, and the floors below the top have stairs up
+    /// where their layout starts. Each floor is populated for its depth,
+    /// leaving `start` empty on the top floor.
+    pub fn generate(seed: u64, floors: u32, generator: &impl MapGenerator, population: &Population, start: &Position) -> Dungeon {
+        let floors = floors.max(1);
+        let levels = (0..floors)
+            .map(|depth| {
+                let seed = Dungeon::floor_seed(seed, depth);
+                let mut map = generator.generate(seed);
+                if depth + 1 < floors {
+                    if let Some(end) = Dungeon::find(&map, &Event::End) {
+                        map.change_event(&end, Event::StairsDown);
+                    }
+                }
+                if depth > 0 {
+                    map.change_event(&Position::new(0, 0), Event::StairsUp);
+                }
+                map.populate(seed, start, &population.at_depth(depth));
+                Floor::new(map)
+            })
+            .collect();
+        Dungeon::new(levels, 0)
+    }
+
+    /// The seed of the floor `depth` levels down.
+    pub fn floor_seed(seed: u64, depth: u32) -> u64 {
+        match depth {
+            0 => seed,
+            depth => derive_seed(seed, (1 << 32) + depth as u64),
+        }
+    }
+
+    fn find(map: &Map, event: &Event) -> Option<Position> {
+        map.events().into_iter().find(|(_, found)| *found == event).map(|(position, _)| *position)
+    }
+
+    pub fn floors(&self) -> &[Floor] {
+        &self.floors
+    }
+
+    /// How many floors below the top the player is.
+    pub fn depth(&self) -> usize {
+        self.current
+    }
+
+    pub fn floor(&self) -> &Floor {
+        &self.floors[self.current]
+    }
+
+    pub fn floor_mut(&mut self) -> &mut Floor {
+        &mut self.floors[self.current]
+    }
+
+    pub fn map(&self) -> &Map {
+        &self.floor().map
+    }
+
+    pub fn map_mut(&mut self) -> &mut Map {
+        &mut self.floor_mut().map
+    }
+
+    pub fn knowledge(&self) -> &Knowledge {
+        &self.floor().knowledge
+    }
+
+    pub fn knowledge_mut(&mut self) -> &mut Knowledge {
+        &mut self.floor_mut().knowledge
+    }
+
+    /// Go down to the next floor and return where the player arrives: its
+    /// stairs up, or the origin when it has none. `None` on the last floor.
+    pub fn descend(&mut self) -> Option<Position> {
+        if self.current + 1 >= self.floors.len() {
+            return None;
+        }
+        self.current += 1;
+        Some(Dungeon::find(self.map(), &Event::StairsUp).unwrap_or(Position::new(0, 0)))
+    }
+
+    /// Go back up to the previous floor, as it was left, and return where the
+    /// player arrives: its stairs down. `None` on the top floor.
+    pub fn ascend(&mut self) -> Option<Position> {
+        if self.current == 0 {
+            return None;
+        }
+        self.current -= 1;
+        Some(Dungeon::find(self.map(), &Event::StairsDown).unwrap_or(Position::new(0, 0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::generator::{Generator, Layout, Rooms};
+    use crate::map::item::Item;
+    use crate::map::monster::Monster;
+    use crate::map::population::Density;
+
+    fn population() -> Population {
+        let mut population = Population::new(Density { monsters: 10, treasures: 5 });
+        population.add_monster(Monster::new("slime", 10, Item::new_weapon("goo", "", 0, 2), None, 5, 1));
+        population.add_treasure(Item::new_gold(1), 1);
+        population
+    }
+
+    fn count(map: &Map, event: &Event) -> usize {
+        map.events().into_iter().filter(|(_, found)| *found == event).count()
+    }
+
+    #[test]
+    fn test_dungeon_generate() {
+        let generator = Generator::default();
+        let dungeon = Dungeon::generate(7, 3, &generator, &population(), &Position::new(0, 0));
+        assert_eq!(dungeon.floors().len(), 3);
+        assert_eq!(dungeon.depth(), 0);
+        assert_eq!(dungeon, Dungeon::generate(7, 3, &generator, &population(), &Position::new(0, 0)));
+
+        for (depth, floor) in dungeon.floors().iter().enumerate() {
+            let last = depth == 2;
+            assert_eq!(count(&floor.map, &Event::StairsDown), if last { 0 } else { 1 });
+            assert_eq!(count(&floor.map, &Event::End), if last { 1 } else { 0 });
+            assert_eq!(count(&floor.map, &Event::StairsUp), if depth == 0 { 0 } else { 1 });
+            assert!(floor.map.validate(&Position::new(0, 0)).is_empty(), "floor {}", depth);
+        }
+        assert_ne!(dungeon.floors()[0].map, dungeon.floors()[1].map);
+        assert_eq!(dungeon.floors()[0].map, {
+            let mut map = generator.generate(7);
+            map.change_event(&Dungeon::find(&map, &Event::End).unwrap(), Event::StairsDown);
+            map.populate(7, &Position::new(0, 0), &population());
+            map
+        });
+    }
+
+    #[test]
+    fn test_dungeon_stairs() {
+        let generator = Generator::from(Layout::Rooms(Rooms::default()));
+        let mut dungeon = Dungeon::generate(3, 2, &generator, &population(), &Position::new(0, 0));
+        assert_eq!(dungeon.ascend(), None);
+
+        let down = Dungeon::find(dungeon.map(), &Event::StairsDown).unwrap();
+        dungeon.knowledge_mut().visit(down);
+        assert_eq!(dungeon.descend(), Some(Position::new(0, 0)));
+        assert_eq!(dungeon.depth(), 1);
+        assert_eq!(dungeon.descend(), None);
+        assert!(!dungeon.knowledge().is_visited(&down));
+        dungeon.map_mut().change_event(&Position::new(0, 0), Event::Empty);
+
+        assert_eq!(dungeon.ascend(), Some(down));
+        assert!(dungeon.knowledge().is_visited(&down));
+        assert_eq!(dungeon.descend(), Some(Position::new(0, 0)));
+        assert_eq!(dungeon.map().event_at(&Position::new(0, 0)), Some(&Event::Empty));
+    }
+
+    #[test]
+    fn test_dungeon_from_map() {
+        let mut map = Map::new();
+        map.add_event(Position::new(0, 0), Event::End);
+        let mut dungeon = Dungeon::from(map.clone());
+        assert_eq!(dungeon.floors().len(), 1);
+        assert_eq!(dungeon.map(), &map);
+        assert_eq!(dungeon.descend(), None);
+        assert_eq!(Dungeon::new(vec![], 4).floors().len(), 1);
+    }
+}
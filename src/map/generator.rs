@@ -0,0 +1,366 @@
+mod bsp;
+mod caves;
+mod maze;
+mod rooms;
+mod walk;
+
+use std::collections::HashSet;
+use std::fmt;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::path::Teleports;
+use super::{Event, Map, Position};
+
+pub use bsp::Bsp;
+pub use caves::Caves;
+pub use maze::{Maze, MazeMethod};
+pub use rooms::Rooms;
+pub use walk::DrunkardsWalk;
+
+/// An algorithm laying out the floor of a labyrinth.
+pub trait MapGenerator {
+    /// The floor positions of a new layout and the one the player starts on.
+    fn carve(&self, rng: &mut StdRng) -> (HashSet<Position>, Position);
+
+    /// Fewest steps the exit should be from the start. When no tile is that
+    /// far, the exit goes on one of the farthest.
+    fn exit_distance(&self) -> u32 {
+        0
+    }
+
+    /// A map with an empty tile on every floor position, moved so the start
+    /// is at the origin, and the exit on another random tile at least
+    /// `exit_distance` steps away. The same seed always gives the same map.
+    fn generate(&self, seed: u64) -> Map {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (floors, start) = self.carve(&mut rng);
+
+        let mut positions: Vec<Position> = floors.iter()
+            .map(|position| Position::new(position.x() - start.x(), position.y() - start.y()))
+            .collect();
+        positions.sort();
+
+        let mut map = Map::new();
+        map.add_event(Position::new(0, 0), Event::Empty);
+        for position in &positions {
+            map.add_event(*position, Event::Empty);
+        }
+
+        let distances = map.distance_field(&Position::new(0, 0), Teleports::Ignore);
+        let farthest = distances.values().copied().max().unwrap_or(0);
+        let wanted = self.exit_distance().clamp(1, farthest.max(1));
+        let exits: Vec<&Position> = positions.iter()
+            .filter(|position| distances.get(position).is_some_and(|distance| *distance >= wanted))
+            .collect();
+        if !exits.is_empty() {
+            let end = *exits[rng.gen_range(0..exits.len())];
+            map.change_event(&end, Event::End);
+        }
+        map
+    }
+}
+
+/// Error produced when a generator name or one of its options is not valid.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum GeneratorError {
+    UnknownAlgorithm(String),
+    UnknownOption { algorithm: String, option: String },
+    InvalidOption { option: String, value: String },
+}
+
+impl fmt::Display for GeneratorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeneratorError::UnknownAlgorithm(algorithm) => {
+                write!(f, "unknown map generator '{}' (expected {})", algorithm, Generator::ALGORITHMS.join(", "))
+            },
+            GeneratorError::UnknownOption { algorithm, option } => write!(f, "unknown option '{}' for the {} generator", option, algorithm),
+            GeneratorError::InvalidOption { option, value } => write!(f, "invalid value {} for option '{}'", value, option),
+        }
+    }
+}
+
+impl std::error::Error for GeneratorError {}
+
+/// One of the map generators with its parameters.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Layout {
+    Walk(DrunkardsWalk),
+    Rooms(Rooms),
+    Bsp(Bsp),
+    Caves(Caves),
+    Maze(Maze),
+}
+
+/// A layout and how far its exit must be, as selected on the command line
+/// or in a `@generator` block.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Generator {
+    pub layout: Layout,
+    pub exit_distance: u32,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Generator::from(Layout::Walk(DrunkardsWalk::default()))
+    }
+}
+
+impl From<Layout> for Generator {
+    fn from(layout: Layout) -> Self {
+        Generator {
+            layout,
+            exit_distance: 10,
+        }
+    }
+}
+
+impl Generator {
+    pub const ALGORITHMS: &'static [&'static str] = &["walk", "rooms", "bsp", "caves", "maze", "prim"];
+
+    /// The generator called `algorithm` with its default parameters, then
+    /// each `(option, value)` applied in order.
+    pub fn new(algorithm: &str, options: &[(String, i64)]) -> Result<Generator, GeneratorError> {
+        let mut generator = Generator::from(match algorithm {
+            "walk" => Layout::Walk(DrunkardsWalk::default()),
+            "rooms" => Layout::Rooms(Rooms::default()),
+            "bsp" => Layout::Bsp(Bsp::default()),
+            "caves" => Layout::Caves(Caves::default()),
+            "maze" => Layout::Maze(Maze::default()),
+            "prim" => Layout::Maze(Maze { method: MazeMethod::Prim, ..Maze::default() }),
+            _ => return Err(GeneratorError::UnknownAlgorithm(algorithm.to_string())),
+        });
+        for (option, value) in options {
+            generator.set(option, *value)?;
+        }
+        Ok(generator)
+    }
+
+    /// Read a `name[:option=value,...]` specification, e.g. `rooms:rooms=8,max=6`.
+    pub fn parse(spec: &str) -> Result<Generator, GeneratorError> {
+        let (algorithm, options) = spec.split_once(':').unwrap_or((spec, ""));
+        let mut parsed = vec![];
+        for option in options.split(',').filter(|option| !option.is_empty()) {
+            let (name, value) = option.split_once('=').unwrap_or((option, ""));
+            let value = value.parse().map_err(|_| GeneratorError::InvalidOption {
+                option: name.to_string(),
+                value: value.to_string(),
+            })?;
+            parsed.push((name.to_string(), value));
+        }
+        Generator::new(algorithm, &parsed)
+    }
+
+    pub fn algorithm(&self) -> &'static str {
+        match &self.layout {
+            Layout::Walk(_) => "walk",
+            Layout::Rooms(_) => "rooms",
+            Layout::Bsp(_) => "bsp",
+            Layout::Caves(_) => "caves",
+            Layout::Maze(maze) => match maze.method {
+                MazeMethod::Backtracker => "maze",
+                MazeMethod::Prim => "prim",
+            },
+        }
+    }
+
+    /// Every parameter of the generator, in the order `new` accepts them.
+    /// `exit` sets the exit distance of every layout.
+    pub fn options(&self) -> Vec<(&'static str, u32)> {
+        let mut options = match &self.layout {
+            Layout::Walk(walk) => vec![("steps", walk.steps)],
+            Layout::Rooms(rooms) => vec![
+                ("width", rooms.width),
+                ("height", rooms.height),
+                ("rooms", rooms.rooms),
+                ("min", rooms.min_size),
+                ("max", rooms.max_size),
+            ],
+            Layout::Bsp(bsp) => vec![("width", bsp.width), ("height", bsp.height), ("leaf", bsp.min_leaf)],
+            Layout::Caves(caves) => vec![
+                ("width", caves.width),
+                ("height", caves.height),
+                ("fill", caves.fill),
+                ("steps", caves.steps),
+            ],
+            Layout::Maze(maze) => vec![("width", maze.width), ("height", maze.height)],
+        };
+        options.push(("exit", self.exit_distance));
+        options
+    }
+
+    fn set(&mut self, option: &str, value: i64) -> Result<(), GeneratorError> {
+        let check = |min: i64, max: i64| match value >= min && value <= max {
+            true => Ok(value as u32),
+            false => Err(GeneratorError::InvalidOption { option: option.to_string(), value: value.to_string() }),
+        };
+        let algorithm = self.algorithm();
+        match (&mut self.layout, option) {
+            (_, "exit") => self.exit_distance = check(0, 1_000_000)?,
+            (Layout::Walk(walk), "steps") => walk.steps = check(0, 1_000_000)?,
+            (Layout::Rooms(rooms), "width") => rooms.width = check(1, MAX_SIZE)?,
+            (Layout::Rooms(rooms), "height") => rooms.height = check(1, MAX_SIZE)?,
+            (Layout::Rooms(rooms), "rooms") => rooms.rooms = check(1, MAX_SIZE)?,
+            (Layout::Rooms(rooms), "min") => rooms.min_size = check(1, MAX_SIZE)?,
+            (Layout::Rooms(rooms), "max") => rooms.max_size = check(1, MAX_SIZE)?,
+            (Layout::Bsp(bsp), "width") => bsp.width = check(1, MAX_SIZE)?,
+            (Layout::Bsp(bsp), "height") => bsp.height = check(1, MAX_SIZE)?,
+            (Layout::Bsp(bsp), "leaf") => bsp.min_leaf = check(3, MAX_SIZE)?,
+            (Layout::Caves(caves), "width") => caves.width = check(1, MAX_SIZE)?,
+            (Layout::Caves(caves), "height") => caves.height = check(1, MAX_SIZE)?,
+            (Layout::Caves(caves), "fill") => caves.fill = check(0, 100)?,
+            (Layout::Caves(caves), "steps") => caves.steps = check(0, 100)?,
+            (Layout::Maze(maze), "width") => maze.width = check(1, MAX_SIZE)?,
+            (Layout::Maze(maze), "height") => maze.height = check(1, MAX_SIZE)?,
+            _ => return Err(GeneratorError::UnknownOption { algorithm: algorithm.to_string(), option: option.to_string() }),
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options: Vec<String> = self.options().iter().map(|(option, value)| format!("{}={}", option, value)).collect();
+        write!(f, "{}:{}", self.algorithm(), options.join(","))
+    }
+}
+
+impl MapGenerator for Generator {
+    fn carve(&self, rng: &mut StdRng) -> (HashSet<Position>, Position) {
+        match &self.layout {
+            Layout::Walk(walk) => walk.carve(rng),
+            Layout::Rooms(rooms) => rooms.carve(rng),
+            Layout::Bsp(bsp) => bsp.carve(rng),
+            Layout::Caves(caves) => caves.carve(rng),
+            Layout::Maze(maze) => maze.carve(rng),
+        }
+    }
+
+    fn exit_distance(&self) -> u32 {
+        self.exit_distance
+    }
+}
+
+/// Largest width or height accepted for grid based generators.
+const MAX_SIZE: i64 = 1000;
+
+/// A rectangle of floor, used by the room based generators.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Rect {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+impl Rect {
+    fn center(&self) -> Position {
+        Position::new(self.x + self.width / 2, self.y + self.height / 2)
+    }
+
+    /// Whether the two rectangles overlap or touch.
+    fn touches(&self, other: &Rect) -> bool {
+        self.x <= other.x + other.width && other.x <= self.x + self.width
+            && self.y <= other.y + other.height && other.y <= self.y + self.height
+    }
+
+    fn carve(&self, floors: &mut HashSet<Position>) {
+        for y in self.y..self.y + self.height {
+            for x in self.x..self.x + self.width {
+                floors.insert(Position::new(x, y));
+            }
+        }
+    }
+}
+
+/// Carve an L-shaped corridor between two positions, turning at a random end.
+fn corridor(floors: &mut HashSet<Position>, from: Position, to: Position, rng: &mut StdRng) {
+    let corner = match rng.gen_bool(0.5) {
+        true => Position::new(to.x(), from.y()),
+        false => Position::new(from.x(), to.y()),
+    };
+    for (a, b) in [(from, corner), (corner, to)] {
+        for y in a.y().min(b.y())..=a.y().max(b.y()) {
+            for x in a.x().min(b.x())..=a.x().max(b.x()) {
+                floors.insert(Position::new(x, y));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every floor can be reached from the origin.
+    fn connected(map: &Map) -> bool {
+        let mut seen = HashSet::new();
+        let mut stack = vec![Position::new(0, 0)];
+        while let Some(position) = stack.pop() {
+            if map.event_at(&position).is_none() || !seen.insert(position) {
+                continue;
+            }
+            for direction in [crate::player::Direction::Up, crate::player::Direction::Down, crate::player::Direction::Left, crate::player::Direction::Right] {
+                stack.push(position.step(direction));
+            }
+        }
+        seen.len() == map.events().len()
+    }
+
+    #[test]
+    fn test_generators_deterministic_and_connected() {
+        for algorithm in Generator::ALGORITHMS {
+            let generator = Generator::new(algorithm, &[]).unwrap();
+            for seed in 0..5 {
+                let map = generator.generate(seed);
+                assert_eq!(map, generator.generate(seed), "{} {}", algorithm, seed);
+                assert!(map.events().len() > 1, "{} {}", algorithm, seed);
+                assert!(connected(&map), "{} {}", algorithm, seed);
+                assert_eq!(map.event_at(&Position::new(0, 0)), Some(&Event::Empty), "{} {}", algorithm, seed);
+                assert_eq!(map.events().iter().filter(|(_, event)| **event == Event::End).count(), 1, "{} {}", algorithm, seed);
+                assert_eq!(map.validate(&Position::new(0, 0)), vec![], "{} {}", algorithm, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generator_exit_distance() {
+        for algorithm in Generator::ALGORITHMS {
+            for exit in [0, 15, 1000] {
+                let generator = Generator::new(algorithm, &[("exit".to_string(), exit)]).unwrap();
+                let map = generator.generate(7);
+                let distances = map.distance_field(&Position::new(0, 0), Teleports::Ignore);
+                let farthest = *distances.values().max().unwrap();
+                let (end, _) = map.events().into_iter().find(|(_, event)| **event == Event::End).unwrap();
+                assert!(distances[end] >= (exit as u32).clamp(1, farthest), "{} {}", algorithm, exit);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generator_parse() {
+        let generator = Generator::parse("rooms:rooms=3,max=5").unwrap();
+        assert_eq!(generator, Generator::from(Layout::Rooms(Rooms { rooms: 3, max_size: 5, ..Rooms::default() })));
+        assert_eq!(Generator::parse(&generator.to_string()), Ok(generator));
+        assert_eq!(Generator::parse("prim").map(|generator| generator.algorithm()), Ok("prim"));
+    }
+
+    #[test]
+    fn test_generator_parse_errors() {
+        assert_eq!(Generator::parse("spiral"), Err(GeneratorError::UnknownAlgorithm("spiral".to_string())));
+        assert_eq!(
+            Generator::parse("maze:fill=3"),
+            Err(GeneratorError::UnknownOption { algorithm: "maze".to_string(), option: "fill".to_string() }),
+        );
+        assert_eq!(
+            Generator::parse("caves:fill=x"),
+            Err(GeneratorError::InvalidOption { option: "fill".to_string(), value: "x".to_string() }),
+        );
+        assert_eq!(
+            Generator::parse("caves:fill=101"),
+            Err(GeneratorError::InvalidOption { option: "fill".to_string(), value: "101".to_string() }),
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use super::{corridor, MapGenerator, Rect};
+use crate::map::Position;
+
+/// A `width` x `height` area split in two again and again until the parts
+/// are smaller than twice `min_leaf`, with a room in every part and
+/// corridors joining the two halves of every split.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Bsp {
+    pub width: u32,
+    pub height: u32,
+    pub min_leaf: u32,
+}
+
+impl Default for Bsp {
+    fn default() -> Self {
+        Bsp {
+            width: 60,
+            height: 40,
+            min_leaf: 8,
+        }
+    }
+}
+
+impl Bsp {
+    /// Fill `area` with rooms and return the center of one of them, to join
+    /// it to the rest.
+    fn split(&self, area: Rect, floors: &mut HashSet<Position>, rng: &mut StdRng) -> Position {
+        let min_leaf = self.min_leaf as i32;
+        let can_split_x = area.width >= 2 * min_leaf;
+        let can_split_y = area.height >= 2 * min_leaf;
+
+        let halves = match (can_split_x, can_split_y) {
+            (false, false) => None,
+            (true, true) => Some(rng.gen_bool(0.5)),
+            (split_x, _) => Some(split_x),
+        };
+        let (first, second) = match halves {
+            None => {
+                let room_width = rng.gen_range((area.width / 2).max(1)..=(area.width - 2).max(1));
+                let room_height = rng.gen_range((area.height / 2).max(1)..=(area.height - 2).max(1));
+                let room = Rect {
+                    x: area.x + rng.gen_range(0..=area.width - room_width),
+                    y: area.y + rng.gen_range(0..=area.height - room_height),
+                    width: room_width,
+                    height: room_height,
+                };
+                room.carve(floors);
+                return room.center();
+            },
+            Some(true) => {
+                let at = rng.gen_range(min_leaf..=area.width - min_leaf);
+                (Rect { width: at, ..area }, Rect { x: area.x + at, width: area.width - at, ..area })
+            },
+            Some(false) => {
+                let at = rng.gen_range(min_leaf..=area.height - min_leaf);
+                (Rect { height: at, ..area }, Rect { y: area.y + at, height: area.height - at, ..area })
+            },
+        };
+
+        let from = self.split(first, floors, rng);
+        let to = self.split(second, floors, rng);
+        corridor(floors, from, to, rng);
+        from
+    }
+}
+
+impl MapGenerator for Bsp {
+    fn carve(&self, rng: &mut StdRng) -> (HashSet<Position>, Position) {
+        let mut floors = HashSet::new();
+        let area = Rect { x: 0, y: 0, width: self.width as i32, height: self.height as i32 };
+        let start = self.split(area, &mut floors, rng);
+        (floors, start)
+    }
+}
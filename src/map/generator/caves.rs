@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use super::MapGenerator;
+use crate::map::Position;
+use crate::player::Direction;
+
+/// Caves grown by a cellular automaton: `fill` percent of a `width` x
+/// `height` area starts as wall, then `steps` times every cell becomes a
+/// wall when most of its neighbours are. Only the largest cave is kept so
+/// every floor is reachable.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Caves {
+    pub width: u32,
+    pub height: u32,
+    pub fill: u32,
+    pub steps: u32,
+}
+
+impl Default for Caves {
+    fn default() -> Self {
+        Caves {
+            width: 60,
+            height: 40,
+            fill: 45,
+            steps: 4,
+        }
+    }
+}
+
+impl Caves {
+    /// Walls among the eight neighbours, counting the outside as wall.
+    fn walls_around(&self, walls: &[Vec<bool>], x: usize, y: usize) -> u32 {
+        let mut count = 0;
+        for dy in -1..=1i64 {
+            for dx in -1..=1i64 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                let outside = nx < 0 || ny < 0 || nx >= self.width as i64 || ny >= self.height as i64;
+                if outside || walls[ny as usize][nx as usize] {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+}
+
+impl MapGenerator for Caves {
+    fn carve(&self, rng: &mut StdRng) -> (HashSet<Position>, Position) {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut walls: Vec<Vec<bool>> = (0..height)
+            .map(|_| (0..width).map(|_| rng.gen_range(0..100) < self.fill).collect())
+            .collect();
+
+        for _ in 0..self.steps {
+            let mut next = walls.clone();
+            for (y, row) in next.iter_mut().enumerate() {
+                for (x, wall) in row.iter_mut().enumerate() {
+                    match self.walls_around(&walls, x, y) {
+                        5.. => *wall = true,
+                        0..=3 => *wall = false,
+                        _ => {},
+                    }
+                }
+            }
+            walls = next;
+        }
+
+        let mut unvisited: HashSet<Position> = HashSet::new();
+        for (y, row) in walls.iter().enumerate() {
+            for (x, wall) in row.iter().enumerate() {
+                if !wall {
+                    unvisited.insert(Position::new(x as i32, y as i32));
+                }
+            }
+        }
+
+        let mut cells: Vec<Position> = unvisited.iter().copied().collect();
+        cells.sort();
+        let mut largest = HashSet::new();
+        for cell in cells {
+            if !unvisited.contains(&cell) {
+                continue;
+            }
+            let mut cave = HashSet::new();
+            let mut stack = vec![cell];
+            while let Some(position) = stack.pop() {
+                if !unvisited.remove(&position) {
+                    continue;
+                }
+                cave.insert(position);
+                for direction in [Direction::Up, Direction::Down, Direction::Left, Direction::Right] {
+                    stack.push(position.step(direction));
+                }
+            }
+            if cave.len() > largest.len() {
+                largest = cave;
+            }
+        }
+
+        if largest.is_empty() {
+            let center = Position::new(self.width as i32 / 2, self.height as i32 / 2);
+            return (HashSet::from([center]), center);
+        }
+        let mut cells: Vec<Position> = largest.iter().copied().collect();
+        cells.sort();
+        let start = cells[rng.gen_range(0..cells.len())];
+        (largest, start)
+    }
+}
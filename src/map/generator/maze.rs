@@ -0,0 +1,91 @@
+use std::collections::HashSet;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use super::MapGenerator;
+use crate::map::Position;
+
+/// How a maze picks the next passage to carve.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MazeMethod {
+    /// Depth first: long winding corridors.
+    Backtracker,
+    /// Randomized Prim: many short dead ends.
+    Prim,
+}
+
+/// A perfect maze of `width` x `height` cells: exactly one path between any
+/// two cells. Cell `(x, y)` is at position `(2x, 2y)`, the positions between
+/// cells being walls or passages.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Maze {
+    pub width: u32,
+    pub height: u32,
+    pub method: MazeMethod,
+}
+
+impl Default for Maze {
+    fn default() -> Self {
+        Maze {
+            width: 20,
+            height: 15,
+            method: MazeMethod::Backtracker,
+        }
+    }
+}
+
+impl Maze {
+    fn neighbours(&self, cell: (i32, i32)) -> Vec<(i32, i32)> {
+        let (x, y) = cell;
+        [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
+            .into_iter()
+            .filter(|(x, y)| *x >= 0 && *y >= 0 && *x < self.width as i32 && *y < self.height as i32)
+            .collect()
+    }
+
+    /// Mark `to` as part of the maze along with the passage from `from`.
+    fn open(floors: &mut HashSet<Position>, from: (i32, i32), to: (i32, i32)) {
+        floors.insert(Position::new(from.0 + to.0, from.1 + to.1));
+        floors.insert(Position::new(2 * to.0, 2 * to.1));
+    }
+}
+
+impl MapGenerator for Maze {
+    fn carve(&self, rng: &mut StdRng) -> (HashSet<Position>, Position) {
+        let start = Position::new(0, 0);
+        let mut floors = HashSet::from([start]);
+
+        match self.method {
+            MazeMethod::Backtracker => {
+                let mut stack = vec![(0, 0)];
+                while let Some(&cell) = stack.last() {
+                    let unvisited: Vec<(i32, i32)> = self.neighbours(cell)
+                        .into_iter()
+                        .filter(|(x, y)| !floors.contains(&Position::new(2 * x, 2 * y)))
+                        .collect();
+                    if unvisited.is_empty() {
+                        stack.pop();
+                        continue;
+                    }
+                    let next = unvisited[rng.gen_range(0..unvisited.len())];
+                    Maze::open(&mut floors, cell, next);
+                    stack.push(next);
+                }
+            },
+            MazeMethod::Prim => {
+                let mut frontier: Vec<((i32, i32), (i32, i32))> = self.neighbours((0, 0)).into_iter().map(|next| ((0, 0), next)).collect();
+                while !frontier.is_empty() {
+                    let (from, to) = frontier.swap_remove(rng.gen_range(0..frontier.len()));
+                    if floors.contains(&Position::new(2 * to.0, 2 * to.1)) {
+                        continue;
+                    }
+                    Maze::open(&mut floors, from, to);
+                    frontier.extend(self.neighbours(to).into_iter().map(|next| (to, next)));
+                }
+            },
+        }
+
+        (floors, start)
+    }
+}
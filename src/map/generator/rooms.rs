@@ -0,0 +1,65 @@
+use std::collections::HashSet;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use super::{corridor, MapGenerator, Rect};
+use crate::map::Position;
+
+/// Rectangular rooms scattered over a `width` x `height` area without
+/// overlapping, each joined to the previous one by a corridor.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Rooms {
+    pub width: u32,
+    pub height: u32,
+    /// How many rooms to try to place.
+    pub rooms: u32,
+    pub min_size: u32,
+    pub max_size: u32,
+}
+
+impl Default for Rooms {
+    fn default() -> Self {
+        Rooms {
+            width: 60,
+            height: 40,
+            rooms: 12,
+            min_size: 4,
+            max_size: 10,
+        }
+    }
+}
+
+impl MapGenerator for Rooms {
+    fn carve(&self, rng: &mut StdRng) -> (HashSet<Position>, Position) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let max_size = self.max_size.max(self.min_size) as i32;
+        let min_size = self.min_size as i32;
+
+        let mut floors = HashSet::new();
+        let mut placed: Vec<Rect> = vec![];
+        for _ in 0..self.rooms.saturating_mul(4) {
+            if placed.len() == self.rooms as usize {
+                break;
+            }
+            let room_width = rng.gen_range(min_size..=max_size).min(width);
+            let room_height = rng.gen_range(min_size..=max_size).min(height);
+            let room = Rect {
+                x: rng.gen_range(0..=width - room_width),
+                y: rng.gen_range(0..=height - room_height),
+                width: room_width,
+                height: room_height,
+            };
+            if placed.iter().any(|other| room.touches(other)) {
+                continue;
+            }
+            room.carve(&mut floors);
+            if let Some(previous) = placed.last() {
+                corridor(&mut floors, previous.center(), room.center(), rng);
+            }
+            placed.push(room);
+        }
+
+        (floors, placed[0].center())
+    }
+}
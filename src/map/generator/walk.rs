@@ -0,0 +1,44 @@
+use std::collections::HashSet;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use super::MapGenerator;
+use crate::map::Position;
+
+/// A random walk from the origin, one step in a random direction at a time.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DrunkardsWalk {
+    pub steps: u32,
+}
+
+impl DrunkardsWalk {
+    pub fn new(steps: u32) -> DrunkardsWalk {
+        DrunkardsWalk { steps }
+    }
+}
+
+impl Default for DrunkardsWalk {
+    fn default() -> Self {
+        DrunkardsWalk::new(1000)
+    }
+}
+
+impl MapGenerator for DrunkardsWalk {
+    fn carve(&self, rng: &mut StdRng) -> (HashSet<Position>, Position) {
+        let start = Position::new(0, 0);
+        let mut floors = HashSet::from([start]);
+        let mut current = start;
+        for _ in 0..self.steps {
+            match rng.gen_range(0..4) {
+                0 => current.add_x(1),
+                1 => current.add_x(-1),
+                2 => current.add_y(1),
+                3 => current.add_y(-1),
+                _ => unreachable!(),
+            }
+            floors.insert(current);
+        }
+        (floors, start)
+    }
+}
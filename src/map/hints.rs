@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+
+use super::{Event, Map, Position};
+
+/// The kinds of events the player can notice without seeing them.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum HintKind {
+    Monster,
+    Treasure,
+    Teleport,
+    End,
+}
+
+impl HintKind {
+    pub const ALL: [HintKind; 4] = [HintKind::Monster, HintKind::Treasure, HintKind::Teleport, HintKind::End];
+
+    /// The kind of hint `event` gives away, if any.
+    pub fn of(event: &Event) -> Option<HintKind> {
+        match event {
+            Event::Monster(_) => Some(HintKind::Monster),
+            Event::Treasure(_, _) => Some(HintKind::Treasure),
+            Event::Teleport(_) => Some(HintKind::Teleport),
+            Event::End | Event::StairsDown => Some(HintKind::End),
+            Event::Empty | Event::StairsUp => None,
+        }
+    }
+}
+
+/// Messages telling the player what lies around them, for every event within
+/// `radius` steps (counted along both axes). An empty message disables the
+/// hints of that kind.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Hints {
+    radius: u32,
+    messages: BTreeMap<HintKind, String>,
+}
+
+impl Default for Hints {
+    fn default() -> Self {
+        let mut hints = Hints::new(1);
+        hints.set_message(HintKind::Monster, "You hear growling.");
+        hints.set_message(HintKind::Treasure, "Something glints.");
+        hints.set_message(HintKind::Teleport, "There is a faint shimmer.");
+        hints.set_message(HintKind::End, "You feel a draft.");
+        hints
+    }
+}
+
+impl Hints {
+    /// The farthest hints reach. Looking around takes time in the square of
+    /// the radius, every turn.
+    pub const MAX_RADIUS: u32 = 10;
+
+    /// Hints without any message.
+    pub fn new(radius: u32) -> Hints {
+        Hints {
+            radius,
+            messages: BTreeMap::new(),
+        }
+    }
+
+    pub fn radius(&self) -> u32 {
+        self.radius
+    }
+
+    pub fn set_radius(&mut self, radius: u32) {
+        self.radius = radius;
+    }
+
+    pub fn message(&self, kind: HintKind) -> &str {
+        self.messages.get(&kind).map_or("", |message| message.as_str())
+    }
+
+    pub fn set_message(&mut self, kind: HintKind, message: &str) {
+        self.messages.insert(kind, message.to_string());
+    }
+
+    /// One message per kind of event found around `position`, not counting
+    /// the event at `position` itself.
+    pub fn around(&self, map: &Map, position: &Position) -> Vec<&str> {
+        let radius = self.radius.min(Hints::MAX_RADIUS) as i32;
+        let mut kinds = vec![];
+        for dy in -radius..=radius {
+            let width = radius - dy.abs();
+            for dx in -width..=width {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                let neighbour = Position::new(position.x() + dx, position.y() + dy);
+                if let Some(kind) = map.event_at(&neighbour).and_then(HintKind::of) {
+                    kinds.push(kind);
+                }
+            }
+        }
+        kinds.sort();
+        kinds.dedup();
+
+        kinds.into_iter()
+            .map(|kind| self.message(kind))
+            .filter(|message| !message.is_empty())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::item::Item;
+    use crate::map::monster::Monster;
+
+    fn map() -> Map {
+        // (0,0) empty, (1,0) monster, (2,0) treasure, (0,2) exit
+        let mut map = Map::new();
+        map.add_event(Position::new(0, 0), Event::Empty);
+        map.add_event(Position::new(1, 0), Event::Monster(Monster::new("slime", 5, Item::new_weapon("goo", "", 0, 5), None, 0, 1)));
+        map.add_event(Position::new(2, 0), Event::Treasure(Item::new_gold(1), 5));
+        map.add_event(Position::new(0, 1), Event::Empty);
+        map.add_event(Position::new(0, 2), Event::End);
+        map
+    }
+
+    #[test]
+    fn test_hints_adjacent() {
+        let hints = Hints::default();
+        assert_eq!(hints.around(&map(), &Position::new(0, 0)), vec!["You hear growling."]);
+        assert_eq!(hints.around(&map(), &Position::new(1, 0)), vec!["Something glints."]);
+    }
+
+    #[test]
+    fn test_hints_radius() {
+        let mut hints = Hints::default();
+        hints.set_radius(2);
+        assert_eq!(hints.around(&map(), &Position::new(0, 0)), vec!["You hear growling.", "Something glints.", "You feel a draft."]);
+        hints.set_radius(0);
+        assert!(hints.around(&map(), &Position::new(0, 0)).is_empty());
+    }
+
+    #[test]
+    fn test_hints_empty_message_is_silent() {
+        let mut hints = Hints::default();
+        hints.set_message(HintKind::Monster, "");
+        assert!(hints.around(&map(), &Position::new(0, 0)).is_empty());
+    }
+}
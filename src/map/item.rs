@@ -1,6 +1,8 @@
 use crate::player;
 use super::{Map, Monster};
+use super::status::StatusEffect;
 use crate::map::Position;
+use crate::player::equipment::Slot;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Effect {
@@ -9,27 +11,38 @@ pub enum Effect {
     Damage(i32),
     Teleport(Position),
     RandomTeleport,
+    /// Puts on a status: a harmful one on the monster being fought, any
+    /// other on the player.
+    Status(StatusEffect),
 }
 
 impl Effect {
-    pub fn apply(&self, map: Map, player: &mut player::Player, monster: Option<&mut Monster>) {
+    /// Apply the effect, borrowing `map` to find where a random teleport
+    /// leads. Returns whether it took hold: damage and harmful statuses need
+    /// the `monster` being fought, a random teleport a walkable tile and
+    /// `None` never does anything. Nothing changes when it does not.
+    pub fn apply(&self, map: &Map, player: &mut player::Player, monster: Option<&mut Monster>) -> bool {
         match self {
+            Effect::None => return false,
             Effect::Heal(heal) => {
-                player.take_damage(-*heal); 
-                ()
+                player.take_damage(-*heal);
             },
-            Effect::Damage(damage) => {
-                if let Some(monster) = monster {
-                    monster.take_damage(*damage);
-                }
+            Effect::Damage(damage) => match monster {
+                Some(monster) => monster.take_damage(*damage),
+                None => return false,
             },
             Effect::Teleport(position) => player.move_to(*position),
-            Effect::RandomTeleport => {
-                let random_position = map.get_random_position();
-                player.move_to(random_position.clone());
+            Effect::RandomTeleport => match map.get_random_position() {
+                Some(position) => player.move_to(*position),
+                None => return false,
             },
-            _ => (),
+            Effect::Status(status) if status.kind.is_harmful() => match monster {
+                Some(monster) => monster.add_status(*status),
+                None => return false,
+            },
+            Effect::Status(status) => player.add_status(*status),
         }
+        true
     }
 }
 
@@ -48,6 +61,8 @@ pub struct Item {
     description: String,
     value: i32,
     type_: ItemType,
+    inflicts: Option<StatusEffect>,
+    slot: Option<Slot>,
 }
 
 impl Item {
@@ -57,6 +72,8 @@ impl Item {
             description: description.to_string(),
             value,
             type_: ItemType::Weapon(damage),
+            inflicts: None,
+            slot: None,
         }
     }
 
@@ -66,6 +83,8 @@ impl Item {
             description: description.to_string(),
             value,
             type_: ItemType::Armor(defense),
+            inflicts: None,
+            slot: None,
         }
     }
 
@@ -74,7 +93,9 @@ impl Item {
             name: "Gold".to_string(),
             description: "A pile of gold".to_string(),
             value,
-            type_: ItemType::Gold(1),
+            type_: ItemType::Gold(value),
+            inflicts: None,
+            slot: None,
         }
     }
 
@@ -84,6 +105,8 @@ impl Item {
             description: description.to_string(),
             value,
             type_: ItemType::Effect(effect),
+            inflicts: None,
+            slot: None,
         }
     }
 
@@ -93,9 +116,38 @@ impl Item {
             description: description.to_string(),
             value,
             type_: ItemType::Exp(value),
+            inflicts: None,
+            slot: None,
         }
     }
 
+    /// The same item putting `status` on whoever it hits, for weapons.
+    pub fn with_inflicts(mut self, status: StatusEffect) -> Item {
+        self.inflicts = Some(status);
+        self
+    }
+
+    pub fn inflicts(&self) -> Option<&StatusEffect> {
+        self.inflicts.as_ref()
+    }
+
+    /// The same item worn in `slot` rather than where its type usually
+    /// goes, e.g. an armor on the head.
+    pub fn with_slot(mut self, slot: Slot) -> Item {
+        self.slot = Some(slot);
+        self
+    }
+
+    /// The slot the item is equipped in, `None` if it cannot be.
+    pub fn slot(&self) -> Option<Slot> {
+        self.slot.or_else(|| Slot::default_for(&self.type_))
+    }
+
+    /// The slot named by the item's data, if any.
+    pub fn explicit_slot(&self) -> Option<Slot> {
+        self.slot
+    }
+
     pub fn use_item(&self) -> Option<&Effect> {
         match self.type_ {
             ItemType::Effect(ref effect) => Some(effect),
@@ -119,14 +171,35 @@ impl Item {
         &self.type_
     }
 
+    /// The damage of a weapon; 0 for any other item.
+    pub fn attack(&self) -> i32 {
+        match self.type_ {
+            ItemType::Weapon(damage) => damage,
+            _ => 0,
+        }
+    }
+
+    /// The defense of an armor; 0 for any other item. Not its `value`,
+    /// which is what it is worth.
+    pub fn defense(&self) -> i32 {
+        match self.type_ {
+            ItemType::Armor(defense) => defense,
+            _ => 0,
+        }
+    }
+
     pub fn add_value(&mut self, value: i32) {
         self.value += value;
+        if let ItemType::Gold(amount) = &mut self.type_ {
+            *amount += value;
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::map;
+    use crate::map::status::StatusKind;
 
     use super::*;
 
@@ -146,6 +219,8 @@ mod tests {
         assert_eq!(item.description(), "A sturdy shield");
         assert_eq!(item.value(), 10);
         assert_eq!(item.type_(), &ItemType::Armor(5));
+        assert_eq!(item.defense(), 5);
+        assert_eq!(item.attack(), 0);
     }
 
     #[test]
@@ -177,14 +252,11 @@ mod tests {
     #[test]
     fn test_effect_apply_heal() {
         let mut player = player::Player::new("Player");
-        let monster = Monster::new("Monster", 10, Item::new_weapon("Weapon", "", 0, 10), None, 1, 1);
-        let event = map::Event::Monster(monster);
-        let mut map = map::Map::new();
-        map.add_event(Position::new(0, 0), event);
-        map.do_event(&Position::new(0, 0), &mut player);
+        let map = map::Map::new();
+        player.take_damage(10);
 
         let effect = Effect::Heal(5);
-        effect.apply(map, &mut player, None);
+        assert!(effect.apply(&map, &mut player, None));
         assert_eq!(player.life(), 95);
     }
 
@@ -194,16 +266,37 @@ mod tests {
         let mut monster = Monster::new("Monster", 10, Item::new_weapon("Weapon", "", 0, 5), None, 0, 1);
         let effect = Effect::Damage(5);
         let map = map::Map::new();
-        effect.apply(map, &mut player, Some(&mut monster));
+        assert!(effect.apply(&map, &mut player, Some(&mut monster)));
         assert_eq!(monster.life(), 5);
+        assert!(!effect.apply(&map, &mut player, None));
+        assert_eq!(player.life(), 100);
+    }
+
+    #[test]
+    fn test_effect_apply_status() {
+        let mut player = player::Player::new("Player");
+        let mut monster = Monster::new("Monster", 10, Item::new_weapon("Weapon", "", 0, 5), None, 0, 1);
+        let poison = StatusEffect::new(StatusKind::Poison, 3, 5);
+        let haste = StatusEffect::new(StatusKind::Haste, 0, 2);
+        let map = map::Map::new();
+        assert!(Effect::Status(poison).apply(&map, &mut player, Some(&mut monster)));
+        assert!(Effect::Status(haste).apply(&map, &mut player, Some(&mut monster)));
+        assert!(!Effect::Status(poison).apply(&map, &mut player, None));
+        assert!(monster.statuses().has(StatusKind::Poison) && !monster.statuses().has(StatusKind::Haste));
+        assert!(player.statuses().has(StatusKind::Haste) && !player.statuses().has(StatusKind::Poison));
     }
 
     #[test]
     fn test_effect_apply_teleport() {
         let mut player = player::Player::new("Player");
         let effect = Effect::Teleport(Position::new(1, 1));
-        let map = map::Map::new();
-        effect.apply(map, &mut player, None);
+        let mut map = map::Map::new();
+        assert!(effect.apply(&map, &mut player, None));
         assert_eq!(player.position(), &Position::new(1, 1));
+
+        assert!(!Effect::RandomTeleport.apply(&map, &mut player, None));
+        map.add_event(Position::new(4, 2), map::Event::Empty);
+        assert!(Effect::RandomTeleport.apply(&map, &mut player, None));
+        assert_eq!(player.position(), &Position::new(4, 2));
     }
 }
\ No newline at end of file
@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+
+use super::tile::{Terrain, Tile};
+use super::{Map, Position};
+use crate::player::{self, Direction};
+
+/// What the player has learned about the labyrinth: the tiles they walked on
+/// and the positions they felt around them, which may be walls.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Knowledge {
+    visited: HashSet<Position>,
+    sensed: HashSet<Position>,
+}
+
+impl Knowledge {
+    pub fn new() -> Knowledge {
+        Knowledge::default()
+    }
+
+    /// Walk on `position`, feeling the four positions around it.
+    pub fn visit(&mut self, position: Position) {
+        self.visited.insert(position);
+        for direction in [Direction::Up, Direction::Down, Direction::Left, Direction::Right] {
+            self.sense(position.step(direction));
+        }
+    }
+
+    pub fn sense(&mut self, position: Position) {
+        self.sensed.insert(position);
+    }
+
+    pub fn is_visited(&self, position: &Position) -> bool {
+        self.visited.contains(position)
+    }
+
+    pub fn is_sensed(&self, position: &Position) -> bool {
+        self.sensed.contains(position)
+    }
+
+    pub fn is_known(&self, position: &Position) -> bool {
+        self.is_visited(position) || self.is_sensed(position)
+    }
+
+    /// Visited positions, sorted.
+    pub fn visited(&self) -> Vec<Position> {
+        let mut visited: Vec<Position> = self.visited.iter().copied().collect();
+        visited.sort();
+        visited
+    }
+
+    /// Sensed positions, sorted.
+    pub fn sensed(&self) -> Vec<Position> {
+        let mut sensed: Vec<Position> = self.sensed.iter().copied().collect();
+        sensed.sort();
+        sensed
+    }
+
+    /// Draw what the player knows of `map`: visited tiles with their event,
+    /// sensed floors as `?` and other sensed terrain (walls, doors...) with
+    /// its symbol, and nothing for the rest.
+    pub fn render(&self, map: &Map, player: &player::Player) -> String {
+        let known: Vec<&Position> = self.visited.iter().chain(self.sensed.iter()).chain([player.position()]).collect();
+        let min_x = known.iter().map(|position| position.x()).min().unwrap_or(0);
+        let max_x = known.iter().map(|position| position.x()).max().unwrap_or(0);
+        let min_y = known.iter().map(|position| position.y()).min().unwrap_or(0);
+        let max_y = known.iter().map(|position| position.y()).max().unwrap_or(0);
+
+        let mut output = String::new();
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let position = Position::new(x, y);
+                let c = if &position == player.position() {
+                    'P'
+                } else if self.is_visited(&position) {
+                    map.tile_at(&position).map_or('.', Tile::symbol)
+                } else if self.is_sensed(&position) {
+                    match map.terrain_at(&position) {
+                        Terrain::Floor => '?',
+                        terrain => terrain.symbol(),
+                    }
+                } else {
+                    ' '
+                };
+                output.push(c);
+            }
+            output.push('\n');
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Event;
+    use crate::player::Player;
+
+    #[test]
+    fn test_knowledge_visit_senses_neighbours() {
+        let mut knowledge = Knowledge::new();
+        knowledge.visit(Position::new(0, 0));
+        assert!(knowledge.is_visited(&Position::new(0, 0)));
+        assert!(knowledge.is_sensed(&Position::new(1, 0)));
+        assert!(knowledge.is_sensed(&Position::new(0, -1)));
+        assert!(!knowledge.is_known(&Position::new(1, 1)));
+        assert_eq!(knowledge.sensed().len(), 4);
+    }
+
+    #[test]
+    fn test_knowledge_render_hides_unexplored() {
+        let mut map = Map::new();
+        for x in 0..4 {
+            map.add_event(Position::new(x, 0), Event::Empty);
+        }
+        map.change_event(&Position::new(3, 0), Event::End);
+        map.set_terrain(&Position::new(1, -1), Terrain::Door);
+        let mut player = Player::new("Player");
+        let mut knowledge = Knowledge::new();
+        knowledge.visit(Position::new(0, 0));
+        knowledge.visit(Position::new(1, 0));
+        player.move_to(Position::new(1, 0));
+        assert_eq!(knowledge.render(&map, &player), " #+ \n#.P?\n ## \n");
+    }
+}
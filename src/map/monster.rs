@@ -1,12 +1,65 @@
+use std::fmt;
+
 use super::item::{Item, ItemType};
+use super::status::{StatusEffect, StatusKind, Statuses, Tick};
+use crate::combat::damage::Formula;
+
+/// What a monster does on its turn, once the player has moved.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Behaviour {
+    /// Stays where it is.
+    #[default]
+    Idle,
+    /// Steps to a random free tile next to it, or stays.
+    Wander,
+    /// Walks a square of this many steps a side, clockwise, waiting
+    /// whenever the way is blocked.
+    Patrol(u32),
+    /// Heads for the player when they are at most this many steps away and
+    /// attacks once next to them.
+    Chase(u32),
+}
+
+impl Behaviour {
+    /// The names of the behaviours, as written after `@behaviour`.
+    pub const NAMES: [&'static str; 4] = ["idle", "wander", "patrol", "chase"];
+
+    /// The behaviour called `name`, with `steps` for those that take a
+    /// number of steps. `None` for an unknown name or a missing number.
+    pub fn new(name: &str, steps: Option<u32>) -> Option<Behaviour> {
+        match (name, steps) {
+            ("idle", None) => Some(Behaviour::Idle),
+            ("wander", None) => Some(Behaviour::Wander),
+            ("patrol", Some(steps)) => Some(Behaviour::Patrol(steps)),
+            ("chase", Some(steps)) => Some(Behaviour::Chase(steps)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Behaviour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Behaviour::Idle => write!(f, "idle"),
+            Behaviour::Wander => write!(f, "wander"),
+            Behaviour::Patrol(steps) => write!(f, "patrol {}", steps),
+            Behaviour::Chase(steps) => write!(f, "chase {}", steps),
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Monster {
     name: String,
     life: i32,
     weapon: Item,
+    armor: Option<Item>,
     drop: Option<(Item, u32)>,
     rareness: u32,
+    behaviour: Behaviour,
+    statuses: Statuses,
+    /// Steps taken on the map so far, which tell a patrol the leg it is on.
+    walked: u32,
 }
 
 impl Monster {
@@ -15,11 +68,31 @@ impl Monster {
             name: name.to_string(),
             life,
             weapon,
+            armor: None,
             drop: drop.map(|item| (item, dropqte)),
             rareness,
+            behaviour: Behaviour::Idle,
+            statuses: Statuses::new(),
+            walked: 0,
         }
     }
 
+    /// The same monster wearing `armor`.
+    pub fn with_armor(mut self, armor: Item) -> Monster {
+        self.armor = Some(armor);
+        self
+    }
+
+    /// The same monster acting as `behaviour` on its turns.
+    pub fn with_behaviour(mut self, behaviour: Behaviour) -> Monster {
+        self.behaviour = behaviour;
+        self
+    }
+
+    pub fn behaviour(&self) -> Behaviour {
+        self.behaviour
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -28,17 +101,62 @@ impl Monster {
         self.life
     }
 
+    pub fn rareness(&self) -> u32 {
+        self.rareness
+    }
+
+    pub fn set_life(&mut self, life: i32) {
+        self.life = life;
+    }
+
+    pub fn walked(&self) -> u32 {
+        self.walked
+    }
+
+    pub fn set_walked(&mut self, walked: u32) {
+        self.walked = walked;
+    }
+
     pub fn take_damage(&mut self, damage: i32) {
         self.life -= damage;
     }
 
     pub fn take_damage_from(&mut self, weapon: &Item) {
-        match weapon.type_() {
-            ItemType::Weapon(damage) => self.take_damage(*damage),
-            _ => {}
+        if let ItemType::Weapon(damage) = weapon.type_() {
+            self.take_damage(Formula::default().damage(*damage, self.defense()));
         }
     }
 
+    /// The damage of the monster's weapon, plus its strength.
+    pub fn attack(&self) -> i32 {
+        self.weapon.attack() + self.statuses.power(StatusKind::Strength)
+    }
+
+    /// The defense of the monster's armor, if it wears one.
+    pub fn defense(&self) -> i32 {
+        self.armor.as_ref().map_or(0, Item::defense)
+    }
+
+    pub fn statuses(&self) -> &Statuses {
+        &self.statuses
+    }
+
+    pub fn add_status(&mut self, status: StatusEffect) {
+        self.statuses.add(status);
+    }
+
+    /// Pass one turn of the monster's statuses, poison and regeneration
+    /// changing its life.
+    pub fn tick_statuses(&mut self) -> Tick {
+        let tick = self.statuses.tick();
+        self.take_damage(-tick.life);
+        tick
+    }
+
+    pub fn armor(&self) -> Option<&Item> {
+        self.armor.as_ref()
+    }
+
     pub fn drop(&self) -> &Option<(Item, u32)> {
         &self.drop
     }
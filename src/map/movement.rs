@@ -0,0 +1,294 @@
+use rand::Rng;
+
+use super::monster::{Behaviour, Monster};
+use super::path::Teleports;
+use super::status::StatusKind;
+use super::{Event, Map, Position};
+use crate::player::Direction;
+
+/// The legs of a patrol, in order.
+const PATROL: [Direction; 4] = [Direction::Right, Direction::Down, Direction::Left, Direction::Up];
+
+impl Map {
+    /// Whether a monster can step on `position`: a walkable tile with
+    /// nothing on it.
+    pub fn is_free(&self, position: &Position) -> bool {
+        self.is_walkable(position) && self.event_at(position) == Some(&Event::Empty)
+    }
+
+    /// Move the event at `from` to `to`, leaving `from` empty. Nothing
+    /// changes, and `false` is returned, unless `to` is free.
+    pub fn move_event(&mut self, from: &Position, to: &Position) -> bool {
+        if from == to || !self.is_free(to) {
+            return false;
+        }
+        let event = match self.event_at_mut(from) {
+            Some(event) => std::mem::replace(event, Event::Empty),
+            None => return false,
+        };
+        self.change_event(to, event);
+        true
+    }
+
+    /// Where the monster at `position`, having `walked` steps so far, wants
+    /// to go, or `None` to stay.
+    fn destination(&self, position: &Position, behaviour: Behaviour, player: &Position, walked: u32, rng: &mut impl Rng) -> Option<Position> {
+        match behaviour {
+            Behaviour::Idle => None,
+            Behaviour::Wander => {
+                let free: Vec<Position> = self.neighbours(position).into_iter().filter(|next| self.is_free(next)).collect();
+                let choice = rng.gen_range(0..=free.len());
+                free.get(choice).copied()
+            },
+            Behaviour::Patrol(side) => {
+                let leg = walked / side.max(1);
+                Some(position.step(PATROL[leg as usize % PATROL.len()]))
+            },
+            Behaviour::Chase(radius) => {
+                let distance = (position.x() - player.x()).unsigned_abs() + (position.y() - player.y()).unsigned_abs();
+                if distance > radius {
+                    return None;
+                }
+                let path = self.shortest_path(position, player, Teleports::Ignore)?;
+                match path.len() - 1 {
+                    steps if steps <= radius as usize => path.get(1).copied(),
+                    _ => None,
+                }
+            },
+        }
+    }
+
+    /// Play the monsters' turn once the player moved to `player`. Monsters
+    /// act one after the other in position order, each seeing where the ones
+    /// before went, so two never end up on the same tile. They only step on
+    /// free tiles and never on the player: the positions of the monsters that
+    /// try to are returned instead, as they attack the player. A blocked
+    /// patrol waits to take the same step again. Stunned monsters stay put,
+    /// hasted ones take two steps and blind ones wander instead of hunting.
+    pub fn move_monsters(&mut self, player: &Position, rng: &mut impl Rng) -> Vec<Position> {
+        let monsters: Vec<(Position, Behaviour, u32, u32)> = self.events().into_iter()
+            .filter_map(|(position, event)| match event {
+                Event::Monster(monster) => {
+                    let statuses = monster.statuses();
+                    let behaviour = match monster.behaviour() {
+                        Behaviour::Chase(_) if statuses.has(StatusKind::Blindness) => Behaviour::Wander,
+                        behaviour => behaviour,
+                    };
+                    let steps = match (statuses.has(StatusKind::Stun), statuses.has(StatusKind::Haste)) {
+                        (true, _) => 0,
+                        (false, true) => 2,
+                        (false, false) => 1,
+                    };
+                    Some((*position, behaviour, steps, monster.walked()))
+                },
+                _ => None,
+            })
+            .collect();
+
+        let mut attackers = vec![];
+        for (mut position, behaviour, steps, mut walked) in monsters {
+            for _ in 0..steps {
+                match self.destination(&position, behaviour, player, walked, rng) {
+                    Some(destination) if destination == *player => {
+                        attackers.push(position);
+                        break;
+                    },
+                    Some(destination) if self.move_event(&position, &destination) => {
+                        position = destination;
+                        walked = walked.saturating_add(1);
+                        if let Some(Event::Monster(monster)) = self.event_at_mut(&position) {
+                            monster.set_walked(walked);
+                        }
+                    },
+                    _ => break,
+                }
+            }
+        }
+        attackers
+    }
+
+    /// Pass a turn of the statuses of every monster, taking those that
+    /// succumb off the map and returning them.
+    pub fn tick_monsters(&mut self) -> Vec<Monster> {
+        let positions: Vec<Position> = self.events().into_iter()
+            .filter(|(_, event)| matches!(event, Event::Monster(monster) if !monster.statuses().is_empty()))
+            .map(|(position, _)| *position)
+            .collect();
+
+        let mut fallen = vec![];
+        for position in positions {
+            let Some(event) = self.event_at_mut(&position) else { continue };
+            let fell = match event {
+                Event::Monster(monster) => {
+                    monster.tick_statuses();
+                    monster.life() <= 0
+                },
+                _ => false,
+            };
+            if fell {
+                if let Event::Monster(monster) = std::mem::replace(event, Event::Empty) {
+                    fallen.push(monster);
+                }
+            }
+        }
+        fallen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::map::item::Item;
+    use crate::map::status::StatusEffect;
+    use crate::map::tile::Terrain;
+
+    fn monster(behaviour: Behaviour) -> Event {
+        Event::Monster(Monster::new("rat", 3, Item::new_weapon("teeth", "", 0, 1), None, 0, 1).with_behaviour(behaviour))
+    }
+
+    /// An open room of `width` by `height` floor tiles from the origin.
+    fn room(width: i32, height: i32) -> Map {
+        let mut map = Map::new();
+        for x in 0..width {
+            for y in 0..height {
+                map.add_event(Position::new(x, y), Event::Empty);
+            }
+        }
+        map
+    }
+
+    fn monsters(map: &Map) -> Vec<Position> {
+        map.events().into_iter().filter(|(_, event)| matches!(event, Event::Monster(_))).map(|(position, _)| *position).collect()
+    }
+
+    #[test]
+    fn test_map_move_event() {
+        let mut map = room(3, 1);
+        map.change_event(&Position::new(0, 0), monster(Behaviour::Idle));
+        map.change_event(&Position::new(2, 0), Event::End);
+        assert!(map.move_event(&Position::new(0, 0), &Position::new(1, 0)));
+        assert_eq!(map.event_at(&Position::new(0, 0)), Some(&Event::Empty));
+        assert_eq!(map.event_at(&Position::new(1, 0)), Some(&monster(Behaviour::Idle)));
+        assert!(!map.move_event(&Position::new(1, 0), &Position::new(2, 0)));
+        assert!(!map.move_event(&Position::new(1, 0), &Position::new(1, 1)));
+        map.set_terrain(&Position::new(0, 0), Terrain::Wall);
+        assert!(!map.move_event(&Position::new(1, 0), &Position::new(0, 0)));
+        assert_eq!(monsters(&map), vec![Position::new(1, 0)]);
+    }
+
+    #[test]
+    fn test_map_move_monsters_chase() {
+        let mut map = room(6, 1);
+        map.change_event(&Position::new(5, 0), monster(Behaviour::Chase(4)));
+        let player = Position::new(0, 0);
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(map.move_monsters(&player, &mut rng).is_empty());
+        assert_eq!(monsters(&map), vec![Position::new(5, 0)]);
+
+        let player = Position::new(1, 0);
+        for expected in [4, 3, 2] {
+            assert!(map.move_monsters(&player, &mut rng).is_empty());
+            assert_eq!(monsters(&map), vec![Position::new(expected, 0)]);
+        }
+        assert_eq!(map.move_monsters(&player, &mut rng), vec![Position::new(2, 0)]);
+        assert_eq!(monsters(&map), vec![Position::new(2, 0)]);
+    }
+
+    #[test]
+    fn test_map_move_monsters_walls_and_collisions() {
+        // Two chasers in a corridor with a wall between them and the player:
+        // they go around it, one behind the other.
+        let mut map = room(3, 3);
+        map.set_terrain(&Position::new(1, 1), Terrain::Wall);
+        map.change_event(&Position::new(2, 1), monster(Behaviour::Chase(10)));
+        map.change_event(&Position::new(2, 2), monster(Behaviour::Chase(10)));
+        let player = Position::new(0, 1);
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..9 {
+            map.move_monsters(&player, &mut rng);
+            let positions = monsters(&map);
+            assert_eq!(positions.len(), 2);
+            assert!(positions.iter().all(|position| map.is_walkable(position) && *position != player));
+        }
+        let mut attackers = map.move_monsters(&player, &mut rng);
+        attackers.sort();
+        assert_eq!(attackers, vec![Position::new(0, 0), Position::new(0, 2)]);
+    }
+
+    #[test]
+    fn test_map_move_monsters_wander_and_patrol() {
+        let mut map = room(5, 5);
+        map.change_event(&Position::new(1, 1), monster(Behaviour::Patrol(2)));
+        let player = Position::new(4, 4);
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut path = vec![];
+        for _ in 0..8 {
+            map.move_monsters(&player, &mut rng);
+            path.push(monsters(&map)[0]);
+        }
+        assert_eq!(path, [(2, 1), (3, 1), (3, 2), (3, 3), (2, 3), (1, 3), (1, 2), (1, 1)].map(|(x, y)| Position::new(x, y)));
+
+        let mut map = room(5, 5);
+        map.change_event(&Position::new(2, 2), monster(Behaviour::Wander));
+        let mut visited = vec![];
+        for _ in 0..50 {
+            map.move_monsters(&player, &mut rng);
+            let position = monsters(&map)[0];
+            assert!(position.x() >= 0 && position.x() < 5 && position.y() >= 0 && position.y() < 5);
+            visited.push(position);
+        }
+        visited.sort();
+        visited.dedup();
+        assert!(visited.len() > 5);
+    }
+
+    #[test]
+    fn test_map_move_monsters_blocked_patrol() {
+        let mut map = room(5, 5);
+        map.change_event(&Position::new(1, 1), monster(Behaviour::Patrol(2)));
+        map.change_event(&Position::new(3, 1), Event::End);
+        let player = Position::new(4, 4);
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut path = vec![];
+        for turn in 0..10 {
+            if turn == 3 {
+                map.change_event(&Position::new(3, 1), Event::Empty);
+            }
+            map.move_monsters(&player, &mut rng);
+            path.push(monsters(&map)[0]);
+        }
+        let expected = [(2, 1), (2, 1), (2, 1), (3, 1), (3, 2), (3, 3), (2, 3), (1, 3), (1, 2), (1, 1)];
+        assert_eq!(path, expected.map(|(x, y)| Position::new(x, y)));
+        match map.event_at(&Position::new(1, 1)) {
+            Some(Event::Monster(monster)) => assert_eq!(monster.walked(), 8),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_map_move_monsters_statuses() {
+        let afflicted = |status: StatusEffect| match monster(Behaviour::Chase(10)) {
+            Event::Monster(mut monster) => {
+                monster.add_status(status);
+                Event::Monster(monster)
+            },
+            _ => unreachable!(),
+        };
+        let mut map = room(8, 3);
+        map.change_event(&Position::new(7, 0), afflicted(StatusEffect::new(StatusKind::Stun, 0, 2)));
+        map.change_event(&Position::new(7, 1), afflicted(StatusEffect::new(StatusKind::Haste, 0, 2)));
+        map.change_event(&Position::new(7, 2), afflicted(StatusEffect::new(StatusKind::Poison, 2, 5)));
+        let player = Position::new(0, 1);
+        let mut rng = StdRng::seed_from_u64(0);
+        map.move_monsters(&player, &mut rng);
+        assert_eq!(monsters(&map), vec![Position::new(5, 1), Position::new(7, 0), Position::new(7, 1)]);
+
+        assert!(map.tick_monsters().is_empty());
+        let fallen = map.tick_monsters();
+        assert_eq!(fallen.len(), 1);
+        assert_eq!(fallen[0].life(), -1);
+        assert_eq!(monsters(&map), vec![Position::new(5, 1), Position::new(7, 0)]);
+    }
+}
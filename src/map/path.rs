@@ -0,0 +1,163 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+
+use super::{Event, Map, Position};
+use crate::player::Direction;
+
+const DIRECTIONS: [Direction; 4] = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+
+/// How paths treat the tiles holding an `Event::Teleport`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Teleports {
+    /// Stepping on a teleport lands on its target, as it does for the player.
+    Follow,
+    /// Teleports are plain floor, e.g. for monsters that do not trigger them.
+    Ignore,
+}
+
+impl Map {
+    /// Every step possible from `position`: its direction and where it lands.
+    pub fn steps(&self, position: &Position, teleports: Teleports) -> Vec<(Direction, Position)> {
+        DIRECTIONS.iter().filter_map(|direction| {
+            let next = position.step(*direction);
+            if !self.is_walkable(&next) {
+                return None;
+            }
+            match (teleports, self.event_at(&next)) {
+                (Teleports::Follow, Some(Event::Teleport(target))) if self.is_walkable(target) => Some((*direction, *target)),
+                (Teleports::Follow, Some(Event::Teleport(_))) => None,
+                _ => Some((*direction, next)),
+            }
+        }).collect()
+    }
+
+    /// Breadth-first search from `from`, stopping early once `to` is reached.
+    /// Each reached position maps to the one it was reached from and the
+    /// direction of that step.
+    fn search(&self, from: &Position, to: Option<&Position>, teleports: Teleports) -> HashMap<Position, Option<(Position, Direction)>> {
+        let mut parents = HashMap::from([(*from, None)]);
+        let mut queue = VecDeque::from([*from]);
+        while let Some(position) = queue.pop_front() {
+            if Some(&position) == to {
+                break;
+            }
+            for (direction, next) in self.steps(&position, teleports) {
+                if let Entry::Vacant(entry) = parents.entry(next) {
+                    entry.insert(Some((position, direction)));
+                    queue.push_back(next);
+                }
+            }
+        }
+        parents
+    }
+
+    /// Fewest steps from `from` to every position that can be reached.
+    pub fn distance_field(&self, from: &Position, teleports: Teleports) -> HashMap<Position, u32> {
+        let mut distances = HashMap::from([(*from, 0)]);
+        let mut queue = VecDeque::from([*from]);
+        while let Some(position) = queue.pop_front() {
+            let distance = distances[&position];
+            for (_, next) in self.steps(&position, teleports) {
+                if let Entry::Vacant(entry) = distances.entry(next) {
+                    entry.insert(distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    /// The positions stood on along one of the shortest ways from `from` to
+    /// `to`, both included. Every step costs the same, so a breadth-first
+    /// search finds it; with `Teleports::Follow` two consecutive positions
+    /// may be far apart.
+    pub fn shortest_path(&self, from: &Position, to: &Position, teleports: Teleports) -> Option<Vec<Position>> {
+        let parents = self.search(from, Some(to), teleports);
+        parents.get(to)?;
+
+        let mut path = vec![*to];
+        let mut current = *to;
+        while let Some(Some((previous, _))) = parents.get(&current) {
+            path.push(*previous);
+            current = *previous;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// The direction of the first step of a shortest way from `from` to
+    /// `to`, or `None` when `to` cannot be reached or is `from` itself.
+    pub fn first_step(&self, from: &Position, to: &Position, teleports: Teleports) -> Option<Direction> {
+        let parents = self.search(from, Some(to), teleports);
+        let mut current = *to;
+        let mut direction = None;
+        while let Some(Some((previous, step))) = parents.get(&current) {
+            direction = Some(*step);
+            current = *previous;
+        }
+        direction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::tile::Terrain;
+
+    /// ```text
+    /// ..#..E
+    /// .X#..
+    /// .....
+    /// ```
+    /// with the teleport X leading to (4,0).
+    fn map() -> Map {
+        let mut map = Map::new();
+        for (y, row) in ["..#...", "..#..", "....."].iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let position = Position::new(x as i32, y as i32);
+                map.add_event(position, Event::Empty);
+                if c == '#' {
+                    map.set_terrain(&position, Terrain::Wall);
+                }
+            }
+        }
+        map.change_event(&Position::new(5, 0), Event::End);
+        map.change_event(&Position::new(1, 1), Event::Teleport(Position::new(4, 0)));
+        map
+    }
+
+    #[test]
+    fn test_map_shortest_path_ignoring_teleports() {
+        let path = map().shortest_path(&Position::new(0, 0), &Position::new(5, 0), Teleports::Ignore).unwrap();
+        assert_eq!(path.len(), 10);
+        assert_eq!(path[0], Position::new(0, 0));
+        assert_eq!(path[9], Position::new(5, 0));
+        assert!(path.windows(2).all(|pair| (pair[0].x() - pair[1].x()).abs() + (pair[0].y() - pair[1].y()).abs() == 1));
+    }
+
+    #[test]
+    fn test_map_shortest_path_through_teleport() {
+        let path = map().shortest_path(&Position::new(0, 0), &Position::new(5, 0), Teleports::Follow).unwrap();
+        assert_eq!(path, vec![Position::new(0, 0), Position::new(0, 1), Position::new(4, 0), Position::new(5, 0)]);
+        assert_eq!(map().first_step(&Position::new(0, 0), &Position::new(5, 0), Teleports::Follow), Some(Direction::Down));
+    }
+
+    #[test]
+    fn test_map_shortest_path_unreachable() {
+        let map = map();
+        assert_eq!(map.shortest_path(&Position::new(0, 0), &Position::new(2, 0), Teleports::Ignore), None);
+        assert_eq!(map.shortest_path(&Position::new(0, 0), &Position::new(0, 0), Teleports::Ignore), Some(vec![Position::new(0, 0)]));
+        assert_eq!(map.first_step(&Position::new(0, 0), &Position::new(0, 0), Teleports::Ignore), None);
+    }
+
+    #[test]
+    fn test_map_distance_field() {
+        let map = map();
+        let walking = map.distance_field(&Position::new(0, 0), Teleports::Ignore);
+        assert_eq!(walking[&Position::new(5, 0)], 9);
+        assert!(!walking.contains_key(&Position::new(2, 0)));
+        let teleporting = map.distance_field(&Position::new(0, 0), Teleports::Follow);
+        assert_eq!(teleporting[&Position::new(5, 0)], 3);
+        assert!(!teleporting.contains_key(&Position::new(1, 1)));
+    }
+}
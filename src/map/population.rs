@@ -0,0 +1,200 @@
+use rand::distributions::WeightedIndex;
+use rand::prelude::Distribution;
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use super::item::{Item, ItemType};
+use super::monster::Monster;
+use super::Event;
+
+/// How many of the empty tiles of a generated map receive a monster or a
+/// treasure, in percent.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Density {
+    pub monsters: u32,
+    pub treasures: u32,
+}
+
+impl Default for Density {
+    fn default() -> Self {
+        Density {
+            monsters: 10,
+            treasures: 5,
+        }
+    }
+}
+
+/// The monsters and treasures a generated map can be populated with. Each
+/// one is picked with a weight of `1 / rareness`, so a rareness of 2 makes
+/// it half as common as a rareness of 1, and a rareness of 0 keeps it out of
+/// generated maps.
+///
+/// Deeper floors are harder: `depth` levels down, a rareness counts as
+/// `depth` less (but at least 1), and monster life and the gold in a pile
+/// grow by a quarter for every level. Other treasures are a single item.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Population {
+    density: Density,
+    depth: u32,
+    monsters: Vec<Monster>,
+    treasures: Vec<(Item, u32)>,
+}
+
+impl Population {
+    pub fn new(density: Density) -> Population {
+        Population {
+            density,
+            ..Population::default()
+        }
+    }
+
+    pub fn density(&self) -> Density {
+        self.density
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// The same monsters and treasures, for the floor `depth` levels down.
+    pub fn at_depth(&self, depth: u32) -> Population {
+        Population {
+            depth,
+            ..self.clone()
+        }
+    }
+
+    fn rareness(&self, rareness: u32) -> u32 {
+        match rareness {
+            0 => 0,
+            rareness => rareness.saturating_sub(self.depth).max(1),
+        }
+    }
+
+    fn scale(&self, value: i32) -> i32 {
+        let scaled = value as i64 * (4 + self.depth as i64) / 4;
+        scaled.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+    }
+
+    pub fn add_monster(&mut self, monster: Monster) {
+        self.monsters.push(monster);
+    }
+
+    pub fn add_treasure(&mut self, item: Item, rareness: u32) {
+        self.treasures.push((item, rareness));
+    }
+
+    /// The event to place on an empty tile: a monster, a treasure or `None`
+    /// to leave it empty.
+    pub fn roll(&self, rng: &mut StdRng) -> Option<Event> {
+        let roll = rng.gen_range(0..100u32);
+        if roll < self.density.monsters {
+            let monsters: Vec<(&Monster, u32)> = self.monsters.iter().map(|monster| (monster, self.rareness(monster.rareness()))).collect();
+            pick(&monsters, rng).map(|monster| {
+                let mut monster = monster.clone();
+                monster.set_life(self.scale(monster.life()));
+                Event::Monster(monster)
+            })
+        } else if roll < self.density.monsters.saturating_add(self.density.treasures) {
+            let treasures: Vec<(&Item, u32)> = self.treasures.iter().map(|(item, rareness)| (item, self.rareness(*rareness))).collect();
+            pick(&treasures, rng).map(|item| match item.type_() {
+                ItemType::Gold(_) => {
+                    let amount = self.scale(item.value().max(1).saturating_mul(rng.gen_range(1..=10)));
+                    Event::Treasure(Item::new_gold(1), amount.unsigned_abs())
+                },
+                _ => Event::Treasure(item.clone(), 1),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Pick one entry, weighted by the inverse of its rareness.
+fn pick<'a, T>(entries: &[(&'a T, u32)], rng: &mut StdRng) -> Option<&'a T> {
+    let weights = entries.iter().map(|(_, rareness)| match rareness {
+        0 => 0.0,
+        rareness => 1.0 / *rareness as f64,
+    });
+    let index = WeightedIndex::new(weights).ok()?;
+    Some(entries[index.sample(rng)].0)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn slime(rareness: u32) -> Monster {
+        Monster::new("slime", 5, Item::new_weapon("goo", "", 0, 5), None, 0, rareness)
+    }
+
+    #[test]
+    fn test_population_empty() {
+        let population = Population::new(Density { monsters: 100, treasures: 0 });
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(population.roll(&mut rng), None);
+    }
+
+    #[test]
+    fn test_population_rareness_weights() {
+        let mut population = Population::new(Density { monsters: 100, treasures: 0 });
+        let mut common = slime(1);
+        common.set_life(1);
+        population.add_monster(common);
+        population.add_monster(slime(4));
+        population.add_monster(slime(0));
+
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut counts = [0; 2];
+        for _ in 0..1000 {
+            match population.roll(&mut rng) {
+                Some(Event::Monster(monster)) if monster.rareness() == 1 => counts[0] += 1,
+                Some(Event::Monster(monster)) if monster.rareness() == 4 => counts[1] += 1,
+                other => panic!("unexpected event {:?}", other),
+            }
+        }
+        assert!(counts[0] > 3 * counts[1], "{:?}", counts);
+    }
+
+    #[test]
+    fn test_population_at_depth() {
+        let mut population = Population::new(Density { monsters: 100, treasures: 0 });
+        population.add_monster(slime(3));
+        population.add_monster(slime(0));
+        let deep = population.at_depth(4);
+        assert_eq!(deep.rareness(3), 1);
+        assert_eq!(deep.rareness(0), 0);
+        assert_eq!(deep.scale(10), 20);
+
+        let mut rng = StdRng::seed_from_u64(4);
+        match deep.roll(&mut rng) {
+            Some(Event::Monster(monster)) => assert_eq!(monster.life(), 10),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_population_gold_amount() {
+        let mut population = Population::new(Density { monsters: 0, treasures: 100 });
+        population.add_treasure(Item::new_gold(1), 1);
+        let mut rng = StdRng::seed_from_u64(3);
+        match population.roll(&mut rng) {
+            Some(Event::Treasure(item, amount)) => {
+                assert_eq!(item, Item::new_gold(1));
+                assert!((1..=10).contains(&amount));
+            },
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_population_item_at_depth() {
+        let mut population = Population::new(Density { monsters: 0, treasures: 100 });
+        let sword = Item::new_weapon("Sword", "", 12, 5);
+        population.add_treasure(sword.clone(), 1);
+        let mut rng = StdRng::seed_from_u64(5);
+        assert_eq!(population.at_depth(4).roll(&mut rng), Some(Event::Treasure(sword, 1)));
+    }
+}
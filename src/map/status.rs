@@ -0,0 +1,193 @@
+use std::fmt;
+
+/// A lasting condition on the player or a monster.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum StatusKind {
+    /// Loses its power in life every turn.
+    Poison,
+    /// Gains its power in life every turn.
+    Regeneration,
+    /// Deals half damage; a blind player gets no hints and a blind monster
+    /// cannot hunt.
+    Blindness,
+    /// Strikes first in a fight; monsters move every other turn around a
+    /// hasted player, and a hasted monster moves twice.
+    Haste,
+    /// Loses its turns, in fights and on the map.
+    Stun,
+    /// Adds its power to the attack.
+    Strength,
+}
+
+impl StatusKind {
+    pub const ALL: [StatusKind; 6] = [
+        StatusKind::Poison,
+        StatusKind::Regeneration,
+        StatusKind::Blindness,
+        StatusKind::Haste,
+        StatusKind::Stun,
+        StatusKind::Strength,
+    ];
+
+    /// The name used in `.blind` files, e.g. `@effect: poison 3 for 5`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusKind::Poison => "poison",
+            StatusKind::Regeneration => "regeneration",
+            StatusKind::Blindness => "blindness",
+            StatusKind::Haste => "haste",
+            StatusKind::Stun => "stun",
+            StatusKind::Strength => "strength",
+        }
+    }
+
+    /// How someone with the status is described, as in "You are no longer
+    /// poisoned."
+    pub fn adjective(&self) -> &'static str {
+        match self {
+            StatusKind::Poison => "poisoned",
+            StatusKind::Regeneration => "regenerating",
+            StatusKind::Blindness => "blind",
+            StatusKind::Haste => "hasted",
+            StatusKind::Stun => "stunned",
+            StatusKind::Strength => "strengthened",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<StatusKind> {
+        StatusKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    /// Whether the status hinders whoever has it, so that items put it on
+    /// the monster being fought rather than on the player.
+    pub fn is_harmful(&self) -> bool {
+        matches!(self, StatusKind::Poison | StatusKind::Blindness | StatusKind::Stun)
+    }
+}
+
+/// A status of some power lasting for a number of turns.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub power: i32,
+    pub turns: u32,
+}
+
+impl StatusEffect {
+    pub fn new(kind: StatusKind, power: i32, turns: u32) -> StatusEffect {
+        StatusEffect { kind, power, turns }
+    }
+}
+
+impl fmt::Display for StatusEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} for {}", self.kind.name(), self.power, self.turns)
+    }
+}
+
+/// What passing a turn did: the life gained, negative when lost, and the
+/// statuses that wore off.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Tick {
+    pub life: i32,
+    pub expired: Vec<StatusKind>,
+}
+
+/// The statuses on the player or a monster, at most one of each kind.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Statuses {
+    effects: Vec<StatusEffect>,
+}
+
+impl Statuses {
+    pub fn new() -> Statuses {
+        Statuses::default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &StatusEffect> {
+        self.effects.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    /// Put on `status`, replacing one of the same kind. A status lasting no
+    /// turn is ignored.
+    pub fn add(&mut self, status: StatusEffect) {
+        if status.turns == 0 {
+            return;
+        }
+        match self.effects.iter_mut().find(|effect| effect.kind == status.kind) {
+            Some(effect) => *effect = status,
+            None => self.effects.push(status),
+        }
+    }
+
+    pub fn get(&self, kind: StatusKind) -> Option<&StatusEffect> {
+        self.effects.iter().find(|effect| effect.kind == kind)
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.get(kind).is_some()
+    }
+
+    /// The power of the status of this kind, 0 without one.
+    pub fn power(&self, kind: StatusKind) -> i32 {
+        self.get(kind).map_or(0, |effect| effect.power)
+    }
+
+    /// Pass one turn: poison and regeneration take effect, then every
+    /// status has one turn less to last.
+    pub fn tick(&mut self) -> Tick {
+        let life = self.power(StatusKind::Regeneration) - self.power(StatusKind::Poison);
+        let mut expired = vec![];
+        self.effects.retain_mut(|effect| {
+            effect.turns -= 1;
+            if effect.turns == 0 {
+                expired.push(effect.kind);
+            }
+            effect.turns > 0
+        });
+        Tick { life, expired }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statuses_tick() {
+        let mut statuses = Statuses::new();
+        statuses.add(StatusEffect::new(StatusKind::Poison, 3, 2));
+        statuses.add(StatusEffect::new(StatusKind::Regeneration, 1, 3));
+        statuses.add(StatusEffect::new(StatusKind::Stun, 0, 0));
+        assert!(!statuses.has(StatusKind::Stun));
+        assert_eq!(statuses.tick(), Tick { life: -2, expired: vec![] });
+        assert_eq!(statuses.tick(), Tick { life: -2, expired: vec![StatusKind::Poison] });
+        assert_eq!(statuses.tick(), Tick { life: 1, expired: vec![StatusKind::Regeneration] });
+        assert!(statuses.is_empty());
+        assert_eq!(statuses.tick(), Tick::default());
+    }
+
+    #[test]
+    fn test_statuses_replace() {
+        let mut statuses = Statuses::new();
+        statuses.add(StatusEffect::new(StatusKind::Strength, 5, 2));
+        statuses.add(StatusEffect::new(StatusKind::Strength, 2, 9));
+        assert_eq!(statuses.iter().collect::<Vec<_>>(), vec![&StatusEffect::new(StatusKind::Strength, 2, 9)]);
+        assert_eq!(statuses.power(StatusKind::Strength), 2);
+        assert_eq!(statuses.power(StatusKind::Haste), 0);
+    }
+
+    #[test]
+    fn test_status_names() {
+        for kind in StatusKind::ALL {
+            assert_eq!(StatusKind::from_name(kind.name()), Some(kind));
+        }
+        assert_eq!(StatusKind::from_name("sleep"), None);
+        assert_eq!(StatusEffect::new(StatusKind::Poison, 3, 5).to_string(), "poison 3 for 5");
+        assert!(StatusKind::Stun.is_harmful() && !StatusKind::Haste.is_harmful());
+    }
+}
@@ -0,0 +1,86 @@
+use super::Event;
+
+/// What a tile is made of, deciding whether the player can stand on it.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord, Default)]
+pub enum Terrain {
+    #[default]
+    Floor,
+    Wall,
+    Door,
+    /// Shallow water the player can wade through.
+    Water,
+    /// A chasm the player cannot cross.
+    Pit,
+}
+
+impl Terrain {
+    pub fn is_walkable(&self) -> bool {
+        matches!(self, Terrain::Floor | Terrain::Door | Terrain::Water)
+    }
+
+    /// How the terrain is drawn when the tile holds no event.
+    pub fn symbol(&self) -> char {
+        match self {
+            Terrain::Floor => '.',
+            Terrain::Wall => '#',
+            Terrain::Door => '+',
+            Terrain::Water => '~',
+            Terrain::Pit => '^',
+        }
+    }
+}
+
+/// One position of the map: its terrain and what happens when the player
+/// steps on it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Tile {
+    pub terrain: Terrain,
+    pub event: Event,
+}
+
+impl Tile {
+    pub fn new(terrain: Terrain, event: Event) -> Tile {
+        Tile { terrain, event }
+    }
+
+    pub fn floor(event: Event) -> Tile {
+        Tile::new(Terrain::Floor, event)
+    }
+
+    pub fn is_walkable(&self) -> bool {
+        self.terrain.is_walkable()
+    }
+
+    /// The event letter, or the terrain when there is nothing on the tile.
+    pub fn symbol(&self) -> char {
+        match &self.event {
+            Event::Empty => self.terrain.symbol(),
+            Event::Monster(_) => 'M',
+            Event::Treasure(_, _) => 'T',
+            Event::Teleport(_) => 'X',
+            Event::End => 'E',
+            Event::StairsDown => '>',
+            Event::StairsUp => '<',
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terrain_walkable() {
+        let walkable: Vec<Terrain> = [Terrain::Floor, Terrain::Wall, Terrain::Door, Terrain::Water, Terrain::Pit]
+            .into_iter()
+            .filter(Terrain::is_walkable)
+            .collect();
+        assert_eq!(walkable, vec![Terrain::Floor, Terrain::Door, Terrain::Water]);
+    }
+
+    #[test]
+    fn test_tile_symbol() {
+        assert_eq!(Tile::new(Terrain::Door, Event::Empty).symbol(), '+');
+        assert_eq!(Tile::new(Terrain::Water, Event::End).symbol(), 'E');
+    }
+}
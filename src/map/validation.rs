@@ -0,0 +1,175 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use super::path::Teleports;
+use super::{Event, Map, Position};
+
+/// A reachability problem found by `Map::validate`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Diagnostic {
+    MissingStart(Position),
+    MissingExit,
+    /// The exit cannot be reached from the start.
+    UnreachableExit(Position),
+    /// Tiles that can never be reached from the start.
+    Unreachable(Vec<Position>),
+    /// A teleport sending the player into a wall or another tile they
+    /// cannot stand on.
+    TeleportIntoWall { teleport: Position, target: Position },
+    /// Tiles the player can reach but never leave towards the exit, e.g.
+    /// behind a teleport.
+    Trapped(Vec<Position>),
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |positions: &[Position]| {
+            let positions: Vec<String> = positions.iter().map(|position| format!("{} {}", position.x(), position.y())).collect();
+            positions.join(", ")
+        };
+        match self {
+            Diagnostic::MissingStart(start) => write!(f, "the start {} {} is not a tile", start.x(), start.y()),
+            Diagnostic::MissingExit => write!(f, "the map has no exit"),
+            Diagnostic::UnreachableExit(exit) => write!(f, "the exit {} {} cannot be reached", exit.x(), exit.y()),
+            Diagnostic::Unreachable(positions) => write!(f, "unreachable tiles: {}", list(positions)),
+            Diagnostic::TeleportIntoWall { teleport, target } => {
+                write!(f, "the teleport at {} {} leads into the wall at {} {}", teleport.x(), teleport.y(), target.x(), target.y())
+            },
+            Diagnostic::Trapped(positions) => write!(f, "tiles with no way to the exit: {}", list(positions)),
+        }
+    }
+}
+
+impl Map {
+    /// Check that the exit (or the stairs down) can be reached from `start`, that every tile can
+    /// be reached and that no teleport leads into a wall or somewhere the
+    /// exit cannot be reached from. An empty list means the map is sound.
+    pub fn validate(&self, start: &Position) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        if !self.is_walkable(start) {
+            diagnostics.push(Diagnostic::MissingStart(*start));
+        }
+
+        for (position, event) in self.events() {
+            if let Event::Teleport(target) = event {
+                if !self.is_walkable(target) {
+                    diagnostics.push(Diagnostic::TeleportIntoWall { teleport: *position, target: *target });
+                }
+            }
+        }
+
+        let reachable = self.distance_field(start, Teleports::Follow);
+        // Teleport tiles are never stood on, so they count as reached when
+        // the player can step onto them.
+        let entered: HashSet<Position> = reachable.keys()
+            .flat_map(|position| self.walkable_neighbours(position))
+            .filter(|position| matches!(self.event_at(position), Some(Event::Teleport(_))))
+            .collect();
+        let unreachable: Vec<Position> = self.tiles().into_iter()
+            .filter(|(_, tile)| tile.is_walkable())
+            .map(|(position, _)| *position)
+            .filter(|position| !reachable.contains_key(position) && !entered.contains(position))
+            .collect();
+        if !unreachable.is_empty() {
+            diagnostics.push(Diagnostic::Unreachable(unreachable));
+        }
+
+        let exits: Vec<Position> = self.tiles().into_iter()
+            .filter(|(_, tile)| tile.is_walkable() && matches!(tile.event, Event::End | Event::StairsDown))
+            .map(|(position, _)| *position)
+            .collect();
+        if exits.is_empty() {
+            diagnostics.push(Diagnostic::MissingExit);
+            return diagnostics;
+        }
+        for exit in &exits {
+            if !reachable.contains_key(exit) {
+                diagnostics.push(Diagnostic::UnreachableExit(*exit));
+            }
+        }
+
+        // Walk the moves backwards from the exits to find every tile the
+        // exit can be reached from.
+        let mut sources: HashMap<Position, Vec<Position>> = HashMap::new();
+        for (position, tile) in self.tiles() {
+            if !tile.is_walkable() {
+                continue;
+            }
+            for (_, next) in self.steps(position, Teleports::Follow) {
+                sources.entry(next).or_default().push(*position);
+            }
+        }
+        let mut leading: HashSet<Position> = exits.iter().copied().collect();
+        let mut stack = exits.clone();
+        while let Some(position) = stack.pop() {
+            for source in sources.get(&position).into_iter().flatten() {
+                if leading.insert(*source) {
+                    stack.push(*source);
+                }
+            }
+        }
+        let mut trapped: Vec<Position> = reachable.keys()
+            .filter(|position| !leading.contains(position))
+            .copied()
+            .collect();
+        trapped.sort();
+        if !trapped.is_empty() && exits.iter().any(|exit| reachable.contains_key(exit)) {
+            diagnostics.push(Diagnostic::Trapped(trapped));
+        }
+
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A corridor from (0,0) to the exit at (4,0).
+    fn corridor() -> Map {
+        let mut map = Map::new();
+        for x in 0..4 {
+            map.add_event(Position::new(x, 0), Event::Empty);
+        }
+        map.add_event(Position::new(4, 0), Event::End);
+        map
+    }
+
+    #[test]
+    fn test_map_validate_sound() {
+        assert_eq!(corridor().validate(&Position::new(0, 0)), vec![]);
+    }
+
+    #[test]
+    fn test_map_validate_unreachable() {
+        let mut map = corridor();
+        map.add_event(Position::new(0, 5), Event::Empty);
+        map.remove_event(&Position::new(2, 0));
+        assert_eq!(map.validate(&Position::new(0, 0)), vec![
+            Diagnostic::Unreachable(vec![Position::new(0, 5), Position::new(3, 0), Position::new(4, 0)]),
+            Diagnostic::UnreachableExit(Position::new(4, 0)),
+        ]);
+    }
+
+    #[test]
+    fn test_map_validate_teleports() {
+        let mut map = corridor();
+        // A pocket only reachable through the teleport at (1,0).
+        map.add_event(Position::new(0, 5), Event::Empty);
+        map.change_event(&Position::new(1, 0), Event::Teleport(Position::new(0, 5)));
+        map.add_event(Position::new(9, 9), Event::Teleport(Position::new(9, 8)));
+        let diagnostics = map.validate(&Position::new(0, 0));
+        assert_eq!(diagnostics, vec![
+            Diagnostic::TeleportIntoWall { teleport: Position::new(9, 9), target: Position::new(9, 8) },
+            Diagnostic::Unreachable(vec![Position::new(2, 0), Position::new(3, 0), Position::new(4, 0), Position::new(9, 9)]),
+            Diagnostic::UnreachableExit(Position::new(4, 0)),
+        ]);
+
+        // The exit is reachable, but stepping south from the start sends the
+        // player to (5,5) with no way out.
+        let mut map = corridor();
+        map.add_event(Position::new(0, 1), Event::Teleport(Position::new(5, 5)));
+        map.add_event(Position::new(5, 5), Event::Empty);
+        assert_eq!(map.validate(&Position::new(0, 0)), vec![Diagnostic::Trapped(vec![Position::new(5, 5)])]);
+    }
+}
@@ -1,5 +1,14 @@
-use crate::map::{item::{Item, ItemType}, Position};
+pub mod equipment;
+pub mod inventory;
 
+use std::fmt;
+
+use crate::combat::damage::Formula;
+use crate::map::{item::{Effect, Item, ItemType}, monster::Monster, status::{StatusEffect, StatusKind, Statuses, Tick}, Map, Position};
+use equipment::{EquipError, Equipment, Slot};
+use inventory::{Inventory, InventoryFull, Stack};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Direction {
     Up,
     Down,
@@ -7,48 +16,75 @@ pub enum Direction {
     Right,
 }
 
+/// Why the player could not use an item. The item stays in the inventory.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum UseError {
+    /// The inventory has no item at this index.
+    NoItem(usize),
+    /// The item has no effect, like a weapon or gold.
+    NotUsable(String),
+    /// The effect had nothing to act on: no monster to hurt, or nowhere to
+    /// teleport to.
+    NoTarget(String),
+}
+
+impl fmt::Display for UseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UseError::NoItem(index) => write!(f, "You have no item {}.", index + 1),
+            UseError::NotUsable(item) => write!(f, "The {} cannot be used.", item),
+            UseError::NoTarget(item) => write!(f, "There is nothing for the {} to act on.", item),
+        }
+    }
+}
+
+impl std::error::Error for UseError {}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct Player {
     name: String,
     life: i32,
     max_life: i32,
     position: Position,
-    weapon: Option<Item>,
-    armor: Option<Item>,
-    inventory: Vec<Item>,
+    equipment: Equipment,
+    inventory: Inventory,
     experience: i32,
     level: i32,
     next_level: i32,
+    statuses: Statuses,
 }
 
 impl Player {
     pub fn new(name: &str) -> Player {
+        let mut equipment = Equipment::new();
+        equipment.equip(Item::new_weapon("Stick", "A stick", 0, 1)).expect("a weapon goes in the main hand");
         Player {
             name: name.to_string(),
             life: 100,
             max_life: 100,
             position: Position::new(0, 0),
-            weapon: Some(Item::new_weapon("Stick", "A stick", 0, 1)),
-            armor: None,
-            inventory: Vec::new(),
+            equipment,
+            inventory: Inventory::new(),
             experience: 0,
             level: 1,
             next_level: 100,
+            statuses: Statuses::new(),
         }
     }
 
-    pub fn new_all(name: &str, life: i32, max_life: i32, position: Position, weapon: Option<Item>, armor: Option<Item>, inventory: Vec<Item>, experience: i32, level: i32, next_level: i32) -> Player {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_all(name: &str, life: i32, max_life: i32, position: Position, equipment: Equipment, inventory: Inventory, experience: i32, level: i32, next_level: i32) -> Player {
         Player {
             name: name.to_string(),
             life,
             max_life,
             position,
-            weapon,
-            armor,
+            equipment,
             inventory,
             experience,
             level,
             next_level,
+            statuses: Statuses::new(),
         }
     }
 
@@ -60,28 +96,60 @@ impl Player {
         self.life
     }
 
+    pub fn max_life(&self) -> i32 {
+        self.max_life
+    }
+
     pub fn position(&self) -> &Position {
         &self.position
     }
 
-    pub fn inventory(&self) -> &Vec<Item> {
+    pub fn inventory(&self) -> &Inventory {
         &self.inventory
     }
 
-    pub fn weapon(&self) -> Option<&Item> {
-        self.weapon.as_ref()
+    pub fn equipment(&self) -> &Equipment {
+        &self.equipment
     }
 
-    pub fn equip_weapon(&mut self, weapon: Item) {
-        self.weapon = Some(weapon);
+    /// The weapon in the main hand.
+    pub fn weapon(&self) -> Option<&Item> {
+        self.equipment.get(Slot::MainHand)
     }
 
+    /// The armor on the body.
     pub fn armor(&self) -> Option<&Item> {
-        self.armor.as_ref()
+        self.equipment.get(Slot::Body)
+    }
+
+    /// Put `item` in its slot, returning what was there before. Refuses
+    /// items that cannot be equipped or do not fit their slot.
+    pub fn equip(&mut self, item: Item) -> Result<Option<Item>, EquipError> {
+        self.equipment.equip(item)
     }
 
-    pub fn equip_armor(&mut self, armor: Item) {
-        self.armor = Some(armor);
+    /// Equip the item in slot `index` of the inventory, putting back in its
+    /// place whatever it replaces. Returns the slot it went to.
+    pub fn equip_item(&mut self, index: usize) -> Result<Slot, EquipError> {
+        let item = self.inventory.get(index).map(Stack::item).ok_or(EquipError::NoItem(index))?;
+        let slot = Equipment::slot_for(item)?;
+        let item = self.inventory.remove(index).unwrap();
+        if let Some(previous) = self.equipment.equip(item).expect("the slot was checked") {
+            // Equipment never stacks, so taking the item out freed a slot.
+            self.inventory.add(previous, 1).expect("the equipped item freed a slot");
+        }
+        Ok(slot)
+    }
+
+    /// Take off what is in `slot` and put it in the inventory, if it fits.
+    pub fn unequip(&mut self, slot: Slot) -> Result<Item, EquipError> {
+        let item = self.equipment.unequip(slot).ok_or(EquipError::EmptySlot(slot))?;
+        if self.inventory.add(item.clone(), 1).is_err() {
+            let name = item.name().to_string();
+            self.equipment.equip(item).expect("the item came from this slot");
+            return Err(EquipError::BagFull(name));
+        }
+        Ok(item)
     }
 
     pub fn move_to(&mut self, position: Position) {
@@ -89,27 +157,50 @@ impl Player {
     }
 
     pub fn move_to_direction(&mut self, direction: Direction) {
-        match direction {
-            Direction::Up => self.position.add_y(-1),
-            Direction::Down => self.position.add_y(1),
-            Direction::Left => self.position.add_x(-1),
-            Direction::Right => self.position.add_x(1),
-        }
+        self.position = self.position.step(direction);
+    }
+
+    /// The damage of the equipped weapons, plus the player's strength.
+    pub fn attack(&self) -> i32 {
+        self.equipment.attack() + self.statuses.power(StatusKind::Strength)
+    }
+
+    /// The defense of the equipped armor.
+    pub fn defense(&self) -> i32 {
+        self.equipment.defense()
     }
 
+    /// Lose `damage` life as is; negative damage heals up to the maximum.
+    /// Armor is accounted for by the damage `Formula`, not here.
     pub fn take_damage(&mut self, damage: i32) -> bool {
-        self.life -= damage - self.armor.as_ref().map_or(0, |armor| armor.value());
+        self.life -= damage;
         self.life = self.life.min(self.max_life);
         self.life <= 0
     }
 
+    pub fn statuses(&self) -> &Statuses {
+        &self.statuses
+    }
+
+    pub fn add_status(&mut self, status: StatusEffect) {
+        self.statuses.add(status);
+    }
+
+    /// Pass one turn of the player's statuses, poison and regeneration
+    /// changing their life.
+    pub fn tick_statuses(&mut self) -> Tick {
+        let tick = self.statuses.tick();
+        self.take_damage(-tick.life);
+        tick
+    }
+
     pub fn is_dead(&self) -> bool {
         self.life <= 0
     }
 
     pub fn take_damage_from(&mut self, weapon: &Item) -> bool {
         match weapon.type_() {
-            ItemType::Weapon(damage) => self.take_damage(*damage),
+            ItemType::Weapon(damage) => self.take_damage(Formula::default().damage(*damage, self.defense())),
             _ => false,
         }
     }
@@ -133,42 +224,47 @@ impl Player {
         self.life = self.max_life;
     }
 
-    pub fn get_gold(&self) -> Option<&Item> {
-        self.inventory.iter().find(|item| {
-            if let ItemType::Gold(_) = item.type_() {
-                true
-            } else {
-                false
-            }
-        })
+    /// The coins in the inventory.
+    pub fn gold(&self) -> u32 {
+        self.inventory.count(Item::new_gold(0).name())
+    }
+
+    /// Take one unit of the item in slot `index` out of the inventory.
+    pub fn remove_item(&mut self, index: usize) -> Option<Item> {
+        self.inventory.remove(index)
+    }
+
+    /// Use one unit of the item in slot `index` on `monster`, if fighting
+    /// one, taking it out of the inventory once its effect took hold.
+    pub fn use_item(&mut self, index: usize, map: &mut Map, monster: Option<&mut Monster>) -> Result<Item, UseError> {
+        let item = self.inventory.get(index).map(Stack::item).ok_or(UseError::NoItem(index))?;
+        let effect = match item.use_item() {
+            Some(Effect::None) | None => return Err(UseError::NotUsable(item.name().to_string())),
+            Some(effect) => effect.clone(),
+        };
+        let name = item.name().to_string();
+        if !effect.apply(map, self, monster) {
+            return Err(UseError::NoTarget(name));
+        }
+        Ok(self.inventory.remove(index).unwrap())
     }
 
-    pub fn get_gold_mut(&mut self) -> Option<&mut Item> {
-        self.inventory.iter_mut().find(|item| {
-            if let ItemType::Gold(_) = item.type_() {
-                true
-            } else {
-                false
-            }
-        })
+    pub fn add_item(&mut self, item: Item) -> Result<(), InventoryFull> {
+        self.add_items(item, 1)
     }
 
-    pub fn add_item(&mut self, item: Item) {
+    /// Take `count` units of `item`: experience is gained at once, anything
+    /// else goes to the inventory if it fits.
+    pub fn add_items(&mut self, item: Item, count: u32) -> Result<(), InventoryFull> {
         match item.type_() {
             ItemType::Exp(exp) => {
-                self.experience += exp;
+                self.experience += exp * count as i32;
                 if self.experience >= self.next_level {
                     self.level_up();
                 }
+                Ok(())
             },
-            ItemType::Gold(_) => {
-                if let Some(gold) = self.get_gold_mut() {
-                    gold.add_value(item.value());
-                } else {
-                    self.inventory.push(item);
-                }
-            },
-            _ => self.inventory.push(item),
+            _ => self.inventory.add(item, count),
         }
     }
 }
@@ -215,30 +311,109 @@ mod tests {
     #[test]
     fn test_player_take_damage_with_armot() {
         let mut player = Player::new("Player 1");
-        player.equip_armor(Item::new_armor("Armor", "An armor", 5, 5));
-        player.take_damage(10);
+        player.equip(Item::new_armor("Armor", "An armor", 50, 5)).unwrap();
+        assert_eq!(player.defense(), 5);
+        player.take_damage_from(&Item::new_weapon("Sword", "A sword", 10, 10));
         assert_eq!(player.life(), 95);
+        player.take_damage_from(&Item::new_weapon("Pin", "A pin", 10, 2));
+        assert_eq!(player.life(), 94);
+        player.take_damage(10);
+        assert_eq!(player.life(), 84);
+    }
+
+    #[test]
+    fn test_player_statuses() {
+        let mut player = Player::new("Player 1");
+        player.add_status(StatusEffect::new(StatusKind::Strength, 4, 1));
+        player.add_status(StatusEffect::new(StatusKind::Regeneration, 5, 3));
+        assert_eq!(player.attack(), 5);
+        let tick = player.tick_statuses();
+        assert_eq!(tick.expired, vec![StatusKind::Strength]);
+        assert_eq!(player.life(), 100);
+        assert_eq!(player.attack(), 1);
+        player.take_damage(20);
+        player.add_status(StatusEffect::new(StatusKind::Poison, 2, 1));
+        player.tick_statuses();
+        assert_eq!(player.life(), 83);
+    }
+
+    #[test]
+    fn test_player_use_item() {
+        let mut player = Player::new("Player 1");
+        let mut map = Map::new();
+        let mut monster = Monster::new("rat", 10, Item::new_weapon("teeth", "", 0, 1), None, 0, 1);
+        player.take_damage(30);
+        player.add_item(Item::new_effect("heal", "", 0, Effect::Heal(20))).unwrap();
+        player.add_item(Item::new_effect("bomb", "", 0, Effect::Damage(4))).unwrap();
+        player.add_item(Item::new_weapon("Sword", "", 0, 5)).unwrap();
+
+        assert_eq!(player.use_item(3, &mut map, None), Err(UseError::NoItem(3)));
+        assert_eq!(player.use_item(2, &mut map, None), Err(UseError::NotUsable("Sword".to_string())));
+        assert_eq!(player.use_item(1, &mut map, None), Err(UseError::NoTarget("bomb".to_string())));
+        assert_eq!(player.inventory().len(), 3);
+        assert_eq!(player.use_item(1, &mut map, Some(&mut monster)).map(|item| item.name().to_string()), Ok("bomb".to_string()));
+        assert_eq!(monster.life(), 6);
+        assert!(player.use_item(0, &mut map, None).is_ok());
+        assert_eq!(player.life(), 90);
+        assert_eq!(player.inventory().len(), 1);
+        assert_eq!(UseError::NoTarget("bomb".to_string()).to_string(), "There is nothing for the bomb to act on.");
+    }
+
+    #[test]
+    fn test_player_equip_item() {
+        let mut player = Player::new("Player 1");
+        player.add_item(Item::new_weapon("Sword", "", 0, 5)).unwrap();
+        player.add_item(Item::new_armor("Helmet", "", 0, 3).with_slot(Slot::Head)).unwrap();
+        player.add_item(Item::new_gold(4)).unwrap();
+
+        assert_eq!(player.equip_item(0), Ok(Slot::MainHand));
+        assert_eq!(player.attack(), 5);
+        assert_eq!(player.inventory().get(2).map(|stack| stack.item().name()), Some("Stick"));
+        assert_eq!(player.equip_item(0), Ok(Slot::Head));
+        assert_eq!(player.defense(), 3);
+        assert_eq!(player.equip_item(0), Err(EquipError::NotEquipment("Gold".to_string())));
+        assert_eq!(player.equip_item(5), Err(EquipError::NoItem(5)));
+        assert_eq!(player.inventory().len(), 2);
+
+        assert_eq!(player.unequip(Slot::Head).map(|item| item.name().to_string()), Ok("Helmet".to_string()));
+        assert_eq!(player.defense(), 0);
+        assert_eq!(player.unequip(Slot::Head), Err(EquipError::EmptySlot(Slot::Head)));
+    }
+
+    #[test]
+    fn test_player_equip_refuses() {
+        let mut player = Player::new_all("Player 1", 100, 100, Position::new(0, 0), Equipment::new(), Inventory::with_capacity(1), 0, 1, 100);
+        player.add_item(Item::new_armor("Mail", "", 0, 4).with_slot(Slot::MainHand)).unwrap();
+        let error = EquipError::WrongSlot { item: "Mail".to_string(), slot: Slot::MainHand };
+        assert_eq!(player.equip_item(0), Err(error));
+        assert_eq!(player.inventory().count("Mail"), 1);
+
+        player.equip(Item::new_weapon("Sword", "", 0, 5)).unwrap();
+        assert_eq!(player.unequip(Slot::MainHand), Err(EquipError::BagFull("Sword".to_string())));
+        assert_eq!(player.attack(), 5);
     }
 
     #[test]
     fn test_player_level_up() {
         let mut player = Player::new("Player 1");
-        player.add_item(Item::new_exp("Exp", "Experience", 100));
+        player.add_item(Item::new_exp("Exp", "Experience", 100)).unwrap();
         assert_eq!(player.level(), 2);
     }
 
     #[test]
     fn test_player_add_item_gold() {
         let mut player = Player::new("Player 1");
-        player.add_item(Item::new_gold(10));
-        player.add_item(Item::new_gold(20));
-        assert_eq!(player.get_gold().unwrap().value(), 30);
+        player.add_item(Item::new_gold(10)).unwrap();
+        player.add_item(Item::new_gold(20)).unwrap();
+        assert_eq!(player.gold(), 30);
+        assert_eq!(player.inventory().len(), 1);
     }
 
     #[test]
     fn test_player_add_item_exp() {
         let mut player = Player::new("Player 1");
-        player.add_item(Item::new_exp("Exp", "Experience", 100));
+        player.add_items(Item::new_exp("Exp", "Experience", 50), 2).unwrap();
         assert_eq!(player.experience(), 100);
+        assert!(player.inventory().is_empty());
     }
 }
\ No newline at end of file
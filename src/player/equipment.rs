@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::map::item::{Item, ItemType};
+
+/// Where the player wears or holds an item.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum Slot {
+    MainHand,
+    OffHand,
+    Head,
+    Body,
+    Ring,
+}
+
+impl Slot {
+    pub const ALL: [Slot; 5] = [Slot::MainHand, Slot::OffHand, Slot::Head, Slot::Body, Slot::Ring];
+
+    /// The name used in `.blind` files and commands, e.g. `@slot: off-hand`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Slot::MainHand => "main-hand",
+            Slot::OffHand => "off-hand",
+            Slot::Head => "head",
+            Slot::Body => "body",
+            Slot::Ring => "ring",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Slot> {
+        Slot::ALL.into_iter().find(|slot| slot.name() == name)
+    }
+
+    /// The slot of an item whose data names none: the main hand for a
+    /// weapon, the body for an armor. Other items cannot be equipped.
+    pub fn default_for(type_: &ItemType) -> Option<Slot> {
+        match type_ {
+            ItemType::Weapon(_) => Some(Slot::MainHand),
+            ItemType::Armor(_) => Some(Slot::Body),
+            _ => None,
+        }
+    }
+
+    /// Whether items of this type may go in the slot: weapons are held in
+    /// either hand, armor goes anywhere but the main hand.
+    pub fn accepts(&self, type_: &ItemType) -> bool {
+        match type_ {
+            ItemType::Weapon(_) => matches!(self, Slot::MainHand | Slot::OffHand),
+            ItemType::Armor(_) => *self != Slot::MainHand,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name().replace('-', " "))
+    }
+}
+
+/// Why an item could not be equipped or taken off. Nothing moved.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum EquipError {
+    /// The inventory has no item at this index.
+    NoItem(usize),
+    /// The item is neither a weapon nor an armor, like a potion or gold.
+    NotEquipment(String),
+    /// The item's slot does not take its type, like an armor in the main
+    /// hand.
+    WrongSlot { item: String, slot: Slot },
+    /// Nothing is equipped in the slot.
+    EmptySlot(Slot),
+    /// The inventory has no room for the item taken off.
+    BagFull(String),
+}
+
+impl fmt::Display for EquipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EquipError::NoItem(index) => write!(f, "You have no item {}.", index + 1),
+            EquipError::NotEquipment(item) => write!(f, "The {} cannot be equipped.", item),
+            EquipError::WrongSlot { item, slot } => write!(f, "The {} does not fit in the {} slot.", item, slot),
+            EquipError::EmptySlot(slot) => write!(f, "You have nothing in the {} slot.", slot),
+            EquipError::BagFull(item) => write!(f, "Your bag is too full to take off the {}.", item),
+        }
+    }
+}
+
+impl std::error::Error for EquipError {}
+
+/// What the player wears and holds, at most one item per slot. Every
+/// weapon equipped adds to the attack and every armor to the defense.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Equipment {
+    slots: BTreeMap<Slot, Item>,
+}
+
+impl Equipment {
+    pub fn new() -> Equipment {
+        Equipment::default()
+    }
+
+    pub fn get(&self, slot: Slot) -> Option<&Item> {
+        self.slots.get(&slot)
+    }
+
+    /// The equipped items, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (Slot, &Item)> {
+        self.slots.iter().map(|(slot, item)| (*slot, item))
+    }
+
+    /// The slot `item` would be equipped in, or why it cannot be.
+    pub fn slot_for(item: &Item) -> Result<Slot, EquipError> {
+        let slot = item.slot().ok_or_else(|| EquipError::NotEquipment(item.name().to_string()))?;
+        if !slot.accepts(item.type_()) {
+            return Err(EquipError::WrongSlot { item: item.name().to_string(), slot });
+        }
+        Ok(slot)
+    }
+
+    /// Put `item` in its slot, returning what was there before.
+    pub fn equip(&mut self, item: Item) -> Result<Option<Item>, EquipError> {
+        let slot = Equipment::slot_for(&item)?;
+        Ok(self.slots.insert(slot, item))
+    }
+
+    pub fn unequip(&mut self, slot: Slot) -> Option<Item> {
+        self.slots.remove(&slot)
+    }
+
+    /// The damage of every weapon equipped.
+    pub fn attack(&self) -> i32 {
+        self.slots.values().map(Item::attack).sum()
+    }
+
+    /// The defense of every armor equipped.
+    pub fn defense(&self) -> i32 {
+        self.slots.values().map(Item::defense).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equipment_slots() {
+        let mut equipment = Equipment::new();
+        let sword = Item::new_weapon("Sword", "", 0, 5);
+        assert_eq!(equipment.equip(sword.clone()), Ok(None));
+        assert_eq!(equipment.equip(Item::new_weapon("Dagger", "", 0, 2).with_slot(Slot::OffHand)), Ok(None));
+        assert_eq!(equipment.equip(Item::new_armor("Helmet", "", 0, 3).with_slot(Slot::Head)), Ok(None));
+        assert_eq!(equipment.equip(Item::new_armor("Mail", "", 0, 4)), Ok(None));
+        assert_eq!(equipment.attack(), 7);
+        assert_eq!(equipment.defense(), 7);
+        let axe = Item::new_weapon("Axe", "", 0, 8);
+        assert_eq!(equipment.equip(axe.clone()), Ok(Some(sword)));
+        assert_eq!(equipment.get(Slot::MainHand), Some(&axe));
+        let slots: Vec<Slot> = equipment.iter().map(|(slot, _)| slot).collect();
+        assert_eq!(slots, vec![Slot::MainHand, Slot::OffHand, Slot::Head, Slot::Body]);
+        assert_eq!(equipment.unequip(Slot::Head).map(|item| item.defense()), Some(3));
+        assert_eq!(equipment.unequip(Slot::Head), None);
+        assert_eq!(equipment.defense(), 4);
+    }
+
+    #[test]
+    fn test_equipment_refuses() {
+        let mut equipment = Equipment::new();
+        assert_eq!(equipment.equip(Item::new_gold(5)), Err(EquipError::NotEquipment("Gold".to_string())));
+        let error = equipment.equip(Item::new_armor("Mail", "", 0, 4).with_slot(Slot::MainHand));
+        assert_eq!(error, Err(EquipError::WrongSlot { item: "Mail".to_string(), slot: Slot::MainHand }));
+        assert_eq!(error.unwrap_err().to_string(), "The Mail does not fit in the main hand slot.");
+        assert!(equipment.equip(Item::new_weapon("Sword", "", 0, 5).with_slot(Slot::Ring)).is_err());
+        assert_eq!(equipment.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_slot_names() {
+        for slot in Slot::ALL {
+            assert_eq!(Slot::from_name(slot.name()), Some(slot));
+        }
+        assert_eq!(Slot::from_name("feet"), None);
+        assert_eq!(Slot::OffHand.to_string(), "off hand");
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt;
+
+use crate::map::item::{Item, ItemType};
+
+/// Some units of an item, taking one slot of an `Inventory`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Stack {
+    item: Item,
+    count: u32,
+}
+
+impl Stack {
+    pub fn item(&self) -> &Item {
+        &self.item
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// Error returned when items do not fit in an `Inventory`; nothing was
+/// added.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct InventoryFull;
+
+impl fmt::Display for InventoryFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the bag is full")
+    }
+}
+
+impl std::error::Error for InventoryFull {}
+
+/// What the player carries: stacks of items, each taking a slot, up to a
+/// capacity. Items are told apart by name. Potions, scrolls and gold pile up
+/// on a single stack, gold counting coins; weapons and armor each take a
+/// slot of their own.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Inventory {
+    stacks: Vec<Stack>,
+    capacity: usize,
+}
+
+impl Default for Inventory {
+    fn default() -> Inventory {
+        Inventory::with_capacity(Inventory::DEFAULT_CAPACITY)
+    }
+}
+
+impl Inventory {
+    /// Slots of a player's bag unless the content says otherwise.
+    pub const DEFAULT_CAPACITY: usize = 20;
+
+    pub fn new() -> Inventory {
+        Inventory::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Inventory {
+        Inventory { stacks: vec![], capacity }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of slots in use.
+    pub fn len(&self) -> usize {
+        self.stacks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stacks.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Stack> {
+        self.stacks.iter()
+    }
+
+    /// The stack in slot `index`.
+    pub fn get(&self, index: usize) -> Option<&Stack> {
+        self.stacks.get(index)
+    }
+
+    fn is_stackable(item: &Item) -> bool {
+        matches!(item.type_(), ItemType::Gold(_) | ItemType::Effect(_) | ItemType::Exp(_))
+    }
+
+    /// Add `count` units of `item`, or nothing at all if they do not fit. A
+    /// pile of gold adds its value in coins, so a worthless one adds nothing.
+    pub fn add(&mut self, item: Item, count: u32) -> Result<(), InventoryFull> {
+        let (item, count) = match item.type_() {
+            ItemType::Gold(_) => (Item::new_gold(1), count.saturating_mul(item.value().max(0) as u32)),
+            _ => (item, count),
+        };
+        if count == 0 {
+            return Ok(());
+        }
+        if Inventory::is_stackable(&item) {
+            if let Some(stack) = self.stacks.iter_mut().find(|stack| stack.item.name() == item.name()) {
+                stack.count = stack.count.saturating_add(count);
+                return Ok(());
+            }
+            if self.stacks.len() >= self.capacity {
+                return Err(InventoryFull);
+            }
+            self.stacks.push(Stack { item, count });
+        } else {
+            if self.stacks.len() + count as usize > self.capacity {
+                return Err(InventoryFull);
+            }
+            for _ in 0..count {
+                self.stacks.push(Stack { item: item.clone(), count: 1 });
+            }
+        }
+        Ok(())
+    }
+
+    /// Take one unit out of slot `index`, freeing the slot once it is empty.
+    pub fn remove(&mut self, index: usize) -> Option<Item> {
+        let stack = self.stacks.get_mut(index)?;
+        stack.count -= 1;
+        if stack.count == 0 {
+            Some(self.stacks.remove(index).item)
+        } else {
+            Some(stack.item.clone())
+        }
+    }
+
+    /// How many units of the item called `name` there are, over every slot.
+    pub fn count(&self, name: &str) -> u32 {
+        self.stacks.iter().filter(|stack| stack.item.name() == name).map(|stack| stack.count).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::item::Effect;
+
+    fn heal() -> Item {
+        Item::new_effect("heal", "", 10, Effect::Heal(10))
+    }
+
+    #[test]
+    fn test_inventory_stacks() {
+        let mut inventory = Inventory::new();
+        inventory.add(heal(), 2).unwrap();
+        inventory.add(Item::new_gold(10), 1).unwrap();
+        inventory.add(heal(), 1).unwrap();
+        inventory.add(Item::new_gold(5), 2).unwrap();
+        inventory.add(Item::new_weapon("Sword", "", 0, 5), 2).unwrap();
+        assert_eq!(inventory.len(), 4);
+        assert_eq!(inventory.count("heal"), 3);
+        assert_eq!(inventory.count("Gold"), 20);
+        assert_eq!(inventory.count("Sword"), 2);
+        assert_eq!(inventory.get(1).map(Stack::item), Some(&Item::new_gold(1)));
+        let counts: Vec<u32> = inventory.iter().map(Stack::count).collect();
+        assert_eq!(counts, vec![3, 20, 1, 1]);
+    }
+
+    #[test]
+    fn test_inventory_remove() {
+        let mut inventory = Inventory::new();
+        inventory.add(heal(), 2).unwrap();
+        inventory.add(Item::new_weapon("Sword", "", 0, 5), 1).unwrap();
+        assert_eq!(inventory.remove(0), Some(heal()));
+        assert_eq!(inventory.count("heal"), 1);
+        assert_eq!(inventory.remove(0), Some(heal()));
+        assert_eq!(inventory.len(), 1);
+        assert_eq!(inventory.remove(1), None);
+        assert_eq!(inventory.remove(0).map(|item| item.name().to_string()), Some("Sword".to_string()));
+        assert!(inventory.is_empty());
+    }
+
+    #[test]
+    fn test_inventory_capacity() {
+        let mut inventory = Inventory::with_capacity(2);
+        inventory.add(heal(), 1).unwrap();
+        assert_eq!(inventory.add(Item::new_weapon("Sword", "", 0, 5), 2), Err(InventoryFull));
+        assert_eq!(inventory.len(), 1);
+        inventory.add(Item::new_weapon("Sword", "", 0, 5), 1).unwrap();
+        assert_eq!(inventory.add(Item::new_gold(3), 1), Err(InventoryFull));
+        inventory.add(heal(), 4).unwrap();
+        assert_eq!(inventory.count("heal"), 5);
+        assert_eq!(inventory.capacity(), 2);
+    }
+
+    #[test]
+    fn test_inventory_worthless_gold() {
+        let mut inventory = Inventory::with_capacity(1);
+        assert_eq!(inventory.add(Item::new_gold(0), 3), Ok(()));
+        assert_eq!(inventory.add(Item::new_gold(-5), 1), Ok(()));
+        assert!(inventory.is_empty());
+        inventory.add(heal(), 1).unwrap();
+        assert_eq!(inventory.add(Item::new_gold(0), 1), Ok(()));
+        assert_eq!(inventory.len(), 1);
+    }
+}